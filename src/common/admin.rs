@@ -0,0 +1,93 @@
+/// Este módulo implementa el puerto de administración de robots y pantallas.
+///
+/// Cada nodo atiende en su puerto de administración los `AdminCommand` que envía la consola
+/// `heladeria-ctl`, con el mismo formato que el resto de los mensajes (`MessageType` serializado
+/// con bincode): por cada conexión se lee un `AdminRequest` y se responde un `AdminResponse`.
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+use crate::common::constants::{ADMIN_TIMEOUT_MS, BUFFER_SIZE};
+use crate::common::messages::{send_message, take_message, AdminCommand, AdminReply, MessageType};
+use crate::common::recorder::{self, Peer};
+use crate::{log_error, log_info, log_warn};
+
+/// Atiende en `127.0.0.1:<port>`, desde otro hilo, los comandos de la consola de administración
+/// con `handler`. Si el puerto está ocupado, lo informa y el nodo sigue sin puerto de
+/// administración.
+pub fn serve<F>(port: u16, handler: F)
+where
+    F: Fn(AdminCommand) -> AdminReply + Send + 'static,
+{
+    let address = format!("127.0.0.1:{}", port);
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log_error!(
+                "No se pudo abrir el puerto de administración {}: {}",
+                address,
+                e
+            );
+            return;
+        }
+    };
+    log_info!("Administración en {}", address);
+    thread::spawn(move || {
+        recorder::set_peer(Peer::Console);
+        for mut stream in listener.incoming().map_while(Result::ok) {
+            let reply = match read_message(&mut stream) {
+                Ok(MessageType::AdminRequest(command)) => {
+                    log_info!("Comando de administración: {:?}", command);
+                    handler(command)
+                }
+                Ok(message) => AdminReply::Error(format!("Mensaje inesperado: {:?}", message)),
+                Err(e) => {
+                    log_warn!("Pedido de administración inválido: {}", e);
+                    continue;
+                }
+            };
+            let _ = write_message(&mut stream, &MessageType::AdminResponse(reply));
+        }
+    });
+}
+
+/// Envía `command` al puerto de administración `address` y devuelve la respuesta del nodo.
+pub fn request(address: &str, command: AdminCommand) -> Result<AdminReply, String> {
+    let mut stream = TcpStream::connect(address)
+        .map_err(|e| format!("No se pudo conectar a {}: {}", address, e))?;
+    write_message(&mut stream, &MessageType::AdminRequest(command))?;
+    match read_message(&mut stream)? {
+        MessageType::AdminResponse(reply) => Ok(reply),
+        message => Err(format!(
+            "Respuesta inesperada de {}: {:?}",
+            address, message
+        )),
+    }
+}
+
+/// Escribe un mensaje en el socket.
+fn write_message(stream: &mut TcpStream, message: &MessageType) -> Result<(), String> {
+    send_message(stream, message).map_err(|e| e.to_string())
+}
+
+/// Lee del socket el primer mensaje completo, esperando a lo sumo `ADMIN_TIMEOUT_MS`.
+fn read_message(stream: &mut TcpStream) -> Result<MessageType, String> {
+    stream
+        .set_read_timeout(Some(Duration::from_millis(ADMIN_TIMEOUT_MS)))
+        .map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; BUFFER_SIZE];
+    let mut read_buffer = Vec::new();
+    loop {
+        match stream.read(&mut buffer) {
+            Ok(0) => return Err("La conexión se cerró antes de recibir el mensaje".to_string()),
+            Ok(n) => {
+                read_buffer.extend_from_slice(&buffer[..n]);
+                if let Some(message) = take_message(&mut read_buffer) {
+                    return Ok(message);
+                }
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+}
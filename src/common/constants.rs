@@ -1,6 +1,30 @@
 /// Dirección del Gateway.
 pub const GATEWAY_ADDRESS: &str = "127.0.0.1:6000";
 
+/// Dirección del Gateway de respaldo, que reemplaza al primario si este se cae.
+pub const GATEWAY_STANDBY_ADDRESS: &str = "127.0.0.1:6001";
+
+/// Espera inicial, en milisegundos, antes de reintentar la conexión con el gateway.
+pub const GATEWAY_RETRY_INITIAL_DELAY_MS: u64 = 100;
+
+/// Espera máxima, en milisegundos, entre reintentos de conexión con el gateway.
+pub const GATEWAY_RETRY_MAX_DELAY_MS: u64 = 5000;
+
+/// Tiempo, en milisegundos, durante el que el gateway de respaldo reintenta conectarse al
+/// primario al iniciar. Si no lo logra, termina sin reemplazarlo.
+pub const STANDBY_CONNECT_GRACE_MS: u64 = 10000;
+
+/// Espera, en milisegundos, entre los intentos del gateway de respaldo de conectarse al primario.
+pub const STANDBY_CONNECT_RETRY_MS: u64 = 200;
+
+/// Cantidad máxima de líneas del log pendientes de enviar a un gateway de respaldo. Si se
+/// acumulan más, el respaldo se considera demasiado lento y se deja de replicar en él.
+pub const REPLICA_QUEUE_CAPACITY: usize = 1024;
+
+/// Tiempo máximo, en milisegundos, para enviar una línea del log a un gateway de respaldo antes
+/// de dejar de replicar en él.
+pub const REPLICA_WRITE_TIMEOUT_MS: u64 = 2000;
+
 /// Tamaño del búfer.
 pub const BUFFER_SIZE: usize = 4096;
 
@@ -15,3 +39,60 @@ pub const FIRST_BATCH: usize = 1;
 
 /// Cantidad de pedidos que lanza la pantalla por cada pedido terminado
 pub const MULTIPLICATION_BATCH: usize = 2;
+
+/// Intervalo, en milisegundos, con el que una pantalla con `--queue-target` revisa si se liberó
+/// lugar en la cola de pedidos de los robots.
+pub const QUEUE_CHECK_INTERVAL_MS: u64 = 500;
+
+/// Plazo, en segundos desde la autorización, para entregar un pedido, si no se indica otro con
+/// `--deadline` o en el propio pedido.
+pub const DEFAULT_ORDER_DEADLINE_SECS: u64 = 300;
+
+/// Intervalo, en milisegundos, con el que una pantalla revisa si se venció el plazo de sus pedidos.
+pub const DEADLINE_CHECK_INTERVAL_MS: u64 = 500;
+
+/// Intervalo, en milisegundos, con el que una pantalla revisa el directorio de pedidos vigilado.
+pub const WATCH_INTERVAL_MS: u64 = 500;
+
+/// Espera, en milisegundos, a partir de la cual un robot avisa que no le llega el token de un
+/// sabor que necesita para preparar un pedido.
+pub const TOKEN_WAIT_WARNING_MS: u64 = 10000;
+
+/// Intervalo, en milisegundos, con el que un robot que espera un token revisa si superó la espera
+/// máxima.
+pub const TOKEN_WAIT_CHECK_INTERVAL_MS: u64 = 500;
+
+/// Intervalo, en milisegundos, con el que cada robot envía al líder sus estadísticas de tokens.
+pub const TOKEN_STATS_REPORT_INTERVAL_MS: u64 = 5000;
+
+/// Precio por gramo de helado, usado por el gateway para calcular el monto autorizado.
+pub const PRICE_PER_GRAM: u32 = 1;
+
+/// Puerto de métricas del robot con ID 0. Cada robot expone sus métricas en este puerto más su ID.
+pub const ROBOT_METRICS_BASE_PORT: u16 = 9100;
+
+/// Puerto de métricas de la pantalla con ID 0. Cada pantalla expone sus métricas en este puerto
+/// más su ID.
+pub const SCREEN_METRICS_BASE_PORT: u16 = 9200;
+
+/// Puerto de métricas del gateway.
+pub const GATEWAY_METRICS_PORT: u16 = 9300;
+
+/// Puerto de métricas del gateway de respaldo.
+pub const GATEWAY_STANDBY_METRICS_PORT: u16 = 9301;
+
+/// Puerto de administración del robot con ID 0. Cada robot atiende a la consola de administración
+/// en este puerto más su ID.
+pub const ROBOT_ADMIN_BASE_PORT: u16 = 11000;
+
+/// Puerto de administración de la pantalla con ID 0. Cada pantalla atiende a la consola de
+/// administración en este puerto más su ID.
+pub const SCREEN_ADMIN_BASE_PORT: u16 = 4000;
+
+/// Tiempo máximo, en milisegundos, que la consola de administración espera la respuesta de un
+/// nodo.
+pub const ADMIN_TIMEOUT_MS: u64 = 5000;
+
+/// Tiempo máximo, en milisegundos, que la API HTTP de la pantalla espera para leer un pedido o
+/// escribir su respuesta, para que un cliente inactivo no la bloquee.
+pub const HTTP_TIMEOUT_MS: u64 = 5000;
@@ -0,0 +1,338 @@
+/// Este módulo implementa el registro estructurado de eventos de robots, pantallas y gateway.
+///
+/// Cada proceso inicializa el registro con `init`, indicando su componente y su ID. Los eventos
+/// se registran con las macros `log_debug!`, `log_info!`, `log_warn!` y `log_error!`, que pueden
+/// llevar el ID del pedido (el que asigna el gateway al autorizarlo, compartido por todos los
+/// procesos), el líder involucrado y el término de la elección que lo eligió:
+///
+/// ```ignore
+/// log_info!(order = order_id; "Preparando pedido");
+/// log_warn!(leader = new_leader_id, term = term; "Nuevo líder en la red");
+/// ```
+///
+/// El nivel mínimo se elige con la variable de entorno `HELADERIA_LOG` (`debug`, `info`, `warn` o
+/// `error`; `info` si no se indica) y el formato con `HELADERIA_LOG_FORMAT`: `human` (el
+/// predeterminado) o `json`, con un objeto JSON por línea. Los eventos `warn` y `error` se
+/// escriben en la salida de errores y el resto en la salida estándar.
+use serde::Serialize;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Variable de entorno que indica el nivel mínimo de los eventos registrados.
+pub const LOG_LEVEL_ENV: &str = "HELADERIA_LOG";
+/// Variable de entorno que indica el formato de los eventos registrados.
+pub const LOG_FORMAT_ENV: &str = "HELADERIA_LOG_FORMAT";
+
+/// Nivel de un evento.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "debug" => Ok(Level::Debug),
+            "info" => Ok(Level::Info),
+            "warn" => Ok(Level::Warn),
+            "error" => Ok(Level::Error),
+            _ => Err(format!("Nivel de log inválido: {}", input)),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let level = match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        };
+        // Se respeta el ancho pedido, para alinear los eventos en formato legible
+        f.pad(level)
+    }
+}
+
+/// Formato de los eventos registrados.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Una línea legible por evento.
+    Human,
+    /// Un objeto JSON por línea.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Formato de log inválido: {}", input)),
+        }
+    }
+}
+
+/// Campos opcionales de un evento.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fields {
+    /// ID del pedido asignado por el gateway.
+    pub order_id: Option<u64>,
+    /// ID del líder involucrado en el evento.
+    pub leader: Option<u64>,
+    /// Término del líder: cantidad de cambios de líder que vio el nodo.
+    pub term: Option<u64>,
+}
+
+/// Configuración del registro del proceso.
+#[derive(Debug)]
+struct Logger {
+    component: &'static str,
+    node_id: Option<usize>,
+    level: Level,
+    format: LogFormat,
+}
+
+/// Evento serializado en formato JSON.
+#[derive(Serialize)]
+struct Record<'a> {
+    timestamp_ms: u128,
+    level: Level,
+    component: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node_id: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    order_id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    leader: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    term: Option<u64>,
+    message: &'a str,
+}
+
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Inicializa el registro del proceso con su componente (`robot`, `screen`, `gateway`) y su ID,
+/// leyendo el nivel y el formato de las variables de entorno. Solo tiene efecto la primera vez.
+pub fn init(component: &'static str, node_id: Option<usize>) {
+    let level = read_env(LOG_LEVEL_ENV, Level::Info);
+    let format = read_env(LOG_FORMAT_ENV, LogFormat::Human);
+    let _ = LOGGER.set(Logger {
+        component,
+        node_id,
+        level,
+        format,
+    });
+}
+
+/// Lee y valida la variable de entorno `name`, usando `default` si no está definida.
+fn read_env<T: FromStr<Err = String>>(name: &str, default: T) -> T {
+    match env::var(name) {
+        Ok(value) => value.parse().unwrap_or_else(|e| {
+            eprintln!("{}. Se usa el valor predeterminado", e);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// Devuelve el registro del proceso. Si no se inicializó, registra los eventos de nivel `info`
+/// o superior en formato legible, sin componente.
+fn logger() -> &'static Logger {
+    LOGGER.get_or_init(|| Logger {
+        component: "heladeria",
+        node_id: None,
+        level: Level::Info,
+        format: LogFormat::Human,
+    })
+}
+
+/// Registra un evento. Se usa a través de las macros `log_*!`.
+pub fn log(level: Level, fields: Fields, message: fmt::Arguments) {
+    let logger = logger();
+    if level < logger.level {
+        return;
+    }
+    let message = message.to_string();
+    let line = match logger.format {
+        LogFormat::Human => human_line(logger, level, fields, &message),
+        LogFormat::Json => json_line(logger, level, fields, message),
+    };
+    if level >= Level::Warn {
+        eprintln!("{}", line);
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Arma la línea JSON de un evento, con la hora actual.
+fn json_line(logger: &Logger, level: Level, fields: Fields, message: String) -> String {
+    let timestamp_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0);
+    serde_json::to_string(&Record {
+        timestamp_ms,
+        level,
+        component: logger.component,
+        node_id: logger.node_id,
+        order_id: fields.order_id,
+        leader: fields.leader,
+        term: fields.term,
+        message: &message,
+    })
+    .unwrap_or(message)
+}
+
+/// Arma la línea legible de un evento: `INFO  robot 1 [pedido 3] Preparando pedido`.
+fn human_line(logger: &Logger, level: Level, fields: Fields, message: &str) -> String {
+    let mut line = format!("{:<5} {}", level, logger.component);
+    if let Some(node_id) = logger.node_id {
+        line.push_str(&format!(" {}", node_id));
+    }
+    if let Some(order_id) = fields.order_id {
+        line.push_str(&format!(" [pedido {}]", order_id));
+    }
+    if let Some(leader) = fields.leader {
+        line.push_str(&format!(" [líder {}]", leader));
+    }
+    if let Some(term) = fields.term {
+        line.push_str(&format!(" [término {}]", term));
+    }
+    line.push(' ');
+    line.push_str(message);
+    line
+}
+
+/// Registra un evento con el nivel indicado y, opcionalmente, el pedido, el líder y el término
+/// involucrados (`order = ...`, `leader = ...`, `term = ...`, en cualquier combinación).
+#[macro_export]
+macro_rules! log_event {
+    ($level:expr; $($field:ident = $value:expr),+; $($arg:tt)+) => {{
+        let mut fields = $crate::common::logger::Fields::default();
+        $($crate::log_field!(fields.$field = $value);)+
+        $crate::common::logger::log($level, fields, format_args!($($arg)+))
+    }};
+    ($level:expr; $($arg:tt)+) => {
+        $crate::common::logger::log(
+            $level,
+            $crate::common::logger::Fields::default(),
+            format_args!($($arg)+),
+        )
+    };
+}
+
+/// Asigna un campo de un evento. Se usa a través de `log_event!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! log_field {
+    ($fields:ident.order = $value:expr) => {
+        $fields.order_id = Some($value as u64)
+    };
+    ($fields:ident.leader = $value:expr) => {
+        $fields.leader = Some($value as u64)
+    };
+    ($fields:ident.term = $value:expr) => {
+        $fields.term = Some($value as u64)
+    };
+}
+
+/// Registra un evento de nivel `debug`.
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)+) => { $crate::log_event!($crate::common::logger::Level::Debug; $($arg)+) };
+}
+
+/// Registra un evento de nivel `info`.
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => { $crate::log_event!($crate::common::logger::Level::Info; $($arg)+) };
+}
+
+/// Registra un evento de nivel `warn`.
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)+) => { $crate::log_event!($crate::common::logger::Level::Warn; $($arg)+) };
+}
+
+/// Registra un evento de nivel `error`.
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => { $crate::log_event!($crate::common::logger::Level::Error; $($arg)+) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn robot_logger() -> Logger {
+        Logger {
+            component: "robot",
+            node_id: Some(1),
+            level: Level::Info,
+            format: LogFormat::Human,
+        }
+    }
+
+    fn election_fields() -> Fields {
+        Fields {
+            order_id: None,
+            leader: Some(2),
+            term: Some(3),
+        }
+    }
+
+    #[test]
+    fn human_line_shows_leader_and_term() {
+        let line = human_line(
+            &robot_logger(),
+            Level::Info,
+            election_fields(),
+            "Nuevo lider",
+        );
+        assert_eq!(line, "INFO  robot 1 [líder 2] [término 3] Nuevo lider");
+    }
+
+    #[test]
+    fn json_line_carries_term_only_when_present() {
+        let line = json_line(
+            &robot_logger(),
+            Level::Warn,
+            election_fields(),
+            "Nuevo lider".to_string(),
+        );
+        let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(record["level"], "WARN");
+        assert_eq!(record["leader"], 2);
+        assert_eq!(record["term"], 3);
+        assert!(record.get("order_id").is_none());
+
+        let line = json_line(
+            &robot_logger(),
+            Level::Info,
+            Fields::default(),
+            "Preparando".to_string(),
+        );
+        let record: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert!(record.get("term").is_none());
+    }
+
+    #[test]
+    fn levels_and_formats_parse_case_insensitively() {
+        assert_eq!("DEBUG".parse::<Level>(), Ok(Level::Debug));
+        assert_eq!("Json".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert!("verbose".parse::<Level>().is_err());
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
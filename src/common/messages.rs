@@ -3,9 +3,18 @@
 /// Contiene estructuras y enumeraciones serializables/deserializables que representan diversos eventos y datos intercambiados entre las entidades del sistema distribuido.
 ///
 /// Las funciones `serialize_message` y `deserialize_message` permiten convertir estos tipos de mensajes en bytes y viceversa, facilitando la comunicación a través de la red.
+/// `send_message` escribe un mensaje en el socket.
+/// `take_messages` separa los mensajes que llegan juntos (o partidos) en una misma lectura del socket.
 use super::flavors::IceCreamFlavor;
-use bincode::{deserialize, serialize};
+use super::recorder::{self, Direction};
+use crate::log_error;
+use bincode::{deserialize, deserialize_from, serialize, ErrorKind};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, Cursor, Read, Write};
+use std::net::TcpStream;
+use std::str::FromStr;
 use std::{collections::HashMap, time::SystemTime};
 
 /// Enumeración que define diferentes tipos de mensajes utilizados en la aplicación.
@@ -35,6 +44,13 @@ pub enum MessageType {
     NewLeaderIntroduction(usize),
     UpdateRobotLeader(usize),
     UpdateScreenLeader(usize),
+    StockSnapshot(StockSnapshot),
+    CancelOrder(CancelOrder),
+    TokenStats(TokenStatsReport),
+    DrainRobot(DrainRobot),
+    Restock(Restock),
+    AdminRequest(AdminCommand),
+    AdminResponse(AdminReply),
 }
 /// Estructura que representa un robot que ha dejado de funcionar.
 
@@ -56,7 +72,11 @@ pub struct DeadScreen {
 pub struct OrderScreen {
     pub sender_id: usize,
     pub order_id: usize,
+    /// Identificador del pedido en la pantalla que lo tomó, si lo tiene.
+    pub client_id: Option<String>,
     pub order_details: HashMap<IceCreamFlavor, i32>,
+    /// Plazo, en milisegundos desde la autorización, para entregar el pedido.
+    pub deadline_ms: u64,
 }
 /// Estructura que representa un pedido genérico.
 
@@ -71,6 +91,209 @@ pub struct Order {
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Abort {
     pub order_id: usize,
+    pub reason: AbortReason,
+}
+
+/// Estructura que representa la cancelación de un pedido cuyo plazo venció, enviada por la
+/// pantalla que lo tomó al resto del anillo y, desde la pantalla líder, al robot líder.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CancelOrder {
+    pub sender_id: usize,
+    pub order_id: usize,
+}
+
+/// Estructura con el stock disponible según el robot líder, que este publica a la pantalla líder
+/// y se replica en el anillo de pantallas.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StockSnapshot {
+    pub stock: HashMap<IceCreamFlavor, i32>,
+    pub timestamp: SystemTime,
+    /// Cantidad de pedidos asignados a los robots que todavía no se entregaron.
+    pub queue_depth: usize,
+}
+
+impl StockSnapshot {
+    /// Verifica si el stock de la foto alcanza para los gramos de cada sabor indicados.
+    pub fn has_enough_stock(&self, required: &HashMap<IceCreamFlavor, i32>) -> bool {
+        required
+            .iter()
+            .all(|(flavor, &grams)| self.stock.get(flavor).is_some_and(|&stock| stock >= grams))
+    }
+}
+
+/// Estadísticas de circulación del token de un sabor medidas por un robot: las vueltas del token
+/// por el anillo (el tiempo entre dos llegadas seguidas al robot) y las esperas del robot hasta
+/// conseguirlo para preparar un pedido.
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct FlavorTokenStats {
+    pub round_trips: u64,
+    pub total_round_trip_ms: u64,
+    pub max_round_trip_ms: u64,
+    pub waits: u64,
+    pub total_wait_ms: u64,
+    pub max_wait_ms: u64,
+}
+
+impl FlavorTokenStats {
+    /// Registra una vuelta del token por el anillo.
+    pub fn add_round_trip(&mut self, round_trip_ms: u64) {
+        self.round_trips += 1;
+        self.total_round_trip_ms = self.total_round_trip_ms.saturating_add(round_trip_ms);
+        self.max_round_trip_ms = self.max_round_trip_ms.max(round_trip_ms);
+    }
+
+    /// Registra una espera del token para preparar un pedido.
+    pub fn add_wait(&mut self, wait_ms: u64) {
+        self.waits += 1;
+        self.total_wait_ms = self.total_wait_ms.saturating_add(wait_ms);
+        self.max_wait_ms = self.max_wait_ms.max(wait_ms);
+    }
+
+    /// Devuelve la duración promedio de una vuelta del token, o `None` si no se midió ninguna.
+    pub fn avg_round_trip_ms(&self) -> Option<u64> {
+        self.total_round_trip_ms.checked_div(self.round_trips)
+    }
+
+    /// Devuelve la espera promedio del token, o `None` si no se midió ninguna.
+    pub fn avg_wait_ms(&self) -> Option<u64> {
+        self.total_wait_ms.checked_div(self.waits)
+    }
+}
+
+/// Estructura con las estadísticas de tokens de un robot, que este envía periódicamente al
+/// robot líder por el anillo.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TokenStatsReport {
+    pub sender_id: usize,
+    pub stats: HashMap<IceCreamFlavor, FlavorTokenStats>,
+}
+
+/// Estructura que indica al anillo de robots que deje de asignarle pedidos nuevos al robot
+/// `robot_id` o, si `drained` es `false`, que vuelva a asignárselos.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DrainRobot {
+    pub sender_id: usize,
+    pub robot_id: usize,
+    pub drained: bool,
+}
+
+/// Estructura que pide al robot líder reponer `grams` gramos de un sabor.
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Restock {
+    pub flavor: IceCreamFlavor,
+    pub grams: i32,
+}
+
+/// Comando que la consola de administración envía al puerto de administración de un nodo.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum AdminCommand {
+    /// Pide el estado del nodo.
+    Status,
+    /// Deja de asignarle pedidos al robot indicado o, si es `false`, vuelve a asignárselos.
+    Drain(usize, bool),
+    /// Repone gramos de un sabor.
+    Restock(IceCreamFlavor, i32),
+    /// Fuerza una elección de líder en el anillo de robots.
+    ForceElection,
+}
+
+/// Tipo de nodo que responde a la consola de administración.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Robot,
+    Screen,
+}
+
+impl fmt::Display for NodeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeKind::Robot => write!(f, "robot"),
+            NodeKind::Screen => write!(f, "screen"),
+        }
+    }
+}
+
+/// Estado de un nodo, tal como lo ve el propio nodo.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct NodeStatus {
+    pub kind: NodeKind,
+    pub id: usize,
+    /// Líder del anillo del nodo.
+    pub leader_id: usize,
+    pub is_leader: bool,
+    /// Líder del otro anillo: la pantalla líder para un robot y el robot líder para una pantalla.
+    pub peer_leader_id: usize,
+    /// Conexiones del anillo: cada nodo y el nodo al que le envía mensajes.
+    pub ring: BTreeMap<usize, usize>,
+    /// Sabores cuyo token tiene el nodo en este momento.
+    pub tokens: Vec<IceCreamFlavor>,
+    /// Stock de cada sabor, si el nodo lo conoce.
+    pub stock: Option<HashMap<IceCreamFlavor, i32>>,
+    /// IDs de los pedidos pendientes, por robot o por pantalla.
+    pub orders: BTreeMap<usize, Vec<usize>>,
+    /// Robots a los que no se les asignan pedidos nuevos.
+    pub drained: Vec<usize>,
+}
+
+/// Respuesta de un nodo a un comando de la consola de administración.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum AdminReply {
+    Status(NodeStatus),
+    /// El comando se aplicó; incluye una descripción de lo hecho.
+    Done(String),
+    /// El comando no se pudo aplicar.
+    Error(String),
+}
+
+/// Motivo por el que se cancela un pedido.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AbortReason {
+    /// No hay stock suficiente de alguno de los sabores del pedido.
+    OutOfStock,
+    /// Se cayó el robot que preparaba el pedido y no se lo pudo reasignar.
+    RobotLost,
+    /// Se venció el plazo del pedido antes de que los robots lo entregaran.
+    DeadlineExpired,
+}
+
+impl AbortReason {
+    /// Devuelve una descripción del motivo para mostrar al cliente.
+    pub fn description(&self) -> &'static str {
+        match self {
+            AbortReason::OutOfStock => "sin stock suficiente",
+            AbortReason::RobotLost => "se cayó el robot que lo preparaba",
+            AbortReason::DeadlineExpired => "se venció el plazo de entrega",
+        }
+    }
+}
+
+impl FromStr for AbortReason {
+    type Err = ();
+    fn from_str(input: &str) -> Result<AbortReason, Self::Err> {
+        match input {
+            "OUT_OF_STOCK" => Ok(AbortReason::OutOfStock),
+            "ROBOT_LOST" => Ok(AbortReason::RobotLost),
+            "DEADLINE_EXPIRED" => Ok(AbortReason::DeadlineExpired),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Formatea el motivo como aparece en el protocolo y en el log del gateway.
+impl fmt::Display for AbortReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AbortReason::OutOfStock => write!(f, "OUT_OF_STOCK"),
+            AbortReason::RobotLost => write!(f, "ROBOT_LOST"),
+            AbortReason::DeadlineExpired => write!(f, "DEADLINE_EXPIRED"),
+        }
+    }
 }
 /// Estructura que representa una confirmación de un pedido completado.
 
@@ -118,7 +341,8 @@ pub struct OrderData {
 pub struct NewLeader {
     pub sender_id: usize,
     pub new_leader_id: usize,
-    pub dead_leader_id: usize,
+    /// Líder caído al que reemplaza, o `None` si la elección se forzó con el líder vivo.
+    pub dead_leader_id: Option<usize>,
 }
 
 /// Estructura que representa un mensaje de elección de líder.
@@ -127,7 +351,8 @@ pub struct NewLeader {
 pub struct Election {
     pub sender_id: usize,
     pub current_candidate_id: usize,
-    pub dead_leader_id: usize,
+    /// Líder caído que origina la elección, o `None` si se forzó con el líder vivo.
+    pub dead_leader_id: Option<usize>,
 }
 
 /// Estructura que representa un mensaje de todos conectados.
@@ -191,19 +416,133 @@ pub fn serialize_message(message: &MessageType) -> Option<Vec<u8>> {
     match serialize(message) {
         Ok(bytes) => Some(bytes),
         Err(err) => {
-            eprintln!("Serialization error: {:?}", err);
+            log_error!("Serialization error: {:?}", err);
             None
         }
     }
 }
 
+/// Serializa un mensaje y lo escribe en el socket. El mensaje se graba como enviado solo si se
+/// escribió entero, así que uno que se vuelve a encolar tras un error se graba una sola vez.
+pub fn send_message(stream: &mut impl Write, message: &MessageType) -> io::Result<()> {
+    let serialized = serialize_message(message)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Serialization error"))?;
+    stream.write_all(&serialized)?;
+    recorder::record(Direction::Sent, &serialized);
+    Ok(())
+}
+
 /// Deserializa bytes en un mensaje `MessageType` usando bincode.
 pub fn deserialize_message(bytes: &[u8]) -> Option<MessageType> {
     match deserialize(bytes) {
         Ok(message) => Some(message),
         Err(err) => {
-            eprintln!("Deserialization error: {:?}", err);
+            log_error!("Deserialization error: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Extrae de `read_buffer` todos los mensajes completos recibidos, en orden, y deja en él los
+/// bytes de un mensaje que todavía no llegó entero. Una misma lectura del socket puede traer
+/// varios mensajes seguidos o solo una parte de uno.
+pub fn take_messages(read_buffer: &mut Vec<u8>) -> Vec<MessageType> {
+    let mut messages = Vec::new();
+    while let Some(message) = take_message(read_buffer) {
+        messages.push(message);
+    }
+    messages
+}
+
+/// Extrae de `read_buffer` el primer mensaje completo recibido, si lo hay, dejando en él los
+/// bytes que le siguen. El mensaje se graba como recibido si el proceso está grabando.
+pub fn take_message(read_buffer: &mut Vec<u8>) -> Option<MessageType> {
+    let mut cursor = Cursor::new(&read_buffer[..]);
+    match deserialize_from::<_, MessageType>(&mut cursor) {
+        Ok(message) => {
+            let consumed = cursor.position() as usize;
+            recorder::record(Direction::Received, &read_buffer[..consumed]);
+            read_buffer.drain(..consumed);
+            Some(message)
+        }
+        Err(err) => {
+            if let ErrorKind::Io(ref e) = *err {
+                if e.kind() == std::io::ErrorKind::UnexpectedEof {
+                    return None;
+                }
+            }
+            // Los bytes no forman un mensaje válido: se descartan para no trabar la conexión
+            log_error!("Deserialization error: {:?}", err);
+            read_buffer.clear();
             None
         }
     }
 }
+
+/// Devuelve un lector que entrega primero los bytes ya leídos de `socket` que quedaron en
+/// `read_buffer` (por ejemplo, los que llegaron junto con el mensaje de introducción) y luego
+/// continúa leyendo del socket.
+pub fn resume_reading(read_buffer: Vec<u8>, socket: TcpStream) -> impl Read {
+    Cursor::new(read_buffer).chain(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(message: &MessageType) -> Vec<u8> {
+        serialize_message(message).unwrap()
+    }
+
+    #[test]
+    fn several_messages_in_one_read_are_all_taken() {
+        let mut read_buffer = bytes(&MessageType::UpdateRobotLeader(1));
+        read_buffer.extend(bytes(&MessageType::ScreenIntroduction(
+            ScreenIntroduction { sender_id: 2 },
+        )));
+        read_buffer.extend(bytes(&MessageType::RemoveRobot(3)));
+
+        let messages = take_messages(&mut read_buffer);
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(messages[0], MessageType::UpdateRobotLeader(1)));
+        assert!(matches!(
+            messages[1],
+            MessageType::ScreenIntroduction(ScreenIntroduction { sender_id: 2 })
+        ));
+        assert!(matches!(messages[2], MessageType::RemoveRobot(3)));
+        assert!(read_buffer.is_empty());
+    }
+
+    #[test]
+    fn a_message_split_across_two_reads_is_taken_when_complete() {
+        let first = bytes(&MessageType::NewLeaderIntroduction(4));
+        let second = bytes(&MessageType::UpdateScreenLeader(5));
+        let (head, tail) = second.split_at(second.len() / 2);
+
+        let mut read_buffer = first.clone();
+        read_buffer.extend_from_slice(head);
+        let messages = take_messages(&mut read_buffer);
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(messages[0], MessageType::NewLeaderIntroduction(4)));
+        assert_eq!(read_buffer, head);
+
+        read_buffer.extend_from_slice(tail);
+        let messages = take_messages(&mut read_buffer);
+        assert_eq!(messages.len(), 1);
+        assert!(matches!(messages[0], MessageType::UpdateScreenLeader(5)));
+        assert!(read_buffer.is_empty());
+    }
+
+    #[test]
+    fn invalid_bytes_are_discarded() {
+        let mut read_buffer = vec![0xff; 8];
+        assert!(take_messages(&mut read_buffer).is_empty());
+        assert!(read_buffer.is_empty());
+
+        read_buffer.extend(bytes(&MessageType::RemoveRobot(6)));
+        assert!(matches!(
+            take_message(&mut read_buffer),
+            Some(MessageType::RemoveRobot(6))
+        ));
+    }
+}
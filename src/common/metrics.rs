@@ -0,0 +1,238 @@
+/// Este módulo implementa las métricas que exponen robots, pantallas y gateway.
+///
+/// Cada proceso registra sus métricas en un registro global con `inc`, `add` y `set`, usando
+/// los `Metric` que declara cada componente, y las expone con `serve` en formato de texto de
+/// Prometheus en `http://127.0.0.1:<puerto>/metrics`:
+///
+/// ```text
+/// # HELP heladeria_robot_orders_prepared_total Pedidos preparados por el robot.
+/// # TYPE heladeria_robot_orders_prepared_total counter
+/// heladeria_robot_orders_prepared_total 3
+/// ```
+///
+/// Las tasas (por ejemplo, tokens pasados por segundo) se obtienen de los contadores con
+/// `rate()` en Prometheus.
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+
+use crate::{log_error, log_info};
+
+/// Ruta en la que se exponen las métricas.
+pub const METRICS_PATH: &str = "/metrics";
+
+/// Tipo de una métrica.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    /// Valor que solo aumenta.
+    Counter,
+    /// Valor que puede subir y bajar.
+    Gauge,
+}
+
+impl MetricKind {
+    fn name(&self) -> &'static str {
+        match self {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Descripción de una métrica: nombre, ayuda y tipo.
+#[derive(Debug)]
+pub struct Metric {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+}
+
+/// Etiquetas de una serie (`flavor="Mint"`), ordenadas por nombre.
+type Labels = Vec<(&'static str, String)>;
+
+/// Valores de una métrica por combinación de etiquetas.
+struct Family {
+    metric: &'static Metric,
+    series: BTreeMap<Labels, f64>,
+}
+
+/// Registro de las métricas del proceso.
+#[derive(Default)]
+struct Registry {
+    families: Mutex<BTreeMap<&'static str, Family>>,
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(Registry::default)
+}
+
+/// Aplica `update` al valor de la serie de `metric` con las etiquetas indicadas, creándola en
+/// cero si todavía no existía.
+fn update(metric: &'static Metric, labels: &[(&'static str, &str)], update: impl FnOnce(&mut f64)) {
+    let mut labels: Labels = labels
+        .iter()
+        .map(|(name, value)| (*name, value.to_string()))
+        .collect();
+    labels.sort();
+    let mut families = registry().families.lock().unwrap();
+    let family = families.entry(metric.name).or_insert_with(|| Family {
+        metric,
+        series: BTreeMap::new(),
+    });
+    update(family.series.entry(labels).or_insert(0.0));
+}
+
+/// Suma uno al contador `metric`.
+pub fn inc(metric: &'static Metric, labels: &[(&'static str, &str)]) {
+    add(metric, labels, 1.0);
+}
+
+/// Suma `value` al contador `metric`.
+pub fn add(metric: &'static Metric, labels: &[(&'static str, &str)], value: f64) {
+    update(metric, labels, |current| *current += value);
+}
+
+/// Asigna `value` al indicador `metric`.
+pub fn set(metric: &'static Metric, labels: &[(&'static str, &str)], value: f64) {
+    update(metric, labels, |current| *current = value);
+}
+
+/// Devuelve todas las métricas registradas en formato de texto de Prometheus.
+pub fn render() -> String {
+    let families = registry().families.lock().unwrap();
+    let mut output = String::new();
+    for family in families.values() {
+        let _ = writeln!(
+            output,
+            "# HELP {} {}",
+            family.metric.name, family.metric.help
+        );
+        let _ = writeln!(
+            output,
+            "# TYPE {} {}",
+            family.metric.name,
+            family.metric.kind.name()
+        );
+        for (labels, value) in &family.series {
+            let _ = writeln!(
+                output,
+                "{}{} {}",
+                family.metric.name,
+                render_labels(labels),
+                value
+            );
+        }
+    }
+    output
+}
+
+/// Formatea las etiquetas de una serie (`{flavor="Mint"}`), o nada si no tiene.
+fn render_labels(labels: &Labels) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape_label_value(value)))
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+/// Escapa el valor de una etiqueta según el formato de texto de Prometheus: barras invertidas,
+/// comillas y saltos de línea.
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Expone las métricas en `127.0.0.1:<port>` desde otro hilo. Si el puerto está ocupado, lo
+/// informa y el proceso sigue sin exponerlas.
+pub fn serve(port: u16) {
+    let address = format!("127.0.0.1:{}", port);
+    let listener = match TcpListener::bind(&address) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log_error!("No se pudieron exponer las métricas en {}: {}", address, e);
+            return;
+        }
+    };
+    log_info!("Métricas en http://{}{}", address, METRICS_PATH);
+    thread::spawn(move || {
+        for stream in listener.incoming().map_while(Result::ok) {
+            handle_request(stream);
+        }
+    });
+}
+
+/// Responde un pedido HTTP con las métricas si pide `GET /metrics`, o con 404 si no.
+fn handle_request(mut stream: TcpStream) {
+    let mut request_line = String::new();
+    let mut reader = BufReader::new(&stream);
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    // Se descartan los encabezados; el cuerpo de un GET está vacío
+    let mut header = String::new();
+    while reader.read_line(&mut header).is_ok_and(|read| read > 0) && header.trim() != "" {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(METRICS_PATH)) => ("200 OK", render()),
+        _ => ("404 Not Found", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_COUNTER: Metric = Metric {
+        name: "heladeria_test_counter_total",
+        help: "Contador de prueba.",
+        kind: MetricKind::Counter,
+    };
+    static TEST_GAUGE: Metric = Metric {
+        name: "heladeria_test_gauge",
+        help: "Indicador de prueba.",
+        kind: MetricKind::Gauge,
+    };
+
+    #[test]
+    fn series_are_rendered_with_help_and_type() {
+        inc(&TEST_COUNTER, &[("flavor", "Mint"), ("screen", "0")]);
+        add(&TEST_COUNTER, &[("screen", "0"), ("flavor", "Mint")], 2.0);
+        set(&TEST_GAUGE, &[], 5.0);
+        set(&TEST_GAUGE, &[], 3.0);
+
+        let output = render();
+        assert!(output.contains(
+            "# HELP heladeria_test_counter_total Contador de prueba.\n\
+             # TYPE heladeria_test_counter_total counter\n\
+             heladeria_test_counter_total{flavor=\"Mint\",screen=\"0\"} 3\n"
+        ));
+        assert!(output.contains("# TYPE heladeria_test_gauge gauge\nheladeria_test_gauge 3\n"));
+    }
+
+    #[test]
+    fn label_values_are_escaped() {
+        let labels: Labels = vec![("customer", "a\"b\\c\nd".to_string())];
+        assert_eq!(render_labels(&labels), "{customer=\"a\\\"b\\\\c\\nd\"}");
+        assert_eq!(render_labels(&Vec::new()), "");
+    }
+}
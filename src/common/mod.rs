@@ -1,3 +1,7 @@
+pub mod admin;
 pub mod constants;
 pub mod flavors;
+pub mod logger;
 pub mod messages;
+pub mod metrics;
+pub mod recorder;
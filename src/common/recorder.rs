@@ -0,0 +1,295 @@
+/// Este módulo implementa la grabación del tráfico entre robots, pantallas y gateway.
+///
+/// Si la variable de entorno `HELADERIA_RECORD_DIR` indica un directorio, cada proceso que llama
+/// a `init` graba en `<directorio>/<componente>_<id>.rec` los bytes de cada mensaje que envía o
+/// recibe por sus sockets, con la hora a la que lo hizo. Los `MessageType` se graban al
+/// escribirlos en el socket (`send_message`) y al extraerlos de lo leído (`take_message`), así
+/// que un mensaje que se vuelve a encolar tras un error de escritura se graba una sola vez. El
+/// texto del protocolo del gateway se graba envolviendo el socket en un `RecordedStream`. Cada
+/// hilo que atiende una conexión entre nodos indica con `set_peer` qué tipo de nodo está del
+/// otro lado, y ese dato se graba junto con cada mensaje. Cuando el otro nodo cierra una conexión que el proceso
+/// estaba leyendo, se graba con `record_closed`, porque de eso depende que el proceso detecte la
+/// caída de otro nodo.
+///
+/// El archivo empieza con un `RecordingHeader` y sigue con un `Frame` por mensaje, ambos
+/// serializados con bincode. `read_recording` lo lee de vuelta; el binario `decode` lo muestra
+/// como una línea de tiempo y el binario `replay` se los vuelve a entregar a un robot o a una
+/// pantalla.
+use bincode::{deserialize, deserialize_from, serialize, ErrorKind};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::env;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::messages::MessageType;
+use crate::{log_error, log_info};
+
+/// Variable de entorno que indica el directorio en el que se graba el tráfico.
+pub const RECORD_DIR_ENV: &str = "HELADERIA_RECORD_DIR";
+/// Extensión de los archivos de grabación.
+pub const RECORDING_EXTENSION: &str = "rec";
+
+/// Sentido de un mensaje grabado.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+    /// El otro nodo cerró la conexión. El frame no tiene bytes.
+    Closed,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Sent => write!(f, "->"),
+            Direction::Received => write!(f, "<-"),
+            Direction::Closed => write!(f, "-x"),
+        }
+    }
+}
+
+/// Tipo de nodo del otro lado de la conexión por la que viajó un mensaje.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Peer {
+    Robot,
+    Screen,
+    /// La consola de administración.
+    Console,
+    /// El gateway de pagos, que habla el protocolo de texto.
+    Gateway,
+    /// Todavía no se sabe: por ejemplo, el mensaje de introducción de una conexión entrante.
+    Unknown,
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let peer = match self {
+            Peer::Robot => "robot",
+            Peer::Screen => "screen",
+            Peer::Console => "console",
+            Peer::Gateway => "gateway",
+            Peer::Unknown => "?",
+        };
+        f.pad(peer)
+    }
+}
+
+/// Encabezado de una grabación: el proceso que la hizo.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RecordingHeader {
+    pub component: String,
+    pub node_id: Option<usize>,
+}
+
+impl fmt::Display for RecordingHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.node_id {
+            Some(node_id) => write!(f, "{} {}", self.component, node_id),
+            None => write!(f, "{}", self.component),
+        }
+    }
+}
+
+/// Formato de los bytes de un mensaje grabado.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Un `MessageType` serializado con bincode.
+    Message,
+    /// Texto del protocolo del gateway, tal como se leyó o escribió del socket.
+    Text,
+}
+
+/// Mensaje grabado: los bytes tal como viajaron por el socket, con la hora y el sentido.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Frame {
+    /// Microsegundos desde la época Unix.
+    pub timestamp_us: u64,
+    pub direction: Direction,
+    pub peer: Peer,
+    pub encoding: Encoding,
+    pub bytes: Vec<u8>,
+}
+
+impl Frame {
+    /// Decodifica el mensaje grabado, si los bytes forman un `MessageType` válido.
+    pub fn message(&self) -> Option<MessageType> {
+        match self.encoding {
+            Encoding::Message => deserialize(&self.bytes).ok(),
+            Encoding::Text => None,
+        }
+    }
+
+    /// Devuelve el texto grabado, si el frame es tráfico del protocolo del gateway.
+    pub fn text(&self) -> Option<String> {
+        match self.encoding {
+            Encoding::Text => Some(String::from_utf8_lossy(&self.bytes).into_owned()),
+            Encoding::Message => None,
+        }
+    }
+}
+
+/// Grabación completa de un proceso.
+#[derive(Debug)]
+pub struct Recording {
+    pub header: RecordingHeader,
+    pub frames: Vec<Frame>,
+}
+
+static RECORDER: OnceLock<Option<Mutex<File>>> = OnceLock::new();
+
+thread_local! {
+    /// Tipo de nodo del otro lado de la conexión que atiende el hilo.
+    static PEER: Cell<Peer> = const { Cell::new(Peer::Unknown) };
+}
+
+/// Indica qué tipo de nodo está del otro lado de la conexión que atiende el hilo actual. Los
+/// mensajes que el hilo envíe o reciba a partir de ahora se graban con ese dato.
+pub fn set_peer(peer: Peer) {
+    PEER.with(|current| current.set(peer));
+}
+
+/// Empieza a grabar el tráfico del proceso si `HELADERIA_RECORD_DIR` indica un directorio. Solo
+/// tiene efecto la primera vez; si no se llama, no se graba nada.
+pub fn init(component: &'static str, node_id: Option<usize>) {
+    RECORDER.get_or_init(|| {
+        let dir = env::var(RECORD_DIR_ENV).ok()?;
+        let header = RecordingHeader {
+            component: component.to_string(),
+            node_id,
+        };
+        match create_recording(Path::new(&dir), &header) {
+            Ok((path, file)) => {
+                log_info!("Grabando el tráfico en {}", path.display());
+                Some(Mutex::new(file))
+            }
+            Err(e) => {
+                log_error!("No se pudo grabar el tráfico en {}: {}", dir, e);
+                None
+            }
+        }
+    });
+}
+
+/// Devuelve la ruta de la grabación de un proceso dentro de `dir`.
+pub fn recording_path(dir: &Path, header: &RecordingHeader) -> PathBuf {
+    let name = match header.node_id {
+        Some(node_id) => format!("{}_{}", header.component, node_id),
+        None => header.component.clone(),
+    };
+    dir.join(name).with_extension(RECORDING_EXTENSION)
+}
+
+/// Crea el archivo de la grabación, reemplazando uno anterior, y escribe el encabezado.
+fn create_recording(dir: &Path, header: &RecordingHeader) -> Result<(PathBuf, File), String> {
+    fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let path = recording_path(dir, header);
+    let mut file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(&path)
+        .map_err(|e| e.to_string())?;
+    let bytes = serialize(header).map_err(|e| e.to_string())?;
+    file.write_all(&bytes).map_err(|e| e.to_string())?;
+    Ok((path, file))
+}
+
+/// Graba un `MessageType` enviado o recibido, si el proceso está grabando. Cada mensaje se
+/// escribe entero y en el momento, para no perderlo si el proceso se cae.
+pub fn record(direction: Direction, bytes: &[u8]) {
+    write_frame(direction, PEER.with(Cell::get), Encoding::Message, bytes);
+}
+
+/// Escribe un frame en la grabación, si el proceso está grabando.
+fn write_frame(direction: Direction, peer: Peer, encoding: Encoding, bytes: &[u8]) {
+    let Some(Some(file)) = RECORDER.get() else {
+        return;
+    };
+    let frame = Frame {
+        timestamp_us: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_micros() as u64)
+            .unwrap_or(0),
+        direction,
+        peer,
+        encoding,
+        bytes: bytes.to_vec(),
+    };
+    if let Ok(serialized) = serialize(&frame) {
+        let _ = file.lock().unwrap().write_all(&serialized);
+    }
+}
+
+/// Graba que el otro nodo cerró la conexión que atiende el hilo actual.
+pub fn record_closed() {
+    record(Direction::Closed, &[]);
+}
+
+/// Socket que habla el protocolo de texto del gateway y graba lo que se lee y escribe por él,
+/// con el tipo de nodo del otro lado. Como el texto no tiene un formato con longitud, se graba
+/// cada lectura y cada escritura tal como ocurrió.
+pub struct RecordedStream {
+    stream: TcpStream,
+    peer: Peer,
+}
+
+impl RecordedStream {
+    pub fn new(stream: TcpStream, peer: Peer) -> Self {
+        RecordedStream { stream, peer }
+    }
+
+    /// Devuelve el socket sin grabar, para operaciones que no leen ni escriben datos.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl Read for RecordedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stream.read(buf)?;
+        if n == 0 {
+            write_frame(Direction::Closed, self.peer, Encoding::Text, &[]);
+        } else {
+            write_frame(Direction::Received, self.peer, Encoding::Text, &buf[..n]);
+        }
+        Ok(n)
+    }
+}
+
+impl Write for RecordedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.stream.write(buf)?;
+        write_frame(Direction::Sent, self.peer, Encoding::Text, &buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Lee una grabación. Si el proceso se cayó mientras escribía el último mensaje, ese mensaje
+/// se descarta.
+pub fn read_recording(path: &Path) -> Result<Recording, String> {
+    let file = File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut reader = BufReader::new(file);
+    let header: RecordingHeader = deserialize_from(&mut reader)
+        .map_err(|e| format!("{}: encabezado inválido: {}", path.display(), e))?;
+    let mut frames = Vec::new();
+    loop {
+        match deserialize_from::<_, Frame>(&mut reader) {
+            Ok(frame) => frames.push(frame),
+            Err(err) => match *err {
+                ErrorKind::Io(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+                _ => return Err(format!("{}: {}", path.display(), err)),
+            },
+        }
+    }
+    Ok(Recording { header, frames })
+}
@@ -0,0 +1,240 @@
+/// Este módulo implementa los comandos de la consola de administración `heladeria-ctl`.
+///
+/// La consola envía cada comando al puerto de administración de un robot o una pantalla y
+/// muestra la respuesta. Las consultas (`status`, `leader`, `ring`, `stock` y `orders`) piden el
+/// estado del nodo y muestran la parte que corresponde; el resto modifica el anillo de robots.
+use std::fmt;
+
+use super::snapshot::{ClusterSnapshot, SnapshotFormat};
+use crate::common::admin;
+use crate::common::constants::{ROBOT_ADMIN_BASE_PORT, SCREEN_ADMIN_BASE_PORT};
+use crate::common::flavors::IceCreamFlavor;
+use crate::common::messages::{AdminCommand, AdminReply, NodeKind, NodeStatus};
+
+/// Nodo al que se envían los comandos.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Robot(usize),
+    Screen(usize),
+}
+
+impl Target {
+    /// Devuelve la dirección del puerto de administración del nodo.
+    pub fn address(&self) -> String {
+        let port = match self {
+            Target::Robot(id) => ROBOT_ADMIN_BASE_PORT + *id as u16,
+            Target::Screen(id) => SCREEN_ADMIN_BASE_PORT + *id as u16,
+        };
+        format!("127.0.0.1:{}", port)
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Robot(id) => write!(f, "robot {}", id),
+            Target::Screen(id) => write!(f, "screen {}", id),
+        }
+    }
+}
+
+/// Comando de la consola de administración.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Muestra el estado completo del nodo.
+    Status,
+    /// Muestra los líderes que conoce el nodo.
+    Leader,
+    /// Muestra las conexiones del anillo del nodo.
+    Ring,
+    /// Muestra el stock de cada sabor.
+    Stock,
+    /// Muestra los pedidos pendientes.
+    Orders,
+    /// Deja de asignarle pedidos nuevos a un robot.
+    Drain(usize),
+    /// Vuelve a asignarle pedidos a un robot retirado.
+    Undrain(usize),
+    /// Repone gramos de un sabor.
+    Restock(IceCreamFlavor, i32),
+    /// Fuerza una elección de líder en el anillo de robots.
+    ForceElection,
+    /// Exporta la topología de los dos anillos, consultando a todos los nodos.
+    Topology(SnapshotFormat),
+}
+
+impl Command {
+    /// Uso de los comandos, para mostrar en la ayuda.
+    pub const USAGE: &'static str = "status | leader | ring | stock | orders | drain <robot> | \
+                                     undrain <robot> | restock <sabor> <gramos> | force-election | \
+                                     topology [json | dot]";
+
+    /// Interpreta un comando y sus argumentos.
+    pub fn parse(args: &[String]) -> Result<Command, String> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args[..] {
+            ["status"] => Ok(Command::Status),
+            ["leader"] => Ok(Command::Leader),
+            ["ring"] => Ok(Command::Ring),
+            ["stock"] => Ok(Command::Stock),
+            ["orders"] => Ok(Command::Orders),
+            ["drain", robot_id] => Ok(Command::Drain(parse_robot_id(robot_id)?)),
+            ["undrain", robot_id] => Ok(Command::Undrain(parse_robot_id(robot_id)?)),
+            ["restock", flavor, grams] => {
+                let flavor = flavor
+                    .parse()
+                    .map_err(|_| format!("Sabor inválido: {}", flavor))?;
+                let grams = grams
+                    .parse()
+                    .map_err(|_| format!("Cantidad de gramos inválida: {}", grams))?;
+                Ok(Command::Restock(flavor, grams))
+            }
+            ["force-election"] => Ok(Command::ForceElection),
+            ["topology"] => Ok(Command::Topology(SnapshotFormat::Json)),
+            ["topology", format] => Ok(Command::Topology(SnapshotFormat::parse(format)?)),
+            _ => Err(format!("Comando inválido. Comandos: {}", Self::USAGE)),
+        }
+    }
+
+    /// Devuelve el comando que se envía al puerto de administración del nodo.
+    fn admin_command(&self) -> AdminCommand {
+        match self {
+            Command::Status
+            | Command::Leader
+            | Command::Ring
+            | Command::Stock
+            | Command::Orders
+            | Command::Topology(_) => AdminCommand::Status,
+            Command::Drain(robot_id) => AdminCommand::Drain(*robot_id, true),
+            Command::Undrain(robot_id) => AdminCommand::Drain(*robot_id, false),
+            Command::Restock(flavor, grams) => AdminCommand::Restock(flavor.clone(), *grams),
+            Command::ForceElection => AdminCommand::ForceElection,
+        }
+    }
+
+    /// Muestra la parte del estado del nodo que pide el comando.
+    fn render(&self, status: &NodeStatus) -> String {
+        match self {
+            Command::Leader => render_leader(status),
+            Command::Ring => render_ring(status),
+            Command::Stock => render_stock(status),
+            Command::Orders => render_orders(status),
+            _ => render_status(status),
+        }
+    }
+}
+
+/// Interpreta el ID de un robot.
+fn parse_robot_id(robot_id: &str) -> Result<usize, String> {
+    robot_id
+        .parse()
+        .map_err(|_| format!("ID de robot inválido: {}", robot_id))
+}
+
+/// Envía el comando al nodo y devuelve el texto a mostrar, o el error del nodo. La topología
+/// se arma consultando, a partir del nodo indicado, a todos los nodos que este conoce.
+pub fn run(target: Target, command: &Command) -> Result<String, String> {
+    if let Command::Topology(format) = command {
+        return ClusterSnapshot::collect(target).map(|snapshot| snapshot.render(*format));
+    }
+    match admin::request(&target.address(), command.admin_command())? {
+        AdminReply::Status(status) => Ok(command.render(&status)),
+        AdminReply::Done(message) => Ok(message),
+        AdminReply::Error(message) => Err(message),
+    }
+}
+
+/// Muestra el estado completo del nodo.
+pub fn render_status(status: &NodeStatus) -> String {
+    let mut lines = vec![format!(
+        "{} {}{}",
+        status.kind,
+        status.id,
+        if status.is_leader { " (líder)" } else { "" }
+    )];
+    lines.push(render_leader(status));
+    lines.push(format!(
+        "Anillo: {}",
+        render_ring(status).replace('\n', ", ")
+    ));
+    if status.kind == NodeKind::Robot {
+        lines.push(format!("Tokens: {}", join_or_none(&status.tokens)));
+    }
+    lines.push(format!(
+        "Stock: {}",
+        render_stock(status).replace('\n', ", ")
+    ));
+    let pending: usize = status.orders.values().map(Vec::len).sum();
+    lines.push(format!("Pedidos pendientes: {}", pending));
+    if status.kind == NodeKind::Robot {
+        lines.push(format!(
+            "Robots retirados: {}",
+            join_or_none(&status.drained)
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Muestra los líderes que conoce el nodo.
+pub fn render_leader(status: &NodeStatus) -> String {
+    match status.kind {
+        NodeKind::Robot => format!(
+            "Robot líder: {}\nPantalla líder: {}",
+            status.leader_id, status.peer_leader_id
+        ),
+        NodeKind::Screen => format!(
+            "Pantalla líder: {}\nRobot líder: {}",
+            status.leader_id, status.peer_leader_id
+        ),
+    }
+}
+
+/// Muestra las conexiones del anillo, una por línea (`0 -> 1`).
+pub fn render_ring(status: &NodeStatus) -> String {
+    status
+        .ring
+        .iter()
+        .map(|(node, next)| format!("{} -> {}", node, next))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Muestra el stock de cada sabor, uno por línea (`Mint 9500 g`).
+pub fn render_stock(status: &NodeStatus) -> String {
+    match status.stock {
+        Some(ref stock) => {
+            let mut lines: Vec<String> = stock
+                .iter()
+                .map(|(flavor, grams)| format!("{} {} g", flavor, grams))
+                .collect();
+            lines.sort();
+            lines.join("\n")
+        }
+        None => "desconocido".to_string(),
+    }
+}
+
+/// Muestra los pedidos pendientes de cada robot o pantalla, uno por línea (`robot 1: 3, 5`).
+pub fn render_orders(status: &NodeStatus) -> String {
+    if status.orders.is_empty() {
+        return "Sin pedidos pendientes".to_string();
+    }
+    status
+        .orders
+        .iter()
+        .map(|(node, order_ids)| format!("{} {}: {}", status.kind, node, join_or_none(order_ids)))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Une los elementos separados por comas, o devuelve `-` si no hay ninguno.
+fn join_or_none<T: fmt::Display>(items: &[T]) -> String {
+    if items.is_empty() {
+        return "-".to_string();
+    }
+    items
+        .iter()
+        .map(T::to_string)
+        .collect::<Vec<String>>()
+        .join(", ")
+}
@@ -0,0 +1,54 @@
+use heladeria::ctl::ctl::{run, Command, Target};
+use std::env;
+
+/// Argumento de línea de comandos que indica el robot al que se envía el comando.
+const ROBOT_ARG: &str = "--robot";
+/// Argumento de línea de comandos que indica la pantalla a la que se envía el comando.
+const SCREEN_ARG: &str = "--screen";
+
+/// Punto de entrada principal para el binario `heladeria-ctl`.
+/// Envía un comando al puerto de administración de un robot (`--robot <id>`, el robot 0 si no se
+/// indica otro nodo) o de una pantalla (`--screen <id>`) y muestra su respuesta. Termina con
+/// código 1 si no se pudo conectar con el nodo o este rechazó el comando.
+///
+/// Uso: cargo run --bin heladeria-ctl -- [--robot <id> | --screen <id>] <comando>
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut target = Target::Robot(0);
+    let mut command_args = &args[1..];
+    if command_args.len() >= 2 && [ROBOT_ARG, SCREEN_ARG].contains(&command_args[0].as_str()) {
+        let id = command_args[1]
+            .parse()
+            .unwrap_or_else(|_| exit_with_usage(&args[0]));
+        target = if command_args[0] == ROBOT_ARG {
+            Target::Robot(id)
+        } else {
+            Target::Screen(id)
+        };
+        command_args = &command_args[2..];
+    }
+
+    let command = Command::parse(command_args).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit_with_usage(&args[0]);
+    });
+    match run(target, &command) {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("Error ({}): {}", target, e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Muestra el uso del binario y termina el proceso.
+fn exit_with_usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} [{} <id> | {} <id>] <{}>",
+        program,
+        ROBOT_ARG,
+        SCREEN_ARG,
+        Command::USAGE
+    );
+    std::process::exit(1);
+}
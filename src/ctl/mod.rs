@@ -0,0 +1,2 @@
+pub mod ctl;
+pub mod snapshot;
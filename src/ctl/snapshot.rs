@@ -0,0 +1,434 @@
+/// Este módulo implementa la foto de la topología de la heladería que exporta `heladeria-ctl`.
+///
+/// A partir de un nodo, se consulta el estado de cada robot y cada pantalla que aparece en los
+/// anillos que informan los nodos, y en el líder del otro anillo. Con esas respuestas se arma
+/// una foto de los dos anillos, sus líderes, el token de cada sabor que tiene cada robot y el
+/// enlace entre la pantalla líder y el robot líder, que puede exportarse en JSON o en DOT
+/// (Graphviz):
+///
+/// ```text
+/// cargo run --bin heladeria-ctl -- topology dot | dot -Tsvg > topologia.svg
+/// ```
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::Write as _;
+
+use super::ctl::Target;
+use crate::common::admin;
+use crate::common::flavors::IceCreamFlavor;
+use crate::common::messages::{AdminCommand, AdminReply, NodeKind, NodeStatus};
+
+/// Formato en el que se exporta la foto.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotFormat {
+    Json,
+    Dot,
+}
+
+impl SnapshotFormat {
+    /// Interpreta el nombre del formato (`json` o `dot`).
+    pub fn parse(format: &str) -> Result<Self, String> {
+        match format {
+            "json" => Ok(SnapshotFormat::Json),
+            "dot" => Ok(SnapshotFormat::Dot),
+            _ => Err(format!(
+                "Formato inválido: {}. Formatos: json | dot",
+                format
+            )),
+        }
+    }
+}
+
+/// Un nodo de un anillo, según su propia respuesta.
+#[derive(Debug, Serialize)]
+pub struct NodeSnapshot {
+    pub id: usize,
+    /// `false` si el nodo no respondió a la consulta.
+    pub reachable: bool,
+    /// Líder del anillo según el nodo.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leader_id: Option<usize>,
+    /// Sabores cuyo token tenía el robot al responder.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<IceCreamFlavor>,
+}
+
+/// Conexión de un anillo: el nodo `from` le envía mensajes al nodo `to`.
+#[derive(Debug, Serialize)]
+pub struct Link {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// Foto de uno de los anillos.
+#[derive(Debug, Default, Serialize)]
+pub struct RingSnapshot {
+    /// Líder según la mayoría de los nodos que respondieron.
+    pub leader_id: Option<usize>,
+    pub nodes: Vec<NodeSnapshot>,
+    /// Conexiones del anillo según el líder, o según el primer nodo que respondió.
+    pub links: Vec<Link>,
+}
+
+/// Enlace entre la pantalla líder y el robot líder.
+#[derive(Debug, Serialize)]
+pub struct LeaderLink {
+    pub screen_id: usize,
+    pub robot_id: usize,
+}
+
+/// Foto de la topología de la heladería.
+#[derive(Debug, Serialize)]
+pub struct ClusterSnapshot {
+    pub robots: RingSnapshot,
+    pub screens: RingSnapshot,
+    /// Robot líder al que está conectada la pantalla líder, según la pantalla líder.
+    pub screen_robot_link: Option<LeaderLink>,
+}
+
+impl ClusterSnapshot {
+    /// Consulta el estado de todos los nodos alcanzables desde `start` y arma la foto.
+    pub fn collect(start: Target) -> Result<Self, String> {
+        Self::collect_with(start, |target| {
+            match admin::request(&target.address(), AdminCommand::Status) {
+                Ok(AdminReply::Status(status)) => Some(status),
+                _ => None,
+            }
+        })
+    }
+
+    /// Arma la foto a partir de `start`, obteniendo el estado de cada nodo con `query`, que
+    /// devuelve `None` si el nodo no respondió.
+    fn collect_with(
+        start: Target,
+        mut query: impl FnMut(Target) -> Option<NodeStatus>,
+    ) -> Result<Self, String> {
+        let mut statuses: BTreeMap<NodeKey, Option<NodeStatus>> = BTreeMap::new();
+        let mut pending = VecDeque::from([start]);
+        while let Some(target) = pending.pop_front() {
+            let key = target_key(target);
+            if statuses.contains_key(&key) {
+                continue;
+            }
+            let status = query(target);
+            if let Some(ref status) = status {
+                pending.extend(neighbours(status));
+            }
+            statuses.insert(key, status);
+        }
+        if matches!(statuses.get(&target_key(start)), Some(None)) {
+            return Err(format!("{} no respondió", start));
+        }
+
+        let (robots, screens): (Vec<_>, Vec<_>) = statuses
+            .into_iter()
+            .partition(|((kind, _), _)| *kind == ROBOT_KEY);
+        let robots = collect_ring(robots);
+        let screens = collect_ring(screens);
+        Ok(ClusterSnapshot {
+            screen_robot_link: screens.ring.leader_id.and_then(|screen_id| {
+                screens
+                    .peer_leaders
+                    .get(&screen_id)
+                    .map(|&robot_id| LeaderLink {
+                        screen_id,
+                        robot_id,
+                    })
+            }),
+            robots: robots.ring,
+            screens: screens.ring,
+        })
+    }
+
+    /// Exporta la foto en el formato indicado.
+    pub fn render(&self, format: SnapshotFormat) -> String {
+        match format {
+            SnapshotFormat::Json => serde_json::to_string_pretty(self).unwrap_or_default(),
+            SnapshotFormat::Dot => self.to_dot(),
+        }
+    }
+
+    /// Exporta la foto como un grafo de Graphviz: un subgrafo por anillo, con el líder en
+    /// negrita, los nodos que no respondieron en gris y el enlace entre líderes punteado.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph heladeria {\n");
+        write_ring_dot(&mut dot, "robot", "Robots", &self.robots);
+        write_ring_dot(&mut dot, "screen", "Pantallas", &self.screens);
+        if let Some(ref link) = self.screen_robot_link {
+            let _ = writeln!(
+                dot,
+                "  \"screen {}\" -> \"robot {}\" [style=dashed, label=\"líder\"];",
+                link.screen_id, link.robot_id
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Clave de los nodos de cada tipo, para ordenar primero los robots y después las pantallas.
+const ROBOT_KEY: u8 = 0;
+const SCREEN_KEY: u8 = 1;
+
+/// Tipo e ID de un nodo.
+type NodeKey = (u8, usize);
+
+fn target_key(target: Target) -> NodeKey {
+    match target {
+        Target::Robot(id) => (ROBOT_KEY, id),
+        Target::Screen(id) => (SCREEN_KEY, id),
+    }
+}
+
+/// Devuelve los nodos que conoce un nodo: los de su anillo y el líder del otro anillo.
+fn neighbours(status: &NodeStatus) -> Vec<Target> {
+    let target = |robot: bool, id: usize| {
+        if robot {
+            Target::Robot(id)
+        } else {
+            Target::Screen(id)
+        }
+    };
+    let is_robot = status.kind == NodeKind::Robot;
+    let ring: BTreeSet<usize> = status
+        .ring
+        .iter()
+        .flat_map(|(&node, &next)| [node, next])
+        .collect();
+    let mut targets: Vec<Target> = ring.into_iter().map(|id| target(is_robot, id)).collect();
+    targets.push(target(!is_robot, status.peer_leader_id));
+    targets
+}
+
+/// Anillo armado a partir de las respuestas, junto con el líder del otro anillo que informa
+/// cada nodo.
+struct CollectedRing {
+    ring: RingSnapshot,
+    peer_leaders: BTreeMap<usize, usize>,
+}
+
+/// Arma la foto de un anillo con las respuestas de sus nodos.
+fn collect_ring(statuses: Vec<(NodeKey, Option<NodeStatus>)>) -> CollectedRing {
+    let mut votes: BTreeMap<usize, usize> = BTreeMap::new();
+    for status in statuses.iter().filter_map(|(_, status)| status.as_ref()) {
+        *votes.entry(status.leader_id).or_insert(0) += 1;
+    }
+    // Ante un empate se elige el ID más bajo
+    let leader_id = votes
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(&leader_id, _)| leader_id);
+
+    let responded = || statuses.iter().filter_map(|(_, status)| status.as_ref());
+    let ring_source = responded()
+        .find(|status| Some(status.id) == leader_id)
+        .or_else(|| responded().next());
+    let links = ring_source
+        .map(|status| {
+            status
+                .ring
+                .iter()
+                .map(|(&from, &to)| Link { from, to })
+                .collect()
+        })
+        .unwrap_or_default();
+    let peer_leaders = responded()
+        .map(|status| (status.id, status.peer_leader_id))
+        .collect();
+    let nodes = statuses
+        .into_iter()
+        .map(|((_, id), status)| NodeSnapshot {
+            id,
+            reachable: status.is_some(),
+            leader_id: status.as_ref().map(|status| status.leader_id),
+            tokens: status.map(|status| status.tokens).unwrap_or_default(),
+        })
+        .collect();
+
+    CollectedRing {
+        ring: RingSnapshot {
+            leader_id,
+            nodes,
+            links,
+        },
+        peer_leaders,
+    }
+}
+
+/// Escribe un anillo como subgrafo de Graphviz.
+fn write_ring_dot(dot: &mut String, kind: &str, label: &str, ring: &RingSnapshot) {
+    let _ = writeln!(dot, "  subgraph cluster_{} {{", kind);
+    let _ = writeln!(dot, "    label=\"{}\";", label);
+    for node in &ring.nodes {
+        let mut lines = vec![format!("{} {}", kind, node.id)];
+        let mut attributes = Vec::new();
+        if ring.leader_id == Some(node.id) {
+            lines.push("líder".to_string());
+            attributes.push("style=bold".to_string());
+        }
+        if !node.tokens.is_empty() {
+            let tokens: Vec<String> = node
+                .tokens
+                .iter()
+                .map(|flavor| flavor.to_string())
+                .collect();
+            lines.push(tokens.join(", "));
+        }
+        if !node.reachable {
+            lines.push("sin respuesta".to_string());
+            attributes.push("color=gray, fontcolor=gray".to_string());
+        }
+        attributes.insert(0, format!("label=\"{}\"", lines.join("\\n")));
+        let _ = writeln!(
+            dot,
+            "    \"{} {}\" [{}];",
+            kind,
+            node.id,
+            attributes.join(", ")
+        );
+    }
+    for link in &ring.links {
+        let _ = writeln!(
+            dot,
+            "    \"{} {}\" -> \"{} {}\";",
+            kind, link.from, kind, link.to
+        );
+    }
+    dot.push_str("  }\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn status(kind: NodeKind, id: usize, leader_id: usize, ring: &[(usize, usize)]) -> NodeStatus {
+        NodeStatus {
+            kind,
+            id,
+            leader_id,
+            is_leader: id == leader_id,
+            peer_leader_id: 0,
+            ring: ring.iter().cloned().collect(),
+            tokens: Vec::new(),
+            stock: None,
+            orders: BTreeMap::new(),
+            drained: Vec::new(),
+        }
+    }
+
+    /// Arma la foto respondiendo con los estados indicados; el resto de los nodos no responde.
+    fn snapshot(start: Target, statuses: Vec<NodeStatus>) -> Result<ClusterSnapshot, String> {
+        let statuses: HashMap<NodeKey, NodeStatus> = statuses
+            .into_iter()
+            .map(|status| {
+                let target = match status.kind {
+                    NodeKind::Robot => Target::Robot(status.id),
+                    NodeKind::Screen => Target::Screen(status.id),
+                };
+                (target_key(target), status)
+            })
+            .collect();
+        ClusterSnapshot::collect_with(start, |target| statuses.get(&target_key(target)).cloned())
+    }
+
+    #[test]
+    fn formats_are_parsed_by_name() {
+        assert_eq!(SnapshotFormat::parse("json"), Ok(SnapshotFormat::Json));
+        assert_eq!(SnapshotFormat::parse("dot"), Ok(SnapshotFormat::Dot));
+        assert!(SnapshotFormat::parse("svg").is_err());
+        assert!(SnapshotFormat::parse("").is_err());
+    }
+
+    #[test]
+    fn both_rings_are_discovered_from_one_node() {
+        let ring = [(0, 1), (1, 0)];
+        let mut robot0 = status(NodeKind::Robot, 0, 0, &ring);
+        robot0.tokens = vec![IceCreamFlavor::Mint];
+        let mut screen1 = status(NodeKind::Screen, 1, 1, &[(0, 1), (1, 0)]);
+        screen1.peer_leader_id = 0;
+        let snapshot = snapshot(
+            Target::Screen(1),
+            vec![
+                robot0,
+                status(NodeKind::Robot, 1, 0, &ring),
+                status(NodeKind::Screen, 0, 1, &[(0, 1), (1, 0)]),
+                screen1,
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(snapshot.robots.leader_id, Some(0));
+        assert_eq!(snapshot.screens.leader_id, Some(1));
+        assert_eq!(snapshot.robots.nodes.len(), 2);
+        assert_eq!(snapshot.robots.nodes[0].tokens, [IceCreamFlavor::Mint]);
+        assert_eq!(snapshot.screens.links.len(), 2);
+        let link = snapshot.screen_robot_link.as_ref().unwrap();
+        assert_eq!((link.screen_id, link.robot_id), (1, 0));
+
+        let dot = snapshot.render(SnapshotFormat::Dot);
+        assert!(dot.contains("\"robot 0\" [label=\"robot 0\\nlíder\\nMint\", style=bold];"));
+        assert!(dot.contains("\"screen 1\" -> \"robot 0\" [style=dashed"));
+    }
+
+    #[test]
+    fn nodes_that_do_not_answer_are_kept_as_unreachable() {
+        // El robot 2 figura en el anillo pero no responde
+        let ring = [(0, 1), (1, 2), (2, 0)];
+        let snapshot = snapshot(
+            Target::Robot(0),
+            vec![
+                status(NodeKind::Robot, 0, 0, &ring),
+                status(NodeKind::Robot, 1, 0, &ring),
+            ],
+        )
+        .unwrap();
+
+        let reachable: Vec<(usize, bool)> = snapshot
+            .robots
+            .nodes
+            .iter()
+            .map(|node| (node.id, node.reachable))
+            .collect();
+        assert_eq!(reachable, [(0, true), (1, true), (2, false)]);
+        // La pantalla líder que informan los robots tampoco responde
+        assert_eq!(snapshot.screens.leader_id, None);
+        assert!(snapshot.screen_robot_link.is_none());
+        assert!(snapshot
+            .to_dot()
+            .contains("\"robot 2\" [label=\"robot 2\\nsin respuesta\", color=gray"));
+    }
+
+    #[test]
+    fn the_leader_is_chosen_by_majority_and_ties_go_to_the_lowest_id() {
+        let ring = [(0, 1), (1, 2), (2, 0)];
+        let majority = snapshot(
+            Target::Robot(0),
+            vec![
+                status(NodeKind::Robot, 0, 2, &ring),
+                status(NodeKind::Robot, 1, 2, &ring),
+                status(NodeKind::Robot, 2, 1, &[(1, 2), (2, 1)]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(majority.robots.leader_id, Some(2));
+        // Las conexiones son las que informa el líder
+        assert_eq!(majority.robots.links.len(), 2);
+
+        let tie = snapshot(
+            Target::Robot(0),
+            vec![
+                status(NodeKind::Robot, 0, 1, &[(0, 1), (1, 0)]),
+                status(NodeKind::Robot, 1, 0, &[(0, 1), (1, 0)]),
+            ],
+        )
+        .unwrap();
+        assert_eq!(tie.robots.leader_id, Some(0));
+    }
+
+    #[test]
+    fn an_unreachable_start_is_an_error() {
+        let result = snapshot(Target::Robot(3), Vec::new());
+        assert_eq!(result.err().as_deref(), Some("robot 3 no respondió"));
+    }
+}
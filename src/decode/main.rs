@@ -0,0 +1,65 @@
+use heladeria::decode::timeline::{load_recordings, render_entry, timeline};
+use std::env;
+use std::io::{self, Write};
+
+/// Argumento de línea de comandos que indica que los mensajes se muestren en varias líneas.
+const PRETTY_ARG: &str = "--pretty";
+/// Argumento de línea de comandos que indica un tipo de mensaje que no se muestra (por
+/// ejemplo, `Token`). Puede repetirse.
+const SKIP_ARG: &str = "--skip";
+
+/// Punto de entrada principal para el binario `decode`.
+/// Lee las grabaciones del tráfico de robots y pantallas (archivos `.rec`, o directorios que los
+/// contienen) y muestra sus mensajes como una línea de tiempo. Con `--pretty`, cada mensaje se
+/// muestra en varias líneas; con `--skip <tipo>`, se omiten los mensajes de ese tipo.
+///
+/// Uso: cargo run --bin decode -- [--pretty] [--skip Token] <grabación | directorio>...
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut pretty = false;
+    let mut skipped: Vec<String> = Vec::new();
+    let mut paths: Vec<String> = Vec::new();
+    let mut remaining = args[1..].iter();
+    while let Some(arg) = remaining.next() {
+        match arg.as_str() {
+            PRETTY_ARG => pretty = true,
+            SKIP_ARG => match remaining.next() {
+                Some(kind) => skipped.push(kind.clone()),
+                None => exit_with_usage(&args[0]),
+            },
+            _ => paths.push(arg.clone()),
+        }
+    }
+    if paths.is_empty() {
+        exit_with_usage(&args[0]);
+    }
+
+    let recordings = load_recordings(&paths).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
+    let entries = timeline(&recordings);
+    let start_us = entries
+        .first()
+        .map(|entry| entry.frame.timestamp_us)
+        .unwrap_or(0);
+    let mut stdout = io::stdout().lock();
+    for entry in entries
+        .iter()
+        .filter(|entry| !skipped.contains(&entry.kind()))
+    {
+        // Se deja de escribir si se cerró la salida (por ejemplo, al pasarla por `head`)
+        if writeln!(stdout, "{}", render_entry(entry, start_us, pretty)).is_err() {
+            break;
+        }
+    }
+}
+
+/// Muestra el uso del binario y termina el proceso.
+fn exit_with_usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} [{}] [{} <type>] <recording | dir>...",
+        program, PRETTY_ARG, SKIP_ARG
+    );
+    std::process::exit(1);
+}
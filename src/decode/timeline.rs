@@ -0,0 +1,200 @@
+/// Este módulo arma la línea de tiempo que muestra el binario `decode` a partir de las
+/// grabaciones del tráfico de robots, pantallas y gateway.
+///
+/// Los mensajes de todas las grabaciones se ordenan por hora y se muestran uno por línea, con
+/// el tiempo desde el primer mensaje, el proceso que lo grabó, el sentido (`->` enviado, `<-`
+/// recibido, `-x` conexión cerrada por el otro nodo), el tipo de nodo del otro lado de la
+/// conexión y el mensaje decodificado:
+///
+/// ```text
+/// +    0.004462s  robot 0   <- robot    AllConnected(AllConnected { sender_id: 1 })
+/// ```
+///
+/// El texto que intercambian pantallas y gateway se muestra tal como viajó, entre comillas:
+///
+/// ```text
+/// +    0.031008s  screen 0  -> gateway  "KEY,0-1760000000000-0,PREPARE,customer-1,Mint,100g;"
+/// ```
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::common::recorder::{
+    read_recording, Direction, Frame, Recording, RecordingHeader, RECORDING_EXTENSION,
+};
+
+/// Mensaje de la línea de tiempo, junto con el proceso que lo grabó.
+pub struct TimelineEntry<'a> {
+    pub node: &'a RecordingHeader,
+    pub frame: &'a Frame,
+}
+
+impl TimelineEntry<'_> {
+    /// Devuelve el tipo del mensaje (por ejemplo, `Token`), o un texto vacío si no es válido.
+    /// Del texto del gateway devuelve el comando con el que empieza (por ejemplo, `PREPARE`).
+    pub fn kind(&self) -> String {
+        if let Some(text) = self.frame.text() {
+            return text
+                .trim()
+                .split(',')
+                .next()
+                .unwrap_or_default()
+                .to_string();
+        }
+        self.frame
+            .message()
+            .map(|message| {
+                let debug = format!("{:?}", message);
+                debug
+                    .split(|c: char| !c.is_alphanumeric())
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Devuelve las grabaciones indicadas. Si una ruta es un directorio, se leen todas las
+/// grabaciones que contiene.
+pub fn load_recordings(paths: &[String]) -> Result<Vec<Recording>, String> {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in paths.iter().map(Path::new) {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|file| {
+                    file.extension()
+                        .is_some_and(|extension| extension == RECORDING_EXTENSION)
+                })
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.to_path_buf());
+        }
+    }
+    files.iter().map(|file| read_recording(file)).collect()
+}
+
+/// Ordena por hora los mensajes de todas las grabaciones. Los mensajes de una misma grabación
+/// conservan su orden.
+pub fn timeline(recordings: &[Recording]) -> Vec<TimelineEntry<'_>> {
+    let mut entries: Vec<TimelineEntry> = recordings
+        .iter()
+        .flat_map(|recording| {
+            recording.frames.iter().map(|frame| TimelineEntry {
+                node: &recording.header,
+                frame,
+            })
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.frame.timestamp_us);
+    entries
+}
+
+/// Muestra un mensaje de la línea de tiempo, con el tiempo transcurrido desde `start_us`. Con
+/// `pretty`, el mensaje se muestra en varias líneas.
+pub fn render_entry(entry: &TimelineEntry, start_us: u64, pretty: bool) -> String {
+    format!(
+        "{}  {:<9} {} {:<8} {}",
+        render_elapsed(entry.frame.timestamp_us, start_us),
+        entry.node.to_string(),
+        entry.frame.direction,
+        entry.frame.peer,
+        render_message(entry.frame, pretty)
+    )
+}
+
+/// Muestra el tiempo transcurrido desde `start_us` hasta `timestamp_us`: `+    1.000250s`.
+pub fn render_elapsed(timestamp_us: u64, start_us: u64) -> String {
+    let elapsed_us = timestamp_us.saturating_sub(start_us);
+    format!(
+        "+{:>5}.{:06}s",
+        elapsed_us / 1_000_000,
+        elapsed_us % 1_000_000
+    )
+}
+
+/// Muestra el mensaje grabado. Con `pretty`, el mensaje se muestra en varias líneas.
+pub fn render_message(frame: &Frame, pretty: bool) -> String {
+    if frame.direction == Direction::Closed {
+        return "conexión cerrada".to_string();
+    }
+    if let Some(text) = frame.text() {
+        return format!("{:?}", text.trim());
+    }
+    match frame.message() {
+        Some(message) if pretty => format!("{:#?}", message),
+        Some(message) => format!("{:?}", message),
+        None => format!("mensaje inválido ({} bytes)", frame.bytes.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::messages::{serialize_message, Commit, MessageType};
+    use crate::common::recorder::{Encoding, Peer};
+
+    fn frame(direction: Direction, encoding: Encoding, bytes: &[u8]) -> Frame {
+        Frame {
+            timestamp_us: 0,
+            direction,
+            peer: Peer::Gateway,
+            encoding,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    fn kind(frame: &Frame) -> String {
+        let node = RecordingHeader {
+            component: "screen".to_string(),
+            node_id: Some(0),
+        };
+        TimelineEntry { node: &node, frame }.kind()
+    }
+
+    #[test]
+    fn gateway_text_is_shown_quoted_and_typed_by_its_command() {
+        let sent = frame(
+            Direction::Sent,
+            Encoding::Text,
+            b"KEY,0-1-0,PREPARE,,Mint,100g;",
+        );
+        let received = frame(Direction::Received, Encoding::Text, b"COMMIT,3\n");
+
+        assert_eq!(
+            render_message(&sent, false),
+            "\"KEY,0-1-0,PREPARE,,Mint,100g;\""
+        );
+        assert_eq!(render_message(&received, false), "\"COMMIT,3\"");
+        assert_eq!(kind(&sent), "KEY");
+        assert_eq!(kind(&received), "COMMIT");
+    }
+
+    #[test]
+    fn messages_are_decoded_and_text_is_not() {
+        let bytes =
+            serialize_message(&MessageType::Commit(Commit { order_id: 1 })).unwrap_or_default();
+        let message = frame(Direction::Received, Encoding::Message, &bytes);
+        let text = frame(Direction::Received, Encoding::Text, &bytes);
+
+        assert_eq!(kind(&message), "Commit");
+        assert!(message.text().is_none());
+        assert!(text.message().is_none());
+    }
+
+    #[test]
+    fn closed_connections_and_invalid_bytes_are_rendered() {
+        let closed = frame(Direction::Closed, Encoding::Text, &[]);
+        let invalid = frame(Direction::Received, Encoding::Message, &[0xff]);
+
+        assert_eq!(render_message(&closed, false), "conexión cerrada");
+        assert_eq!(
+            render_message(&invalid, false),
+            "mensaje inválido (1 bytes)"
+        );
+        assert_eq!(kind(&invalid), "");
+    }
+}
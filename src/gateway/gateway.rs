@@ -1,14 +1,53 @@
+use crate::common::constants::{
+    PRICE_PER_GRAM, REPLICA_QUEUE_CAPACITY, REPLICA_WRITE_TIMEOUT_MS, STANDBY_CONNECT_GRACE_MS,
+    STANDBY_CONNECT_RETRY_MS,
+};
+use crate::common::messages::AbortReason;
+use crate::common::metrics::{self, Metric, MetricKind};
+use crate::common::recorder::{Peer, RecordedStream};
+use crate::{log_error, log_info, log_warn};
+use crossbeam_channel::{bounded, Sender, TrySendError};
 use rand::Rng;
 use std::collections::HashMap;
 use std::fs::OpenOptions;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
+
+/// Pagos autorizados y rechazados, según la etiqueta `result`.
+pub const PAYMENTS: Metric = Metric {
+    name: "heladeria_gateway_payments_total",
+    help: "Pagos procesados por el gateway, por resultado (approved o rejected).",
+    kind: MetricKind::Counter,
+};
+
+/// Órdenes autorizadas que todavía no se cobraron ni cancelaron.
+pub const PENDING_HOLDS: Metric = Metric {
+    name: "heladeria_gateway_pending_holds",
+    help: "Órdenes autorizadas pendientes de cobro o cancelación.",
+    kind: MetricKind::Gauge,
+};
+
+/// Monto total retenido por las órdenes autorizadas pendientes.
+pub const PENDING_HOLD_AMOUNT: Metric = Metric {
+    name: "heladeria_gateway_pending_hold_amount",
+    help: "Monto autorizado de las órdenes pendientes de cobro o cancelación.",
+    kind: MetricKind::Gauge,
+};
 
 /// Estructura para manejar el archivo de registro de logs.
 pub struct LogFile {
     file: Arc<Mutex<std::fs::File>>,
+    replication: Arc<Mutex<Replication>>,
+}
+
+/// Estructura con las líneas registradas y los canales hacia los hilos que las envían a cada
+/// gateway de respaldo.
+struct Replication {
+    history: Vec<String>,
+    replicas: Vec<Sender<String>>,
 }
 
 impl LogFile {
@@ -21,14 +60,106 @@ impl LogFile {
                 .open(file_path)
                 .unwrap(),
         ));
-        println!("Log file opened or created: {}", file_path);
-        LogFile { file }
+        log_info!("Log file opened or created: {}", file_path);
+        LogFile {
+            file,
+            replication: Arc::new(Mutex::new(Replication {
+                history: Vec::new(),
+                replicas: Vec::new(),
+            })),
+        }
     }
-    /// Escribe un mensaje en el archivo de log.
+    /// Escribe un mensaje en el archivo de log y lo replica en los gateways de respaldo.
     fn write_log(&self, message: &str) {
+        let mut replication = self.replication.lock().unwrap();
         let mut file = self.file.lock().unwrap();
         writeln!(file, "{}", message).unwrap();
-        println!("Log written: {}", message);
+        // Todos los registros del log empiezan con `<comando>,<id del pedido>`
+        match message.split(',').nth(1).map(str::parse::<u64>) {
+            Some(Ok(order_id)) => log_info!(order = order_id; "Log written: {}", message),
+            _ => log_info!("Log written: {}", message),
+        }
+
+        // Las líneas se envían desde el hilo de cada respaldo: se deja de replicar en los que
+        // se cayeron o tienen la cola llena
+        replication
+            .replicas
+            .retain(|replica| match replica.try_send(message.to_string()) {
+                Ok(()) => true,
+                Err(TrySendError::Full(_)) => {
+                    log_warn!("Standby gateway is too slow. Dropping it");
+                    false
+                }
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+        replication.history.push(message.to_string());
+    }
+    /// Registra un gateway de respaldo, que recibe desde su propio hilo todas las líneas
+    /// registradas hasta el momento y las que se registren después.
+    fn add_replica(&self, replica: TcpStream) {
+        let mut replication = self.replication.lock().unwrap();
+        let history = replication.history.clone();
+        let (tx_replica, rx_replica) = bounded::<String>(REPLICA_QUEUE_CAPACITY);
+        replication.replicas.push(tx_replica);
+        drop(replication);
+
+        log_info!("Standby gateway connected");
+        thread::spawn(move || {
+            if let Err(e) = send_to_replica(replica, history, rx_replica.iter()) {
+                log_warn!("Standby gateway dropped: {}", e);
+            }
+        });
+    }
+}
+
+/// Estructura que representa una transacción registrada en el gateway.
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub screen: String,
+    pub customer: String,
+    pub details: String,
+    pub authorized_amount: u32,
+    pub captured_amount: u32,
+    pub refunded_amount: u32,
+}
+
+impl Transaction {
+    /// Crea una nueva transacción autorizada a partir de la pantalla que la envió, la
+    /// referencia del cliente y el detalle del pedido. Devuelve `None` si el monto del pedido
+    /// no se puede representar.
+    pub fn new(screen: String, customer: String, details: String) -> Option<Self> {
+        let authorized_amount = Self::amount_from_details(&details)?;
+        Some(Transaction {
+            screen,
+            customer,
+            details,
+            authorized_amount,
+            captured_amount: 0,
+            refunded_amount: 0,
+        })
+    }
+
+    /// Calcula el monto de un pedido a partir de su detalle (`<sabor>,<gramos>g;...`). Devuelve
+    /// `None` si el monto no se puede representar.
+    pub fn amount_from_details(details: &str) -> Option<u32> {
+        details
+            .split(';')
+            .filter_map(|flavor| flavor.rsplit(',').next())
+            .filter_map(|grams| grams.trim().trim_end_matches('g').parse::<u32>().ok())
+            .try_fold(0u32, |amount, grams| {
+                amount.checked_add(grams.checked_mul(PRICE_PER_GRAM)?)
+            })
+    }
+
+    /// Devuelve la pantalla y el cliente que originaron la transacción, como se registran en el
+    /// log (`<pantalla>,<cliente>`).
+    pub fn origin(&self) -> String {
+        format!("{},{}", self.screen, self.customer)
+    }
+
+    /// Devuelve el monto cobrado que todavía puede ser reembolsado.
+    pub fn refundable_amount(&self) -> u32 {
+        self.captured_amount - self.refunded_amount
     }
 }
 
@@ -37,7 +168,19 @@ pub struct Gateway {
     rejection_percentage: u8,
     log_file: LogFile,
     current_order_id: Arc<Mutex<u32>>,
-    orders_table: Arc<Mutex<HashMap<u32, String>>>,
+    orders_table: Arc<Mutex<HashMap<u32, Transaction>>>,
+    committed_table: Arc<Mutex<HashMap<u32, Transaction>>>,
+    /// Orden autorizada con cada clave de PREPARE informada por las pantallas.
+    prepared_keys: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+/// Datos que una pantalla informa en su conexión con el gateway.
+#[derive(Default)]
+struct Session {
+    /// Pantalla que se identificó en la conexión (vacía si no lo hizo).
+    screen: String,
+    /// Clave del próximo PREPARE, si la pantalla la informó.
+    prepare_key: Option<String>,
 }
 
 impl Gateway {
@@ -45,6 +188,15 @@ impl Gateway {
     const COMMIT: &'static str = "COMMIT";
     const ABORT: &'static str = "ABORT";
     const ORDERS: &'static str = "ORDERS";
+    const CAPTURE: &'static str = "CAPTURE";
+    const REFUND: &'static str = "REFUND";
+    const CAPTURED: &'static str = "CAPTURED";
+    const REFUNDED: &'static str = "REFUNDED";
+    const REPLICA: &'static str = "REPLICA";
+    const SCREEN: &'static str = "SCREEN";
+    const KEY: &'static str = "KEY";
+    const UNKNOWN_ABORT_REASON: &'static str = "UNKNOWN";
+    const AMOUNT_OVERFLOW: &'static str = "AMOUNT_OVERFLOW";
 
     /// Crea una nueva instancia de `Gateway` con un porcentaje de rechazo y un archivo de log.
     pub fn new(rejection_percentage: u8, log_file: LogFile) -> Self {
@@ -53,13 +205,15 @@ impl Gateway {
             log_file,
             current_order_id: Arc::new(Mutex::new(1)),
             orders_table: Arc::new(Mutex::new(HashMap::new())),
+            committed_table: Arc::new(Mutex::new(HashMap::new())),
+            prepared_keys: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
     /// Inicia el servidor para escuchar conexiones TCP en la dirección especificada.
     pub fn start(&self, address: &str) {
         let listener = TcpListener::bind(address).unwrap();
-        println!("Server listening on {}", address);
+        log_info!("Server listening on {}", address);
 
         for stream in listener.incoming() {
             match stream {
@@ -70,18 +224,172 @@ impl Gateway {
                     });
                 }
                 Err(e) => {
-                    eprintln!("Connection failed: {}", e);
+                    log_error!("Connection failed: {}", e);
                 }
             }
         }
     }
 
-    /// Maneja el comando PREPARE, autorizando el pago y registrando la orden.
-    fn handle_prepare(&self, parts: Vec<&str>, stream: &mut TcpStream) {
-        let order_details = if parts.len() > 1 {
-            parts[1..].join(",")
-        } else {
-            String::new()
+    /// Inicia el gateway de respaldo: replica el log del gateway primario y, cuando se corta la
+    /// conexión con este, comienza a escuchar conexiones en la dirección especificada.
+    ///
+    /// Si no logra conectarse al primario dentro de `STANDBY_CONNECT_GRACE_MS`, devuelve un
+    /// error sin reemplazarlo: que no responda al iniciar no indica que se haya caído, y
+    /// atender sin su log dejaría dos gateways autorizando pagos.
+    pub fn start_standby(&self, primary_address: &str, address: &str) -> Result<(), String> {
+        let primary = Self::connect_to_primary(primary_address)?;
+        log_info!("Replicating primary gateway on {}", primary_address);
+        self.replicate_from(primary);
+        log_warn!("Primary gateway is down. Taking over");
+        self.start(address);
+        Ok(())
+    }
+
+    /// Se conecta al gateway primario, reintentando mientras dure el período de gracia por si
+    /// todavía está iniciando.
+    fn connect_to_primary(primary_address: &str) -> Result<TcpStream, String> {
+        let deadline = Instant::now() + Duration::from_millis(STANDBY_CONNECT_GRACE_MS);
+        loop {
+            match TcpStream::connect(primary_address) {
+                Ok(primary) => return Ok(primary),
+                Err(e) if Instant::now() >= deadline => {
+                    return Err(format!("Primary gateway is unreachable: {}", e))
+                }
+                Err(_) => thread::sleep(Duration::from_millis(STANDBY_CONNECT_RETRY_MS)),
+            }
+        }
+    }
+
+    /// Recibe las líneas del log del gateway primario y las aplica hasta que se corta la conexión.
+    fn replicate_from(&self, mut primary: TcpStream) {
+        let replica_msg = format!("{}\n", Self::REPLICA);
+        if primary.write_all(replica_msg.as_bytes()).is_err() {
+            return;
+        }
+        for line in BufReader::new(primary).lines() {
+            match line {
+                Ok(line) => self.apply_log_line(&line),
+                Err(_) => return,
+            }
+        }
+    }
+
+    /// Aplica una línea del log replicado sobre las tablas del gateway y la registra.
+    fn apply_log_line(&self, line: &str) {
+        let parts: Vec<&str> = line.split(',').collect();
+        let order_id = match parts.get(1).and_then(|id| id.parse::<u32>().ok()) {
+            Some(id) => id,
+            None => return,
+        };
+        let amount = parts.get(2).and_then(|amount| amount.parse::<u32>().ok());
+
+        let mut prepared_keys = self.prepared_keys.lock().unwrap();
+        let mut orders_table = self.orders_table.lock().unwrap();
+        let mut committed_table = self.committed_table.lock().unwrap();
+        match parts[0] {
+            Self::PREPARE => {
+                let mut current_id = self.current_order_id.lock().unwrap();
+                *current_id = (*current_id).max(order_id.saturating_add(1));
+                let field = |index: usize| parts.get(index).unwrap_or(&"").to_string();
+                let details = parts.get(4..).unwrap_or(&[]).join(",");
+                if let Some(transaction) = Transaction::new(field(2), field(3), details) {
+                    orders_table.insert(order_id, transaction);
+                }
+            }
+            Self::COMMIT => {
+                if let Some(mut transaction) = orders_table.remove(&order_id) {
+                    transaction.captured_amount = transaction.authorized_amount;
+                    committed_table.insert(order_id, transaction);
+                }
+                forget_prepare_key(&mut prepared_keys, order_id);
+            }
+            Self::CAPTURE => {
+                if let Some(mut transaction) = orders_table.remove(&order_id) {
+                    transaction.captured_amount = amount.unwrap_or(0);
+                    committed_table.insert(order_id, transaction);
+                }
+                forget_prepare_key(&mut prepared_keys, order_id);
+            }
+            Self::REFUND => {
+                if let Some(transaction) = committed_table.get_mut(&order_id) {
+                    transaction.refunded_amount += amount.unwrap_or(0);
+                }
+            }
+            Self::ABORT => {
+                orders_table.remove(&order_id);
+                forget_prepare_key(&mut prepared_keys, order_id);
+            }
+            Self::KEY => {
+                if let Some(key) = parts.get(2) {
+                    prepared_keys.insert(key.to_string(), order_id);
+                }
+            }
+            _ => return,
+        }
+        update_pending_holds(&orders_table);
+        self.log_file.write_log(line);
+    }
+
+    /// Maneja el comando REPLICA, registrando la conexión como un gateway de respaldo.
+    fn handle_replica(&self, stream: &mut RecordedStream) {
+        match stream.get_ref().try_clone() {
+            Ok(replica) => self.log_file.add_replica(replica),
+            Err(e) => log_error!("Unable to register standby gateway: {}", e),
+        }
+    }
+
+    /// Maneja el comando SCREEN, con el que una pantalla se identifica al conectarse.
+    fn handle_screen(&self, parts: Vec<&str>, session: &mut Session) {
+        match parts.get(1) {
+            Some(screen_id) if !screen_id.is_empty() => session.screen = screen_id.to_string(),
+            _ => log_error!("Invalid screen ID"),
+        }
+    }
+
+    /// Maneja el comando KEY (`KEY,<clave>`), con el que una pantalla informa la clave del
+    /// PREPARE que envía a continuación.
+    fn handle_key(&self, parts: Vec<&str>, session: &mut Session) {
+        match parts.get(1) {
+            Some(key) if !key.is_empty() => session.prepare_key = Some(key.to_string()),
+            _ => log_error!("Invalid PREPARE key"),
+        }
+    }
+
+    /// Maneja el comando PREPARE (`PREPARE,<cliente>,<detalle>`), autorizando el pago y
+    /// registrando la orden junto con la pantalla y el cliente que la originaron.
+    ///
+    /// Si el monto del pedido no se puede representar, se responde `ABORT,AMOUNT_OVERFLOW` sin
+    /// autorizarlo.
+    ///
+    /// Si el PREPARE repite la clave de una orden ya autorizada y todavía abierta, se responde con
+    /// esa orden sin volver a retener el pago: la pantalla reenvía el PREPARE cuando pierde la
+    /// respuesta.
+    fn handle_prepare(&self, parts: Vec<&str>, session: &mut Session, stream: &mut RecordedStream) {
+        let customer = parts.get(1).unwrap_or(&"").to_string();
+        let order_details = parts.get(2..).unwrap_or(&[]).join(",");
+        let screen = session.screen.as_str();
+        let prepare_key = session.prepare_key.take();
+
+        // Se retiene el lock de las claves hasta registrar la orden: dos PREPARE con la misma
+        // clave no pueden autorizar el pago dos veces
+        let mut prepared_keys = self.prepared_keys.lock().unwrap();
+        let prepared_order_id = prepare_key
+            .as_ref()
+            .and_then(|key| prepared_keys.get(key).copied());
+        if let Some(order_id) = prepared_order_id {
+            log_info!(order = order_id; "Repeated PREPARE for order {}", order_id);
+            let response = format!("{},{}\n", Self::COMMIT, order_id);
+            stream.write_all(response.as_bytes()).unwrap();
+            return;
+        }
+
+        let Some(transaction) =
+            Transaction::new(screen.to_string(), customer.clone(), order_details.clone())
+        else {
+            let response = format!("{},{}\n", Self::ABORT, Self::AMOUNT_OVERFLOW);
+            stream.write_all(response.as_bytes()).unwrap();
+            log_warn!("Rejected: amount of {} overflows", order_details);
+            return;
         };
 
         if self.authorize_payment() {
@@ -91,25 +399,37 @@ impl Gateway {
             *current_id += 1;
 
             let mut orders_table = self.orders_table.lock().unwrap();
-            orders_table.insert(new_order_id, order_details.clone());
+            orders_table.insert(new_order_id, transaction);
 
             self.log_file.write_log(&format!(
-                "{},{},{}",
+                "{},{},{},{},{}",
                 Self::PREPARE,
                 new_order_id,
+                screen,
+                customer,
                 order_details
             ));
+            if let Some(key) = prepare_key {
+                self.log_file
+                    .write_log(&format!("{},{},{}", Self::KEY, new_order_id, key));
+                prepared_keys.insert(key, new_order_id);
+            }
+
+            metrics::inc(&PAYMENTS, &[("result", "approved")]);
 
             let response = format!("{},{}\n", Self::COMMIT, new_order_id);
             stream.write_all(response.as_bytes()).unwrap();
         } else {
+            metrics::inc(&PAYMENTS, &[("result", "rejected")]);
             let response = format!("{}\n", Self::ABORT);
             stream.write_all(response.as_bytes()).unwrap();
-            println!("Rejected: {}", order_details);
+            log_info!("Rejected: {}", order_details);
         }
     }
-    /// Maneja el comando COMMIT, confirmando y removiendo la orden de la tabla.
-    fn handle_commit(&self, parts: Vec<&str>, stream: &mut TcpStream) {
+    /// Maneja el comando COMMIT, confirmando y removiendo la orden de la tabla. Se registra en el
+    /// log junto con la pantalla y el cliente que originaron la orden, al igual que los demás
+    /// cierres (`COMMIT,<id>,<pantalla>,<cliente>,<detalle>`).
+    fn handle_commit(&self, parts: Vec<&str>, stream: &mut RecordedStream) {
         let order_id = match parts[1].parse::<u32>() {
             Ok(id) => id,
             Err(_) => {
@@ -119,18 +439,32 @@ impl Gateway {
             }
         };
 
+        let mut prepared_keys = self.prepared_keys.lock().unwrap();
         let mut orders_table = self.orders_table.lock().unwrap();
-        if let Some(order_details) = orders_table.remove(&order_id) {
-            self.log_file
-                .write_log(&format!("{},{},{}", Self::COMMIT, order_id, order_details));
+        if let Some(mut transaction) = orders_table.remove(&order_id) {
+            forget_prepare_key(&mut prepared_keys, order_id);
+            self.log_file.write_log(&format!(
+                "{},{},{},{}",
+                Self::COMMIT,
+                order_id,
+                transaction.origin(),
+                transaction.details
+            ));
+            transaction.captured_amount = transaction.authorized_amount;
+            self.committed_table
+                .lock()
+                .unwrap()
+                .insert(order_id, transaction);
         } else {
             //let msg = "Order ID not found\n";
             //stream.write_all(msg.as_bytes()).unwrap();
         }
     }
 
-    /// Maneja el comando ABORT, cancelando y removiendo la orden de la tabla.
-    fn handle_abort(&self, parts: Vec<&str>, stream: &mut TcpStream) {
+    /// Maneja el comando ABORT (`ABORT,<id>,<motivo>`), cancelando y removiendo la orden de la
+    /// tabla. El motivo se registra en el log antes de la pantalla, el cliente y el detalle de la
+    /// orden.
+    fn handle_abort(&self, parts: Vec<&str>, stream: &mut RecordedStream) {
         let order_id = match parts[1].parse::<u32>() {
             Ok(id) => id,
             Err(_) => {
@@ -140,32 +474,155 @@ impl Gateway {
             }
         };
 
+        // Las pantallas que no informan el motivo se registran como UNKNOWN
+        let reason = parts
+            .get(2)
+            .and_then(|reason| reason.parse::<AbortReason>().ok())
+            .map_or(Self::UNKNOWN_ABORT_REASON.to_string(), |reason| {
+                reason.to_string()
+            });
+
+        let mut prepared_keys = self.prepared_keys.lock().unwrap();
         let mut orders_table = self.orders_table.lock().unwrap();
-        if let Some(order_details) = orders_table.remove(&order_id) {
-            self.log_file
-                .write_log(&format!("{},{},{}", Self::ABORT, order_id, order_details));
+        if let Some(transaction) = orders_table.remove(&order_id) {
+            forget_prepare_key(&mut prepared_keys, order_id);
+            self.log_file.write_log(&format!(
+                "{},{},{},{},{}",
+                Self::ABORT,
+                order_id,
+                reason,
+                transaction.origin(),
+                transaction.details
+            ));
         } else {
             //let msg = "Order ID not found\n";
             //stream.write_all(msg.as_bytes()).unwrap();
         }
     }
+    /// Maneja el comando CAPTURE, cobrando parcialmente una orden autorizada y removiéndola de la tabla.
+    ///
+    /// El monto a cobrar no puede superar el monto autorizado en el PREPARE.
+    fn handle_capture(&self, parts: Vec<&str>, stream: &mut RecordedStream) {
+        let (order_id, amount) = match Self::parse_id_and_amount(&parts) {
+            Ok(values) => values,
+            Err(msg) => {
+                stream.write_all(msg.as_bytes()).unwrap();
+                return;
+            }
+        };
+
+        let mut prepared_keys = self.prepared_keys.lock().unwrap();
+        let mut orders_table = self.orders_table.lock().unwrap();
+        let mut transaction = match orders_table.get(&order_id) {
+            Some(transaction) if amount <= transaction.authorized_amount => {
+                orders_table.remove(&order_id).unwrap()
+            }
+            Some(_) => {
+                let msg = "Amount exceeds authorized amount\n";
+                stream.write_all(msg.as_bytes()).unwrap();
+                return;
+            }
+            None => {
+                let msg = "Order ID not found\n";
+                stream.write_all(msg.as_bytes()).unwrap();
+                return;
+            }
+        };
+        forget_prepare_key(&mut prepared_keys, order_id);
+
+        self.log_file.write_log(&format!(
+            "{},{},{},{},{}",
+            Self::CAPTURE,
+            order_id,
+            amount,
+            transaction.origin(),
+            transaction.details
+        ));
+        transaction.captured_amount = amount;
+        self.committed_table
+            .lock()
+            .unwrap()
+            .insert(order_id, transaction);
+
+        let response = format!("{},{},{}\n", Self::CAPTURED, order_id, amount);
+        stream.write_all(response.as_bytes()).unwrap();
+    }
+
+    /// Maneja el comando REFUND, devolviendo parte de lo cobrado en una orden confirmada.
+    ///
+    /// El total reembolsado no puede superar el monto cobrado en el COMMIT o CAPTURE.
+    fn handle_refund(&self, parts: Vec<&str>, stream: &mut RecordedStream) {
+        let (order_id, amount) = match Self::parse_id_and_amount(&parts) {
+            Ok(values) => values,
+            Err(msg) => {
+                stream.write_all(msg.as_bytes()).unwrap();
+                return;
+            }
+        };
+
+        let mut committed_table = self.committed_table.lock().unwrap();
+        let transaction = match committed_table.get_mut(&order_id) {
+            Some(transaction) => transaction,
+            None => {
+                let msg = "Order ID not found\n";
+                stream.write_all(msg.as_bytes()).unwrap();
+                return;
+            }
+        };
+        if amount > transaction.refundable_amount() {
+            let msg = "Amount exceeds captured amount\n";
+            stream.write_all(msg.as_bytes()).unwrap();
+            return;
+        }
+
+        transaction.refunded_amount += amount;
+        self.log_file.write_log(&format!(
+            "{},{},{},{},{}",
+            Self::REFUND,
+            order_id,
+            amount,
+            transaction.origin(),
+            transaction.details
+        ));
+
+        let response = format!("{},{},{}\n", Self::REFUNDED, order_id, amount);
+        stream.write_all(response.as_bytes()).unwrap();
+    }
+
+    /// Obtiene el ID de orden y el monto de un comando `<COMANDO>,<id>,<monto>`.
+    fn parse_id_and_amount(parts: &[&str]) -> Result<(u32, u32), &'static str> {
+        let order_id = parts
+            .get(1)
+            .and_then(|id| id.parse::<u32>().ok())
+            .ok_or("Invalid order ID\n")?;
+        let amount = parts
+            .get(2)
+            .and_then(|amount| amount.parse::<u32>().ok())
+            .filter(|amount| *amount > 0)
+            .ok_or("Invalid amount\n")?;
+        Ok((order_id, amount))
+    }
+
     /// Maneja el comando ORDERS, enviando la lista de órdenes actuales.
-    fn handle_orders(&self, stream: &mut TcpStream) {
+    fn handle_orders(&self, stream: &mut RecordedStream) {
         let orders_table = self.orders_table.lock().unwrap();
         let mut response = String::from(Self::ORDERS);
-        for (order_id, order_details) in orders_table.iter() {
-            response.push_str(&format!(",{}:{}", order_id, order_details));
+        for (order_id, transaction) in orders_table.iter() {
+            response.push_str(&format!(",{}:{}", order_id, transaction.details));
         }
         response.push('\n');
         stream.write_all(response.as_bytes()).unwrap();
     }
     /// Maneja comandos desconocidos enviando un mensaje de error al cliente.
-    fn handle_unknown_command(&self, stream: &mut TcpStream) {
+    fn handle_unknown_command(&self, stream: &mut RecordedStream) {
         let msg = "Unknown command\n";
         stream.write_all(msg.as_bytes()).unwrap();
     }
-    /// Maneja una conexión TCP, leyendo y procesando mensajes en un bucle.
-    fn handle_connection(&self, mut stream: TcpStream) {
+    /// Maneja una conexión TCP, leyendo y procesando mensajes en un bucle. Lo que se lee y escribe
+    /// por ella se graba si el gateway está grabando.
+    fn handle_connection(&self, stream: TcpStream) {
+        let mut stream = RecordedStream::new(stream, Peer::Screen);
+        let mut session = Session::default();
         loop {
             let mut buffer = [0; 1024];
             let bytes_read = match stream.read(&mut buffer) {
@@ -191,27 +648,46 @@ impl Gateway {
                 }
 
                 // Process the current segment
-                self.handle_message((&parts[index..segment_end]).to_vec(), &mut stream);
+                self.handle_message(
+                    (&parts[index..segment_end]).to_vec(),
+                    &mut session,
+                    &mut stream,
+                );
 
                 // Move index to the next segment
                 index = segment_end;
             }
         }
     }
-    /// Determina si una cadena es un comando válido.
-    fn is_command(part: &str) -> bool {
-        part == Self::PREPARE || part == Self::COMMIT || part == Self::ABORT || part == Self::ORDERS
+    /// Determina si una cadena es un comando válido. Las pantallas no pueden enviar como dato
+    /// un valor que lo sea, porque se tomaría como el comienzo de otro comando.
+    pub fn is_command(part: &str) -> bool {
+        part == Self::PREPARE
+            || part == Self::COMMIT
+            || part == Self::ABORT
+            || part == Self::ORDERS
+            || part == Self::CAPTURE
+            || part == Self::REFUND
+            || part == Self::REPLICA
+            || part == Self::SCREEN
+            || part == Self::KEY
     }
     /// Maneja un mensaje específico en función del comando recibido.
-    fn handle_message(&self, parts: Vec<&str>, stream: &mut TcpStream) {
+    fn handle_message(&self, parts: Vec<&str>, session: &mut Session, stream: &mut RecordedStream) {
         let command = parts[0];
         match command {
-            Self::PREPARE => self.handle_prepare(parts, stream),
+            Self::SCREEN => self.handle_screen(parts, session),
+            Self::KEY => self.handle_key(parts, session),
+            Self::PREPARE => self.handle_prepare(parts, session, stream),
             Self::COMMIT => self.handle_commit(parts, stream),
             Self::ABORT => self.handle_abort(parts, stream),
             Self::ORDERS => self.handle_orders(stream),
+            Self::CAPTURE => self.handle_capture(parts, stream),
+            Self::REFUND => self.handle_refund(parts, stream),
+            Self::REPLICA => self.handle_replica(stream),
             _ => self.handle_unknown_command(stream),
         };
+        update_pending_holds(&self.orders_table.lock().unwrap());
     }
     /// Autoriza el pago generando un número aleatorio y comparándolo con el porcentaje de rechazo.
     fn authorize_payment(&self) -> bool {
@@ -227,9 +703,113 @@ impl Clone for Gateway {
             rejection_percentage: self.rejection_percentage,
             log_file: LogFile {
                 file: Arc::clone(&self.log_file.file),
+                replication: Arc::clone(&self.log_file.replication),
             },
             current_order_id: Arc::clone(&self.current_order_id),
             orders_table: Arc::clone(&self.orders_table),
+            committed_table: Arc::clone(&self.committed_table),
+            prepared_keys: Arc::clone(&self.prepared_keys),
         }
     }
 }
+
+/// Envía a un gateway de respaldo las líneas del log, hasta que se cierra el canal o falla el
+/// envío. Un envío que tarda más de `REPLICA_WRITE_TIMEOUT_MS` se considera fallido.
+fn send_to_replica(
+    mut replica: TcpStream,
+    history: Vec<String>,
+    lines: impl Iterator<Item = String>,
+) -> std::io::Result<()> {
+    replica.set_write_timeout(Some(Duration::from_millis(REPLICA_WRITE_TIMEOUT_MS)))?;
+    for line in history.into_iter().chain(lines) {
+        replica.write_all(format!("{}\n", line).as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Olvida la clave de PREPARE de una orden que se cerró: la pantalla ya no la reenvía.
+fn forget_prepare_key(prepared_keys: &mut HashMap<String, u32>, order_id: u32) {
+    prepared_keys.retain(|_, prepared_id| *prepared_id != order_id);
+}
+
+/// Actualiza las métricas de las órdenes autorizadas pendientes.
+fn update_pending_holds(orders_table: &HashMap<u32, Transaction>) {
+    let amount: u32 = orders_table
+        .values()
+        .map(|transaction| transaction.authorized_amount)
+        .sum();
+    metrics::set(&PENDING_HOLDS, &[], orders_table.len() as f64);
+    metrics::set(&PENDING_HOLD_AMOUNT, &[], amount as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amount_from_details_sums_the_grams_of_each_flavor() {
+        assert_eq!(
+            Transaction::amount_from_details("Mint,100g;Vanilla,150g;"),
+            Some(250 * PRICE_PER_GRAM)
+        );
+        assert_eq!(Transaction::amount_from_details(""), Some(0));
+        // Los gramos que no son números no se cobran
+        assert_eq!(
+            Transaction::amount_from_details("Mint,abc;Vanilla,50g"),
+            Some(50 * PRICE_PER_GRAM)
+        );
+    }
+
+    #[test]
+    fn amount_from_details_detects_overflow() {
+        let half = u32::MAX / PRICE_PER_GRAM / 2 + 1;
+        let details = format!("Mint,{}g;Vanilla,{}g;", half, half);
+        assert_eq!(Transaction::amount_from_details(&details), None);
+        assert!(Transaction::new("0".into(), "ana".into(), details).is_none());
+    }
+
+    #[test]
+    fn origin_joins_screen_and_customer() {
+        let transaction = Transaction::new("3".into(), "ana".into(), "Mint,100g;".into()).unwrap();
+        assert_eq!(transaction.origin(), "3,ana");
+        assert_eq!(transaction.refundable_amount(), 0);
+    }
+
+    #[test]
+    fn commands_are_recognized_exactly() {
+        for command in ["PREPARE", "COMMIT", "ABORT", "KEY", "SCREEN", "REPLICA"] {
+            assert!(Gateway::is_command(command));
+        }
+        assert!(!Gateway::is_command("commit"));
+        assert!(!Gateway::is_command("\"COMMIT\""));
+        assert!(!Gateway::is_command("Mint"));
+    }
+
+    fn test_gateway(name: &str) -> Gateway {
+        let path = std::env::temp_dir().join(format!("{}_{}.log", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        Gateway::new(0, LogFile::new(path.to_str().unwrap()))
+    }
+
+    #[test]
+    fn replicated_keys_are_forgotten_when_their_order_closes() {
+        let gateway = test_gateway("gateway_replicated_keys");
+        gateway.apply_log_line("PREPARE,1,0,ana,Mint,100g;");
+        gateway.apply_log_line("KEY,1,0-1");
+        gateway.apply_log_line("PREPARE,2,0,beto,Mint,100g;");
+        gateway.apply_log_line("KEY,2,0-2");
+        assert_eq!(gateway.prepared_keys.lock().unwrap().len(), 2);
+
+        gateway.apply_log_line("COMMIT,1,0,ana,Mint,100g;");
+        gateway.apply_log_line("ABORT,2,UNKNOWN,0,beto,Mint,100g;");
+        assert!(gateway.prepared_keys.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn replicated_prepare_with_the_last_order_id_does_not_overflow() {
+        let gateway = test_gateway("gateway_last_order_id");
+        gateway.apply_log_line(&format!("PREPARE,{},0,ana,Mint,100g;", u32::MAX));
+        assert_eq!(*gateway.current_order_id.lock().unwrap(), u32::MAX);
+        assert!(gateway.orders_table.lock().unwrap().contains_key(&u32::MAX));
+    }
+}
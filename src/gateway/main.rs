@@ -1,3 +1,5 @@
+use heladeria::common::constants::{GATEWAY_METRICS_PORT, GATEWAY_STANDBY_METRICS_PORT};
+use heladeria::common::{logger, metrics, recorder};
 use heladeria::gateway::gateway::Gateway;
 use heladeria::gateway::gateway::LogFile;
 use std::env;
@@ -5,16 +7,28 @@ use std::fs;
 
 const LOCAL_IP: &str = "127.0.0.1";
 const GATEWAY_PORT: &u16 = &6000;
+const STANDBY_GATEWAY_PORT: &u16 = &6001;
 const LOG_FILE_PATH: &str = "transactions.log";
+const STANDBY_LOG_FILE_PATH: &str = "transactions_standby.log";
 const DEFAULT_REJECTION_PERCENTAGE: u8 = 10;
+const STANDBY_FLAG: &str = "--standby";
 
 /// Punto de entrada principal para el binario `gateway`.
-/// Acepta un argumento opcional para el porcentaje de rechazo.
+/// Acepta un argumento opcional para el porcentaje de rechazo y el flag `--standby`.
 /// Elimina el archivo de log existente y crea una nueva instancia de `Gateway` para manejar conexiones.
+///
+/// Con `--standby` el gateway replica el log del primario (puerto 6000) en su propio archivo
+/// y, si el primario se cae, lo reemplaza escuchando en el puerto 6001. Si no logra conectarse
+/// al primario al iniciar, termina sin reemplazarlo.
 fn main() {
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
+    let is_standby = args.iter().any(|arg| arg == STANDBY_FLAG);
+    args.retain(|arg| arg != STANDBY_FLAG);
     if args.len() > 2 {
-        eprintln!("Usage: {} <rejection-percentage>", args[0]);
+        eprintln!(
+            "Usage: {} <rejection-percentage> [{}]",
+            args[0], STANDBY_FLAG
+        );
         std::process::exit(1);
     }
 
@@ -24,12 +38,38 @@ fn main() {
         args[1].parse().expect("Invalid rejection_percentage")
     };
 
-    if fs::metadata(LOG_FILE_PATH).is_ok() {
-        fs::remove_file(LOG_FILE_PATH).expect("Failed to remove existing log file");
+    let component = if is_standby {
+        "gateway-standby"
+    } else {
+        "gateway"
+    };
+    logger::init(component, None);
+    recorder::init(component, None);
+    metrics::serve(if is_standby {
+        GATEWAY_STANDBY_METRICS_PORT
+    } else {
+        GATEWAY_METRICS_PORT
+    });
+
+    let log_file_path = if is_standby {
+        STANDBY_LOG_FILE_PATH
+    } else {
+        LOG_FILE_PATH
+    };
+    if fs::metadata(log_file_path).is_ok() {
+        fs::remove_file(log_file_path).expect("Failed to remove existing log file");
     }
 
-    let log_file = LogFile::new(LOG_FILE_PATH);
+    let log_file = LogFile::new(log_file_path);
     let gateway = Gateway::new(rejection_percentage, log_file);
     let gateway_address = format!("{}:{}", LOCAL_IP, GATEWAY_PORT);
-    gateway.start(&gateway_address);
+    if is_standby {
+        let standby_address = format!("{}:{}", LOCAL_IP, STANDBY_GATEWAY_PORT);
+        if let Err(e) = gateway.start_standby(&gateway_address, &standby_address) {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    } else {
+        gateway.start(&gateway_address);
+    }
 }
@@ -0,0 +1,279 @@
+/// Este módulo implementa el `Launcher`, que levanta una topología completa del sistema.
+///
+/// Los procesos se inician en el orden que necesitan los anillos (gateway, robots en orden de ID
+/// y pantallas en orden de ID), esperando a que cada uno acepte conexiones antes de iniciar el
+/// siguiente. La salida de cada proceso se reenvía a la del launcher con su nombre como prefijo,
+/// y al detenerse se terminan todos los procesos en el orden inverso al que se iniciaron.
+use std::env;
+use std::io::{self, BufRead, BufReader, Read};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::topology::Topology;
+use crate::common::constants::GATEWAY_ADDRESS;
+
+/// Puerto del robot con ID 0. Cada robot escucha en este puerto más su ID.
+const ROBOT_BASE_PORT: usize = 10000;
+/// Puerto de la pantalla con ID 0. Cada pantalla escucha en este puerto más su ID.
+const SCREEN_BASE_PORT: usize = 3000;
+/// Tiempo máximo, en milisegundos, que se espera a que un proceso acepte conexiones.
+const READY_TIMEOUT_MS: u64 = 10000;
+/// Intervalo, en milisegundos, entre intentos de conexión al esperar un proceso.
+const READY_POLL_INTERVAL_MS: u64 = 100;
+/// Espera, en milisegundos, tras iniciar un proceso que no acepta conexiones al arrancar (el
+/// gateway de respaldo solo escucha cuando se cae el primario).
+const STARTUP_DELAY_MS: u64 = 500;
+/// Intervalo, en milisegundos, con el que se revisa si terminó alguno de los procesos.
+const MONITOR_INTERVAL_MS: u64 = 500;
+
+/// Proceso iniciado por el launcher.
+struct Process {
+    name: String,
+    child: Child,
+    exited: bool,
+}
+
+/// Levanta y detiene los procesos de una topología.
+pub struct Launcher {
+    bin_dir: PathBuf,
+    processes: Vec<Process>,
+}
+
+impl Launcher {
+    /// Crea un launcher que ejecuta los binarios del directorio en el que está el propio launcher.
+    pub fn new() -> Result<Self, String> {
+        let current_exe = env::current_exe().map_err(|e| e.to_string())?;
+        let bin_dir = current_exe
+            .parent()
+            .ok_or("No se encontró el directorio del launcher")?
+            .to_path_buf();
+        Ok(Launcher {
+            bin_dir,
+            processes: Vec::new(),
+        })
+    }
+
+    /// Inicia los procesos de la topología en orden. Si alguno no llega a aceptar conexiones,
+    /// devuelve el error; los procesos ya iniciados se detienen con `shutdown`.
+    pub fn launch(&mut self, topology: &Topology) -> Result<(), String> {
+        let mut gateway_args: Vec<String> = topology
+            .gateway
+            .reject_percentage
+            .iter()
+            .map(|percentage| percentage.to_string())
+            .collect();
+        self.start(
+            Topology::GATEWAY,
+            "gateway",
+            gateway_args.clone(),
+            Some(GATEWAY_ADDRESS.to_string()),
+        )?;
+        if topology.gateway.standby {
+            gateway_args.push("--standby".to_string());
+            self.start(Topology::GATEWAY_STANDBY, "gateway", gateway_args, None)?;
+        }
+
+        for id in 0..topology.robots {
+            self.start(
+                &Topology::robot_name(id),
+                "robot",
+                vec![id.to_string(), topology.robots.to_string()],
+                Some(format!("127.0.0.1:{}", ROBOT_BASE_PORT + id)),
+            )?;
+        }
+
+        let total_screens = topology.screens.len();
+        for (id, screen) in topology.screens.iter().enumerate() {
+            let mut args = vec![
+                id.to_string(),
+                total_screens.to_string(),
+                screen.orders.clone(),
+            ];
+            args.extend(screen.options());
+            self.start(
+                &Topology::screen_name(id),
+                "screen",
+                args,
+                Some(format!("127.0.0.1:{}", SCREEN_BASE_PORT + id)),
+            )?;
+        }
+        println!("[launcher] Topología iniciada");
+        Ok(())
+    }
+
+    /// Inicia el binario `binary` con los argumentos indicados y, si se indica `ready_address`,
+    /// espera a que acepte conexiones en esa dirección.
+    fn start(
+        &mut self,
+        name: &str,
+        binary: &str,
+        args: Vec<String>,
+        ready_address: Option<String>,
+    ) -> Result<(), String> {
+        let program = self.bin_dir.join(binary);
+        if !program.exists() {
+            return Err(format!(
+                "No se encontró {}. Compilá los binarios con `cargo build`",
+                program.display()
+            ));
+        }
+        let mut child = Command::new(&program)
+            .args(&args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("No se pudo iniciar {}: {}", name, e))?;
+        forward_output(name.to_string(), child.stdout.take(), false);
+        forward_output(name.to_string(), child.stderr.take(), true);
+        println!(
+            "[launcher] {} iniciado: {} {}",
+            name,
+            binary,
+            args.join(" ")
+        );
+        self.processes.push(Process {
+            name: name.to_string(),
+            child,
+            exited: false,
+        });
+
+        match ready_address {
+            Some(address) => self.wait_ready(&address),
+            None => {
+                thread::sleep(Duration::from_millis(STARTUP_DELAY_MS));
+                Ok(())
+            }
+        }
+    }
+
+    /// Espera a que el último proceso iniciado acepte conexiones en `address`.
+    fn wait_ready(&mut self, address: &str) -> Result<(), String> {
+        let process = match self.processes.last_mut() {
+            Some(process) => process,
+            None => return Ok(()),
+        };
+        let timeout = Instant::now() + Duration::from_millis(READY_TIMEOUT_MS);
+        loop {
+            if TcpStream::connect(address).is_ok() {
+                return Ok(());
+            }
+            if let Ok(Some(status)) = process.child.try_wait() {
+                process.exited = true;
+                return Err(format!(
+                    "{} terminó antes de estar listo ({})",
+                    process.name, status
+                ));
+            }
+            if Instant::now() >= timeout {
+                return Err(format!(
+                    "{} no aceptó conexiones en {} después de {} ms",
+                    process.name, address, READY_TIMEOUT_MS
+                ));
+            }
+            thread::sleep(Duration::from_millis(READY_POLL_INTERVAL_MS));
+        }
+    }
+
+    /// Deja correr el sistema durante `duration` o, si no se indica, hasta que se presione Enter
+    /// (o se cierre la entrada estándar). Mientras tanto informa los procesos que terminan.
+    pub fn run(&mut self, duration: Option<Duration>) {
+        let stop = match duration {
+            Some(_) => None,
+            None => {
+                println!("[launcher] Presioná Enter para detener el sistema");
+                Some(wait_for_enter())
+            }
+        };
+        let started = Instant::now();
+        loop {
+            if duration.is_some_and(|duration| started.elapsed() >= duration) {
+                break;
+            }
+            if stop.as_ref().is_some_and(|stop| stop.try_recv().is_ok()) {
+                break;
+            }
+            self.report_exited();
+            thread::sleep(Duration::from_millis(MONITOR_INTERVAL_MS));
+        }
+    }
+
+    /// Termina el proceso `name` (por ejemplo, `robot 0`), simulando su caída.
+    pub fn kill(&mut self, name: &str) -> Result<(), String> {
+        let process = self
+            .processes
+            .iter_mut()
+            .find(|process| process.name == name)
+            .ok_or(format!("No existe el proceso {}", name))?;
+        if process.exited {
+            return Err(format!("{} ya había terminado", name));
+        }
+        process.child.kill().map_err(|e| e.to_string())?;
+        let _ = process.child.wait();
+        process.exited = true;
+        println!("[launcher] {} terminado", name);
+        Ok(())
+    }
+
+    /// Informa los procesos que terminaron desde la última revisión.
+    fn report_exited(&mut self) {
+        for process in self.processes.iter_mut().filter(|process| !process.exited) {
+            if let Ok(Some(status)) = process.child.try_wait() {
+                process.exited = true;
+                println!("[launcher] {} terminó ({})", process.name, status);
+            }
+        }
+    }
+
+    /// Detiene los procesos en el orden inverso al que se iniciaron, esperando a que termine
+    /// cada uno.
+    pub fn shutdown(&mut self) {
+        for mut process in self.processes.drain(..).rev() {
+            if !process.exited {
+                let _ = process.child.kill();
+            }
+            let _ = process.child.wait();
+            println!("[launcher] {} detenido", process.name);
+        }
+    }
+}
+
+impl Drop for Launcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+/// Reenvía cada línea de la salida de un proceso a la del launcher, con el nombre del proceso
+/// como prefijo.
+fn forward_output<R: Read + Send + 'static>(name: String, output: Option<R>, is_stderr: bool) {
+    let output = match output {
+        Some(output) => output,
+        None => return,
+    };
+    thread::spawn(move || {
+        for line in BufReader::new(output).lines().map_while(Result::ok) {
+            if is_stderr {
+                eprintln!("[{}] {}", name, line);
+            } else {
+                println!("[{}] {}", name, line);
+            }
+        }
+    });
+}
+
+/// Devuelve un canal que recibe un mensaje cuando se presiona Enter o se cierra la entrada
+/// estándar.
+fn wait_for_enter() -> Receiver<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut line = String::new();
+        let _ = io::stdin().lock().read_line(&mut line);
+        let _ = tx.send(());
+    });
+    rx
+}
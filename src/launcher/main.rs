@@ -0,0 +1,44 @@
+use heladeria::launcher::launcher::Launcher;
+use heladeria::launcher::topology::Topology;
+use std::env;
+use std::time::Duration;
+
+/// Punto de entrada principal para el binario `launcher`.
+/// Acepta un argumento con la ruta del archivo JSON que describe la topología a levantar.
+/// Inicia el gateway, los robots y las pantallas en orden, esperando a que cada uno esté listo,
+/// muestra la salida de todos con el nombre de cada proceso como prefijo y, al cumplirse la
+/// duración de la topología (o al presionar Enter), los detiene.
+///
+/// Los binarios se toman del mismo directorio que el launcher, por lo que deben compilarse antes.
+///
+/// Uso: cargo build && cargo run --bin launcher topology.json
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <topology-path>", args[0]);
+        std::process::exit(1);
+    }
+
+    let topology = match Topology::from_file(&args[1]) {
+        Ok(topology) => topology,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut launcher = match Launcher::new() {
+        Ok(launcher) => launcher,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = launcher.launch(&topology) {
+        eprintln!("Error: {}", e);
+        launcher.shutdown();
+        std::process::exit(1);
+    }
+    launcher.run(topology.duration_secs.map(Duration::from_secs));
+    launcher.shutdown();
+}
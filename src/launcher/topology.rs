@@ -0,0 +1,238 @@
+/// Este módulo define la topología que levanta el `launcher`, leída de un archivo JSON:
+///
+/// ```json
+/// {
+///   "gateway": { "reject_percentage": 10, "standby": true },
+///   "robots": 2,
+///   "screens": [
+///     { "orders": "orders.json" },
+///     { "orders": "orders.json", "http": 8080, "window": 2, "deadline": 60 }
+///   ],
+///   "duration_secs": 60
+/// }
+/// ```
+use serde::Deserialize;
+use std::fs;
+
+use crate::screen::order_source::OrderSource;
+
+/// Configuración del gateway de pagos.
+#[derive(Debug, Default, Deserialize)]
+pub struct GatewayConfig {
+    /// Porcentaje de pagos rechazados. Si no se indica, se usa el predeterminado del gateway.
+    #[serde(default)]
+    pub reject_percentage: Option<u8>,
+    /// Si es `true`, también se levanta un gateway de respaldo.
+    #[serde(default)]
+    pub standby: bool,
+}
+
+/// Configuración de una pantalla.
+#[derive(Debug, Deserialize)]
+pub struct ScreenConfig {
+    /// Archivo o directorio de pedidos de la pantalla.
+    pub orders: String,
+    /// Puerto de la API HTTP de pedidos, si se habilita.
+    #[serde(default)]
+    pub http: Option<u16>,
+    /// Cantidad máxima de pedidos en curso.
+    #[serde(default)]
+    pub window: Option<usize>,
+    /// Profundidad objetivo de la cola de pedidos de los robots.
+    #[serde(default)]
+    pub queue_target: Option<usize>,
+    /// Plazo de entrega de los pedidos, en segundos.
+    #[serde(default)]
+    pub deadline: Option<u64>,
+}
+
+impl ScreenConfig {
+    /// Devuelve los argumentos opcionales del binario `screen` para esta pantalla.
+    pub fn options(&self) -> Vec<String> {
+        let mut options = Vec::new();
+        let mut push = |name: &str, value: Option<String>| {
+            if let Some(value) = value {
+                options.push(name.to_string());
+                options.push(value);
+            }
+        };
+        push("--http", self.http.map(|port| port.to_string()));
+        push("--window", self.window.map(|window| window.to_string()));
+        push(
+            "--queue-target",
+            self.queue_target.map(|target| target.to_string()),
+        );
+        push(
+            "--deadline",
+            self.deadline.map(|deadline| deadline.to_string()),
+        );
+        options
+    }
+}
+
+/// Topología completa del sistema: gateway, robots y pantallas.
+#[derive(Debug, Deserialize)]
+pub struct Topology {
+    #[serde(default)]
+    pub gateway: GatewayConfig,
+    /// Cantidad de robots.
+    pub robots: usize,
+    /// Pantallas, en orden de ID.
+    pub screens: Vec<ScreenConfig>,
+    /// Segundos que corre el sistema antes de detenerlo. Si no se indica, corre hasta que se
+    /// presione Enter.
+    #[serde(default)]
+    pub duration_secs: Option<u64>,
+}
+
+impl Topology {
+    /// Tamaño mínimo de los anillos de robots y de pantallas.
+    pub const MIN_RING_SIZE: usize = 2;
+    /// Nombre del proceso del gateway.
+    pub const GATEWAY: &'static str = "gateway";
+    /// Nombre del proceso del gateway de respaldo.
+    pub const GATEWAY_STANDBY: &'static str = "gateway standby";
+
+    /// Nombre del proceso del robot `id`.
+    pub fn robot_name(id: usize) -> String {
+        format!("robot {}", id)
+    }
+
+    /// Nombre del proceso de la pantalla `id`.
+    pub fn screen_name(id: usize) -> String {
+        format!("screen {}", id)
+    }
+
+    /// Devuelve los nombres de todos los procesos de la topología, en el orden en que se inician.
+    pub fn process_names(&self) -> Vec<String> {
+        let mut names = vec![Self::GATEWAY.to_string()];
+        if self.gateway.standby {
+            names.push(Self::GATEWAY_STANDBY.to_string());
+        }
+        names.extend((0..self.robots).map(Self::robot_name));
+        names.extend((0..self.screens.len()).map(Self::screen_name));
+        names
+    }
+
+    /// Lee y valida la topología del archivo JSON indicado.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_json(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Lee y valida una topología en JSON.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let topology: Topology = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        topology.validate()?;
+        Ok(topology)
+    }
+
+    /// Verifica que los anillos tengan el tamaño mínimo y que las pantallas lean sus pedidos de
+    /// archivos o directorios, ya que el launcher no les pasa la entrada estándar.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.robots < Self::MIN_RING_SIZE {
+            return Err(format!(
+                "Se necesitan al menos {} robots",
+                Self::MIN_RING_SIZE
+            ));
+        }
+        if self.screens.len() < Self::MIN_RING_SIZE {
+            return Err(format!(
+                "Se necesitan al menos {} pantallas",
+                Self::MIN_RING_SIZE
+            ));
+        }
+        for (id, screen) in self.screens.iter().enumerate() {
+            match OrderSource::from_arg(&screen.orders) {
+                OrderSource::File(_) | OrderSource::Directory(_) => {}
+                _ => {
+                    return Err(format!(
+                        "La pantalla {} debe leer sus pedidos de un archivo o directorio",
+                        id
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn optional_fields_take_their_defaults() {
+        let topology = Topology::from_json(
+            r#"{"robots": 2, "screens": [{"orders": "a.json"}, {"orders": "b.json"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(topology.gateway.reject_percentage, None);
+        assert!(!topology.gateway.standby);
+        assert_eq!(topology.duration_secs, None);
+        assert!(topology.screens[0].options().is_empty());
+        assert_eq!(
+            topology.process_names(),
+            vec!["gateway", "robot 0", "robot 1", "screen 0", "screen 1"]
+        );
+    }
+
+    #[test]
+    fn screen_options_follow_the_screen_arguments() {
+        let topology = Topology::from_json(
+            r#"{
+                "gateway": {"reject_percentage": 10, "standby": true},
+                "robots": 3,
+                "screens": [
+                    {"orders": "a.json", "http": 8080, "window": 2},
+                    {"orders": "b.json", "queue_target": 4, "deadline": 60}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(topology.gateway.reject_percentage, Some(10));
+        assert_eq!(
+            topology.screens[0].options(),
+            ["--http", "8080", "--window", "2"]
+        );
+        assert_eq!(
+            topology.screens[1].options(),
+            ["--queue-target", "4", "--deadline", "60"]
+        );
+        assert_eq!(
+            topology.process_names()[..3],
+            ["gateway", "gateway standby", "robot 0"]
+        );
+    }
+
+    #[test]
+    fn rings_smaller_than_the_minimum_are_rejected() {
+        assert!(Topology::from_json(
+            r#"{"robots": 1, "screens": [{"orders": "a.json"}, {"orders": "b.json"}]}"#
+        )
+        .is_err());
+        assert!(
+            Topology::from_json(r#"{"robots": 2, "screens": [{"orders": "a.json"}]}"#).is_err()
+        );
+    }
+
+    #[test]
+    fn screens_cannot_read_from_the_terminal() {
+        for orders in [OrderSource::STDIN_ARG, OrderSource::INTERACTIVE_ARG] {
+            let json = format!(
+                r#"{{"robots": 2, "screens": [{{"orders": "a.json"}}, {{"orders": "{}"}}]}}"#,
+                orders
+            );
+            let error = Topology::from_json(&json).unwrap_err();
+            assert!(error.contains("pantalla 1"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        assert!(Topology::from_json("").is_err());
+        assert!(Topology::from_json(r#"{"robots": "two", "screens": []}"#).is_err());
+        assert!(Topology::from_json(r#"{"robots": 2}"#).is_err());
+        assert!(Topology::from_file("no-such-topology.json").is_err());
+    }
+}
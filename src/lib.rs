@@ -1,4 +1,11 @@
 pub mod common;
+pub mod ctl;
+pub mod decode;
 pub mod gateway;
+pub mod launcher;
+pub mod loadgen;
+pub mod reconcile;
+pub mod replay;
 pub mod robot;
+pub mod scenario;
 pub mod screen;
@@ -0,0 +1,481 @@
+/// Este módulo implementa el generador de pedidos sintéticos del binario `loadgen`.
+///
+/// Un `LoadProfile` describe la carga a generar: cantidad de pedidos, popularidad de cada
+/// sabor, tamaños de vasito, tasa de llegada con horas pico opcionales y porcentaje de pedidos
+/// con sabores inválidos. El `OrderGenerator` produce los pedidos con sus momentos de llegada,
+/// que pueden escribirse en cualquiera de los formatos de entrada de las pantallas o enviarse a
+/// una pantalla a medida que llegan.
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::common::flavors::IceCreamFlavor;
+use crate::screen::order_reader::OrderFormat;
+
+/// Sabores que no existen, usados para generar pedidos inválidos.
+const INVALID_FLAVORS: [&str; 3] = ["Pistachio", "Banana", "Lemon"];
+/// Gramos mínimos de cada sabor de un vasito.
+const MIN_FLAVOR_GRAMS: u32 = 10;
+
+/// Horas pico: durante los primeros `duration_secs` segundos de cada período de `period_secs`
+/// segundos, los pedidos llegan `factor` veces más rápido.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RushHour {
+    pub period_secs: u64,
+    pub duration_secs: u64,
+    pub factor: f64,
+}
+
+/// Descripción de la carga a generar, leída de un archivo JSON. Los campos que no se indican
+/// toman su valor predeterminado.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoadProfile {
+    /// Cantidad de pedidos.
+    pub orders: usize,
+    /// Peso relativo de cada sabor al elegir los sabores de un vasito.
+    pub flavor_weights: BTreeMap<String, u32>,
+    /// Tamaños de vasito, en gramos, elegidos con igual probabilidad.
+    pub cup_sizes: Vec<u32>,
+    /// Cantidad máxima de sabores por vasito.
+    pub max_flavors: usize,
+    /// Pedidos por segundo fuera de las horas pico.
+    pub rate_per_sec: f64,
+    /// Horas pico, si las hay.
+    pub rush: Option<RushHour>,
+    /// Porcentaje de pedidos con un sabor inválido.
+    pub invalid_percentage: u8,
+    /// Cantidad de clientes distintos que hacen los pedidos.
+    pub customers: usize,
+    /// Semilla del generador. Con la misma semilla se generan los mismos pedidos.
+    pub seed: Option<u64>,
+}
+
+impl Default for LoadProfile {
+    fn default() -> Self {
+        LoadProfile {
+            orders: 100,
+            flavor_weights: IceCreamFlavor::iter()
+                .map(|flavor| (flavor.to_string(), 1))
+                .collect(),
+            cup_sizes: vec![250, 500, 1000],
+            max_flavors: 3,
+            rate_per_sec: 1.0,
+            rush: None,
+            invalid_percentage: 0,
+            customers: 50,
+            seed: None,
+        }
+    }
+}
+
+impl LoadProfile {
+    /// Lee y valida el perfil del archivo JSON indicado.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let profile: LoadProfile =
+            serde_json::from_str(&contents).map_err(|e| format!("{}: {}", path, e))?;
+        profile.validate()?;
+        Ok(profile)
+    }
+
+    /// Verifica que el perfil permita generar pedidos.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.flavor_weights.values().all(|weight| *weight == 0) {
+            return Err("Al menos un sabor debe tener peso mayor a cero".to_string());
+        }
+        if self.cup_sizes.is_empty() || self.cup_sizes.contains(&0) {
+            return Err("Los tamaños de vasito deben ser mayores a cero".to_string());
+        }
+        if self.max_flavors == 0 {
+            return Err("Los vasitos deben tener al menos un sabor".to_string());
+        }
+        if self.rate_per_sec <= 0.0 {
+            return Err("La tasa de llegada debe ser mayor a cero".to_string());
+        }
+        if let Some(ref rush) = self.rush {
+            if rush.period_secs == 0 || rush.factor <= 0.0 {
+                return Err(
+                    "Las horas pico deben tener período y factor mayores a cero".to_string()
+                );
+            }
+        }
+        if self.invalid_percentage > 100 {
+            return Err("El porcentaje de pedidos inválidos debe estar entre 0 y 100".to_string());
+        }
+        if self.customers == 0 {
+            return Err("Debe haber al menos un cliente".to_string());
+        }
+        Ok(())
+    }
+
+    /// Devuelve la tasa de llegada, en pedidos por segundo, en el momento `at`.
+    fn rate_at(&self, at: Duration) -> f64 {
+        match self.rush {
+            Some(ref rush) if at.as_secs() % rush.period_secs < rush.duration_secs => {
+                self.rate_per_sec * rush.factor
+            }
+            _ => self.rate_per_sec,
+        }
+    }
+}
+
+/// Sabor de un pedido generado, en el formato de entrada de las pantallas.
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedFlavor {
+    pub name: String,
+    pub grams: u32,
+}
+
+/// Pedido generado, en el formato de entrada de las pantallas, junto con su momento de llegada.
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedOrder {
+    pub id: String,
+    pub customer: String,
+    pub flavors: Vec<GeneratedFlavor>,
+    /// Momento de llegada, desde el inicio de la carga. No forma parte del formato de entrada.
+    #[serde(skip)]
+    pub at: Duration,
+}
+
+impl GeneratedOrder {
+    /// Devuelve el pedido como una línea NDJSON.
+    pub fn to_ndjson(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Devuelve el pedido como filas CSV `order,customer,flavor,grams`, una por sabor.
+    pub fn to_csv_rows(&self) -> String {
+        self.flavors
+            .iter()
+            .map(|flavor| {
+                format!(
+                    "{},{},{},{}\n",
+                    self.id, self.customer, flavor.name, flavor.grams
+                )
+            })
+            .collect()
+    }
+}
+
+/// Genera los pedidos descriptos por un `LoadProfile`.
+pub struct OrderGenerator {
+    profile: LoadProfile,
+    rng: StdRng,
+    flavors: Vec<String>,
+    weights: Vec<u32>,
+    generated: usize,
+    clock: Duration,
+}
+
+impl OrderGenerator {
+    /// Crea un generador para el perfil indicado, que debe ser válido.
+    pub fn new(profile: LoadProfile) -> Self {
+        let rng = match profile.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        let (flavors, weights) = profile
+            .flavor_weights
+            .iter()
+            .filter(|(_, weight)| **weight > 0)
+            .map(|(flavor, weight)| (flavor.clone(), *weight))
+            .unzip();
+        OrderGenerator {
+            profile,
+            rng,
+            flavors,
+            weights,
+            generated: 0,
+            clock: Duration::ZERO,
+        }
+    }
+
+    /// Elige hasta `count` sabores distintos según su popularidad.
+    fn pick_flavors(&mut self, count: usize) -> Vec<String> {
+        let mut weights = self.weights.clone();
+        let mut picked = Vec::new();
+        while picked.len() < count {
+            let index = match WeightedIndex::new(&weights) {
+                Ok(distribution) => distribution.sample(&mut self.rng),
+                // No quedan sabores con peso mayor a cero
+                Err(_) => break,
+            };
+            picked.push(self.flavors[index].clone());
+            weights[index] = 0;
+        }
+        picked
+    }
+
+    /// Reparte los gramos del vasito entre sus sabores en partes iguales, redondeadas a
+    /// múltiplos de `MIN_FLAVOR_GRAMS`. El último sabor se lleva el resto.
+    fn split_cup(size: u32, flavors: Vec<String>) -> Vec<GeneratedFlavor> {
+        let count = flavors.len() as u32;
+        let share = (size / count / MIN_FLAVOR_GRAMS * MIN_FLAVOR_GRAMS).max(MIN_FLAVOR_GRAMS);
+        flavors
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| GeneratedFlavor {
+                name,
+                grams: if index as u32 == count - 1 {
+                    size.saturating_sub(share * (count - 1))
+                        .max(MIN_FLAVOR_GRAMS)
+                } else {
+                    share
+                },
+            })
+            .collect()
+    }
+
+    /// Avanza el reloj de la carga hasta la llegada del próximo pedido, con tiempos entre
+    /// llegadas exponenciales según la tasa del momento.
+    fn next_arrival(&mut self) -> Duration {
+        let rate = self.profile.rate_at(self.clock);
+        let uniform: f64 = self.rng.gen_range(f64::EPSILON..1.0);
+        self.clock += Duration::from_secs_f64(-uniform.ln() / rate);
+        self.clock
+    }
+}
+
+impl Iterator for OrderGenerator {
+    type Item = GeneratedOrder;
+
+    fn next(&mut self) -> Option<GeneratedOrder> {
+        if self.generated >= self.profile.orders {
+            return None;
+        }
+        self.generated += 1;
+        let at = if self.generated == 1 {
+            Duration::ZERO
+        } else {
+            self.next_arrival()
+        };
+
+        let size = self.profile.cup_sizes[self.rng.gen_range(0..self.profile.cup_sizes.len())];
+        let max_flavors = self.profile.max_flavors.min(self.flavors.len());
+        let count = self.rng.gen_range(1..=max_flavors);
+        let mut flavors = Self::split_cup(size, self.pick_flavors(count));
+        if self.rng.gen_range(0..100) < self.profile.invalid_percentage {
+            let index = self.rng.gen_range(0..flavors.len());
+            flavors[index].name =
+                INVALID_FLAVORS[self.rng.gen_range(0..INVALID_FLAVORS.len())].to_string();
+        }
+        let customer = self.rng.gen_range(1..=self.profile.customers);
+
+        Some(GeneratedOrder {
+            id: format!("gen-{}", self.generated),
+            customer: format!("customer-{}", customer),
+            flavors,
+            at,
+        })
+    }
+}
+
+/// Documento JSON con todos los pedidos (`{"orders": [...]}`).
+#[derive(Serialize)]
+struct OrdersDocument<'a> {
+    orders: &'a [GeneratedOrder],
+}
+
+/// Escribe los pedidos en `path`, en el formato de entrada de las pantallas que corresponde a
+/// su extensión. Devuelve la cantidad de pedidos escritos.
+pub fn write_orders(path: &str, orders: &[GeneratedOrder]) -> Result<usize, String> {
+    let contents = match OrderFormat::from_path(Path::new(path)) {
+        Some(OrderFormat::Json) => {
+            serde_json::to_string_pretty(&OrdersDocument { orders }).map_err(|e| e.to_string())?
+        }
+        Some(OrderFormat::Ndjson) => orders
+            .iter()
+            .map(|order| order.to_ndjson() + "\n")
+            .collect(),
+        Some(OrderFormat::Csv) => {
+            let rows: String = orders.iter().map(GeneratedOrder::to_csv_rows).collect();
+            format!("order,customer,flavor,grams\n{}", rows)
+        }
+        None => return Err(format!("{} no es un archivo .json, .ndjson o .csv", path)),
+    };
+    fs::write(path, contents).map_err(|e| format!("{}: {}", path, e))?;
+    Ok(orders.len())
+}
+
+/// Deposita `order` en el directorio vigilado `dir` como un archivo NDJSON. El archivo se
+/// escribe con otra extensión y se renombra al terminar, para que la pantalla no lo lea a medias.
+pub fn spool_order(dir: &Path, order: &GeneratedOrder) -> Result<(), String> {
+    let partial = dir.join(format!("{}.partial", order.id));
+    let mut file = fs::File::create(&partial).map_err(|e| e.to_string())?;
+    writeln!(file, "{}", order.to_ndjson()).map_err(|e| e.to_string())?;
+    fs::rename(&partial, dir.join(format!("{}.ndjson", order.id))).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(json: &str) -> LoadProfile {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn seeded(orders: usize) -> LoadProfile {
+        LoadProfile {
+            orders,
+            seed: Some(7),
+            ..LoadProfile::default()
+        }
+    }
+
+    #[test]
+    fn missing_fields_take_their_default() {
+        let loaded = profile(
+            r#"{"orders": 5, "rush": {"period_secs": 60, "duration_secs": 10, "factor": 3.0}}"#,
+        );
+
+        assert_eq!(loaded.orders, 5);
+        assert_eq!(loaded.cup_sizes, [250, 500, 1000]);
+        assert_eq!(loaded.flavor_weights.len(), IceCreamFlavor::iter().count());
+        assert!(loaded.validate().is_ok());
+        assert!(serde_json::from_str::<LoadProfile>(r#"{"orders": -1}"#).is_err());
+        assert!(serde_json::from_str::<LoadProfile>(r#"{"rush": {"factor": 2.0}}"#).is_err());
+    }
+
+    #[test]
+    fn invalid_profiles_are_rejected() {
+        for json in [
+            r#"{"flavor_weights": {"Mint": 0}}"#,
+            r#"{"flavor_weights": {}}"#,
+            r#"{"cup_sizes": []}"#,
+            r#"{"cup_sizes": [250, 0]}"#,
+            r#"{"max_flavors": 0}"#,
+            r#"{"rate_per_sec": 0.0}"#,
+            r#"{"rush": {"period_secs": 0, "duration_secs": 0, "factor": 2.0}}"#,
+            r#"{"rush": {"period_secs": 60, "duration_secs": 10, "factor": 0.0}}"#,
+            r#"{"invalid_percentage": 101}"#,
+            r#"{"customers": 0}"#,
+        ] {
+            assert!(profile(json).validate().is_err(), "{}", json);
+        }
+    }
+
+    #[test]
+    fn rush_hours_multiply_the_rate() {
+        let loaded = profile(
+            r#"{"rate_per_sec": 2.0, "rush": {"period_secs": 60, "duration_secs": 10, "factor": 3.0}}"#,
+        );
+
+        assert_eq!(loaded.rate_at(Duration::from_secs(5)), 6.0);
+        assert_eq!(loaded.rate_at(Duration::from_secs(30)), 2.0);
+        assert_eq!(loaded.rate_at(Duration::from_secs(65)), 6.0);
+    }
+
+    #[test]
+    fn the_same_seed_generates_the_same_orders() {
+        let first: Vec<String> = OrderGenerator::new(seeded(20))
+            .map(|order| order.to_ndjson())
+            .collect();
+        let second: Vec<String> = OrderGenerator::new(seeded(20))
+            .map(|order| order.to_ndjson())
+            .collect();
+
+        assert_eq!(first.len(), 20);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn orders_follow_the_profile() {
+        let orders: Vec<GeneratedOrder> = OrderGenerator::new(seeded(200)).collect();
+
+        assert_eq!(orders[0].at, Duration::ZERO);
+        assert_eq!(orders[199].id, "gen-200");
+        for pair in orders.windows(2) {
+            assert!(pair[0].at <= pair[1].at);
+        }
+        for order in &orders {
+            let grams: u32 = order.flavors.iter().map(|flavor| flavor.grams).sum();
+            assert!([250, 500, 1000].contains(&grams), "{:?}", order);
+            assert!((1..=3).contains(&order.flavors.len()));
+            assert!(order
+                .flavors
+                .iter()
+                .all(|flavor| flavor.name.parse::<IceCreamFlavor>().is_ok()));
+        }
+    }
+
+    #[test]
+    fn invalid_percentage_adds_unknown_flavors() {
+        let all_invalid = LoadProfile {
+            invalid_percentage: 100,
+            ..seeded(20)
+        };
+
+        for order in OrderGenerator::new(all_invalid) {
+            assert!(order
+                .flavors
+                .iter()
+                .any(|flavor| INVALID_FLAVORS.contains(&flavor.name.as_str())));
+        }
+    }
+
+    #[test]
+    fn flavors_with_zero_weight_are_never_picked() {
+        let only_mint = LoadProfile {
+            flavor_weights: BTreeMap::from([("Mint".to_string(), 1), ("Vanilla".to_string(), 0)]),
+            ..seeded(20)
+        };
+
+        for order in OrderGenerator::new(only_mint) {
+            assert_eq!(order.flavors.len(), 1);
+            assert_eq!(order.flavors[0].name, "Mint");
+        }
+    }
+
+    #[test]
+    fn cups_are_split_in_multiples_of_the_minimum() {
+        let split = |size, count| -> Vec<u32> {
+            let flavors = (0..count).map(|index| format!("f{}", index)).collect();
+            OrderGenerator::split_cup(size, flavors)
+                .into_iter()
+                .map(|flavor| flavor.grams)
+                .collect()
+        };
+
+        assert_eq!(split(250, 1), [250]);
+        assert_eq!(split(250, 3), [80, 80, 90]);
+        assert_eq!(split(1000, 4), [250, 250, 250, 250]);
+        // Un vasito chico no deja sabores sin gramos
+        assert_eq!(split(15, 3), [10, 10, 10]);
+    }
+
+    #[test]
+    fn orders_are_written_in_the_screen_formats() {
+        let order = GeneratedOrder {
+            id: "gen-1".to_string(),
+            customer: "customer-2".to_string(),
+            flavors: vec![
+                GeneratedFlavor {
+                    name: "Mint".to_string(),
+                    grams: 100,
+                },
+                GeneratedFlavor {
+                    name: "Vanilla".to_string(),
+                    grams: 150,
+                },
+            ],
+            at: Duration::from_secs(3),
+        };
+
+        assert_eq!(
+            order.to_ndjson(),
+            r#"{"id":"gen-1","customer":"customer-2","flavors":[{"name":"Mint","grams":100},{"name":"Vanilla","grams":150}]}"#
+        );
+        assert_eq!(
+            order.to_csv_rows(),
+            "gen-1,customer-2,Mint,100\ngen-1,customer-2,Vanilla,150\n"
+        );
+        assert!(write_orders("orders.txt", &[order]).is_err());
+    }
+}
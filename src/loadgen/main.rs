@@ -0,0 +1,118 @@
+use heladeria::loadgen::generator::{
+    spool_order, write_orders, GeneratedOrder, LoadProfile, OrderGenerator,
+};
+use std::env;
+use std::io::{self, Write};
+use std::path::Path;
+use std::thread;
+use std::time::Instant;
+
+/// Argumento de línea de comandos que indica el archivo JSON con el perfil de carga.
+const PROFILE_ARG: &str = "--profile";
+/// Argumento de línea de comandos que indica la cantidad de pedidos.
+const ORDERS_ARG: &str = "--orders";
+/// Argumento de línea de comandos que indica los pedidos por segundo.
+const RATE_ARG: &str = "--rate";
+/// Argumento de línea de comandos que indica el porcentaje de pedidos con sabores inválidos.
+const INVALID_ARG: &str = "--invalid";
+/// Argumento de línea de comandos que indica la semilla del generador.
+const SEED_ARG: &str = "--seed";
+/// Argumento de línea de comandos que indica el archivo en el que se escriben los pedidos.
+const OUTPUT_ARG: &str = "--output";
+/// Argumento de línea de comandos que indica el directorio vigilado en el que se depositan los
+/// pedidos a medida que llegan.
+const SPOOL_ARG: &str = "--spool";
+
+/// Destino de los pedidos generados.
+enum Destination {
+    /// Pedidos NDJSON escritos en la salida estándar a medida que llegan.
+    Stdout,
+    /// Archivo de pedidos escrito de una vez.
+    File(String),
+    /// Directorio vigilado de una pantalla, con un archivo por pedido a medida que llegan.
+    Spool(String),
+}
+
+/// Punto de entrada principal para el binario `loadgen`.
+/// Genera pedidos sintéticos según un perfil de carga (`--profile <path>`), cuyos valores pueden
+/// reemplazarse con `--orders <n>`, `--rate <pedidos/s>`, `--invalid <porcentaje>` y
+/// `--seed <n>`. Con `--output <path>` escribe los pedidos en un archivo JSON, NDJSON o CSV según
+/// su extensión; con `--spool <dir>` los deposita en el directorio vigilado de una pantalla a
+/// medida que llegan, y si no se indica ninguno los escribe en NDJSON en la salida estándar a
+/// medida que llegan, para enviarlos a una pantalla que lee de la entrada estándar.
+///
+/// Uso: cargo run --bin loadgen -- --orders 20 --rate 2 | cargo run --bin screen 0 2 -
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let mut profile = LoadProfile::default();
+    let mut destination = Destination::Stdout;
+    for option in args[1..].chunks(2) {
+        if option.len() < 2 {
+            exit_with_usage(&args[0]);
+        }
+        match option[0].as_str() {
+            PROFILE_ARG => {
+                profile = LoadProfile::from_file(&option[1]).unwrap_or_else(|e| exit_with_error(&e))
+            }
+            ORDERS_ARG => profile.orders = option[1].parse().expect("Invalid orders"),
+            RATE_ARG => profile.rate_per_sec = option[1].parse().expect("Invalid rate"),
+            INVALID_ARG => {
+                profile.invalid_percentage = option[1].parse().expect("Invalid percentage")
+            }
+            SEED_ARG => profile.seed = Some(option[1].parse().expect("Invalid seed")),
+            OUTPUT_ARG => destination = Destination::File(option[1].clone()),
+            SPOOL_ARG => destination = Destination::Spool(option[1].clone()),
+            _ => exit_with_usage(&args[0]),
+        }
+    }
+    if let Err(e) = profile.validate() {
+        exit_with_error(&e);
+    }
+
+    let generator = OrderGenerator::new(profile);
+    let result = match destination {
+        Destination::File(ref path) => {
+            let orders: Vec<GeneratedOrder> = generator.collect();
+            write_orders(path, &orders)
+                .map(|count| eprintln!("Se generaron {} pedidos en {}", count, path))
+        }
+        Destination::Spool(ref dir) => paced(generator, |order| spool_order(Path::new(dir), order)),
+        Destination::Stdout => paced(generator, |order| {
+            let mut stdout = io::stdout().lock();
+            writeln!(stdout, "{}", order.to_ndjson())
+                .and_then(|_| stdout.flush())
+                .map_err(|e| e.to_string())
+        }),
+    };
+    if let Err(e) = result {
+        exit_with_error(&e);
+    }
+}
+
+/// Entrega cada pedido con `deliver` en su momento de llegada.
+fn paced<F>(generator: OrderGenerator, mut deliver: F) -> Result<(), String>
+where
+    F: FnMut(&GeneratedOrder) -> Result<(), String>,
+{
+    let started = Instant::now();
+    for order in generator {
+        thread::sleep(order.at.saturating_sub(started.elapsed()));
+        deliver(&order)?;
+    }
+    Ok(())
+}
+
+/// Muestra el error y termina el proceso.
+fn exit_with_error(error: &str) -> ! {
+    eprintln!("Error: {}", error);
+    std::process::exit(1);
+}
+
+/// Muestra el uso del binario y termina el proceso.
+fn exit_with_usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} [{} <path>] [{} <n>] [{} <n>] [{} <n>] [{} <n>] [{} <path> | {} <dir>]",
+        program, PROFILE_ARG, ORDERS_ARG, RATE_ARG, INVALID_ARG, SEED_ARG, OUTPUT_ARG, SPOOL_ARG
+    );
+    std::process::exit(1);
+}
@@ -0,0 +1,32 @@
+use heladeria::reconcile::reconcile::Reconciliation;
+use std::env;
+
+const LOG_FILE_PATH: &str = "transactions.log";
+
+/// Punto de entrada principal para el binario `reconcile`.
+/// Acepta un argumento opcional con la ruta del log de transacciones del gateway.
+/// Imprime el reporte de conciliación y termina con código 1 si encuentra inconsistencias.
+///
+/// Uso: cargo run --bin reconcile [log_path]
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() > 2 {
+        eprintln!("Usage: {} <log-path>", args[0]);
+        std::process::exit(1);
+    }
+
+    let log_path = args.get(1).map(String::as_str).unwrap_or(LOG_FILE_PATH);
+
+    match Reconciliation::from_file(log_path) {
+        Ok(reconciliation) => {
+            print!("{}", reconciliation);
+            if !reconciliation.is_consistent() {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,503 @@
+/// Este módulo implementa la conciliación del archivo de transacciones del gateway.
+///
+/// Lee las líneas `PREPARE`, `COMMIT`, `ABORT`, `CAPTURE` y `REFUND` registradas por el gateway,
+/// detecta inconsistencias (autorizaciones sin cerrar, cierres duplicados, reembolsos inválidos)
+/// y calcula estadísticas de confirmaciones, cancelaciones y recaudación. Las líneas `KEY`, que
+/// registran la clave de cada `PREPARE`, no afectan la conciliación.
+use crate::common::constants::PRICE_PER_GRAM;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+const PREPARE: &str = "PREPARE";
+const COMMIT: &str = "COMMIT";
+const ABORT: &str = "ABORT";
+const CAPTURE: &str = "CAPTURE";
+const REFUND: &str = "REFUND";
+const KEY: &str = "KEY";
+
+/// Pantalla a la que se imputan las transacciones que no registran su origen.
+const UNKNOWN_SCREEN: &str = "unknown";
+
+/// Estado de una transacción reconstruido a partir del log.
+#[derive(Debug, Default)]
+struct TransactionRecord {
+    flavors: HashMap<String, u32>,
+    screen: Option<String>,
+    prepares: usize,
+    commits: usize,
+    captures: usize,
+    aborts: usize,
+    captured_amount: u32,
+    refunded_amount: u32,
+}
+
+impl TransactionRecord {
+    /// Cantidad de veces que la transacción fue cerrada (cobrada o cancelada).
+    fn settlements(&self) -> usize {
+        self.commits + self.captures + self.aborts
+    }
+
+    /// Monto efectivamente recaudado (cobrado menos reembolsado).
+    fn revenue(&self) -> u32 {
+        self.captured_amount.saturating_sub(self.refunded_amount)
+    }
+
+    /// Gramos totales del pedido. Un detalle adulterado no puede desbordar la suma.
+    fn total_grams(&self) -> u32 {
+        self.flavors
+            .values()
+            .fold(0, |total: u32, grams| total.saturating_add(*grams))
+    }
+
+    /// Monto total autorizado según el detalle del pedido.
+    fn authorized_amount(&self) -> u32 {
+        self.total_grams().saturating_mul(PRICE_PER_GRAM)
+    }
+}
+
+/// Resultado de conciliar un archivo de transacciones.
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    transactions: BTreeMap<u32, TransactionRecord>,
+    inconsistencies: Vec<String>,
+}
+
+impl Reconciliation {
+    /// Concilia el archivo de log ubicado en `log_path`.
+    pub fn from_file(log_path: &str) -> Result<Self, String> {
+        let file =
+            File::open(log_path).map_err(|e| format!("Unable to open {}: {}", log_path, e))?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Concilia las líneas de log que se leen de `reader`.
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Self, String> {
+        let mut reconciliation = Reconciliation::default();
+        for (line_number, line) in reader.lines().enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            reconciliation.process_line(line_number + 1, &line);
+        }
+        reconciliation.finish();
+        Ok(reconciliation)
+    }
+
+    /// Devuelve `true` si no se encontraron inconsistencias.
+    pub fn is_consistent(&self) -> bool {
+        self.inconsistencies.is_empty()
+    }
+
+    /// Devuelve las inconsistencias encontradas.
+    pub fn inconsistencies(&self) -> &[String] {
+        &self.inconsistencies
+    }
+
+    /// Cantidad de transacciones cobradas, total o parcialmente.
+    pub fn committed_count(&self) -> usize {
+        self.transactions
+            .values()
+            .filter(|record| record.commits + record.captures > 0)
+            .count()
+    }
+
+    /// Cantidad de transacciones canceladas.
+    pub fn aborted_count(&self) -> usize {
+        self.transactions
+            .values()
+            .filter(|record| record.aborts > 0)
+            .count()
+    }
+
+    /// Devuelve los gramos entregados de cada sabor, según los pedidos cobrados.
+    pub fn served_grams_per_flavor(&self) -> BTreeMap<String, u32> {
+        let mut served = BTreeMap::new();
+        for record in self.transactions.values() {
+            if record.commits + record.captures == 0 {
+                continue;
+            }
+            for (flavor, grams) in &record.flavors {
+                let served = served.entry(flavor.clone()).or_insert(0u32);
+                *served = served.saturating_add(*grams);
+            }
+        }
+        served
+    }
+
+    /// Procesa una línea del log, actualizando la transacción correspondiente.
+    fn process_line(&mut self, line_number: usize, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let parts: Vec<&str> = line.split(',').collect();
+        let order_id = match parts.get(1).and_then(|id| id.parse::<u32>().ok()) {
+            Some(id) => id,
+            None => {
+                self.inconsistencies
+                    .push(format!("Line {}: malformed entry '{}'", line_number, line));
+                return;
+            }
+        };
+
+        match parts[0] {
+            PREPARE => {
+                let record = self.transactions.entry(order_id).or_default();
+                record.prepares += 1;
+                record.screen = parts
+                    .get(2)
+                    .filter(|screen| !screen.is_empty())
+                    .map(|screen| screen.to_string());
+                record.flavors = parse_flavors(parts.get(4..).unwrap_or(&[]));
+            }
+            COMMIT => {
+                let record = self.settled_record(line_number, order_id, COMMIT);
+                record.commits += 1;
+                record.captured_amount = record.authorized_amount();
+            }
+            ABORT => {
+                let record = self.settled_record(line_number, order_id, ABORT);
+                record.aborts += 1;
+            }
+            CAPTURE => match parse_amount(&parts) {
+                Some(amount) => {
+                    let record = self.settled_record(line_number, order_id, CAPTURE);
+                    record.captures += 1;
+                    record.captured_amount = amount;
+                    if amount > record.authorized_amount() {
+                        self.inconsistencies.push(format!(
+                            "Line {}: CAPTURE of {} exceeds authorized amount for order {}",
+                            line_number, amount, order_id
+                        ));
+                    }
+                }
+                None => self
+                    .inconsistencies
+                    .push(format!("Line {}: malformed entry '{}'", line_number, line)),
+            },
+            REFUND => match parse_amount(&parts) {
+                Some(amount) => self.apply_refund(line_number, order_id, amount),
+                None => self
+                    .inconsistencies
+                    .push(format!("Line {}: malformed entry '{}'", line_number, line)),
+            },
+            KEY => {}
+            _ => self
+                .inconsistencies
+                .push(format!("Line {}: unknown entry '{}'", line_number, line)),
+        }
+    }
+
+    /// Devuelve la transacción que se está cerrando, registrando cierres sin PREPARE previo.
+    fn settled_record(
+        &mut self,
+        line_number: usize,
+        order_id: u32,
+        command: &str,
+    ) -> &mut TransactionRecord {
+        if !self.transactions.contains_key(&order_id) {
+            self.inconsistencies.push(format!(
+                "Line {}: {} for order {} without PREPARE",
+                line_number, command, order_id
+            ));
+        }
+        self.transactions.entry(order_id).or_default()
+    }
+
+    /// Aplica un reembolso, validando que la transacción haya sido cobrada.
+    fn apply_refund(&mut self, line_number: usize, order_id: u32, amount: u32) {
+        match self.transactions.get_mut(&order_id) {
+            Some(record) if record.commits + record.captures > 0 => {
+                record.refunded_amount = record.refunded_amount.saturating_add(amount);
+                if record.refunded_amount > record.captured_amount {
+                    self.inconsistencies.push(format!(
+                        "Line {}: REFUND total for order {} exceeds captured amount",
+                        line_number, order_id
+                    ));
+                }
+            }
+            _ => self.inconsistencies.push(format!(
+                "Line {}: REFUND for order {} that was never charged",
+                line_number, order_id
+            )),
+        }
+    }
+
+    /// Revisa el estado final de cada transacción una vez leído todo el log.
+    fn finish(&mut self) {
+        for (order_id, record) in &self.transactions {
+            if record.prepares > 1 {
+                self.inconsistencies
+                    .push(format!("Duplicate PREPARE for order {}", order_id));
+            }
+            if record.settlements() == 0 {
+                self.inconsistencies
+                    .push(format!("Unmatched PREPARE for order {}", order_id));
+            }
+            if record.commits + record.captures > 1 {
+                self.inconsistencies
+                    .push(format!("Duplicate COMMIT for order {}", order_id));
+            }
+            if record.aborts > 0 && record.commits + record.captures > 0 {
+                self.inconsistencies
+                    .push(format!("Order {} was both committed and aborted", order_id));
+            }
+        }
+    }
+
+    /// Devuelve la recaudación por sabor, repartiendo el monto de cada pedido según sus gramos.
+    pub fn revenue_per_flavor(&self) -> BTreeMap<String, u32> {
+        let mut revenue = BTreeMap::new();
+        for record in self.transactions.values() {
+            let total_grams = record.total_grams();
+            if total_grams == 0 {
+                continue;
+            }
+            for (flavor, grams) in &record.flavors {
+                let share =
+                    u64::from(record.revenue()) * u64::from(*grams) / u64::from(total_grams);
+                let revenue = revenue.entry(flavor.clone()).or_insert(0u32);
+                *revenue = revenue.saturating_add(share as u32);
+            }
+        }
+        revenue
+    }
+
+    /// Devuelve la recaudación por pantalla.
+    pub fn revenue_per_screen(&self) -> BTreeMap<String, u32> {
+        let mut revenue = BTreeMap::new();
+        for record in self.transactions.values() {
+            let screen = record
+                .screen
+                .clone()
+                .unwrap_or_else(|| UNKNOWN_SCREEN.to_string());
+            let revenue = revenue.entry(screen).or_insert(0u32);
+            *revenue = revenue.saturating_add(record.revenue());
+        }
+        revenue
+    }
+}
+
+/// Obtiene los gramos por sabor de un detalle `<sabor>,<gramos>g;...` separado por comas.
+fn parse_flavors(details: &[&str]) -> HashMap<String, u32> {
+    let mut flavors = HashMap::new();
+    for flavor in details.join(",").split(';') {
+        if let Some((name, grams)) = flavor.split_once(',') {
+            if let Ok(grams) = grams.trim().trim_end_matches('g').parse::<u32>() {
+                let total = flavors.entry(name.trim().to_string()).or_insert(0u32);
+                *total = total.saturating_add(grams);
+            }
+        }
+    }
+    flavors
+}
+
+/// Obtiene el monto de una línea `<COMANDO>,<id>,<monto>,...`.
+fn parse_amount(parts: &[&str]) -> Option<u32> {
+    parts.get(2).and_then(|amount| amount.parse::<u32>().ok())
+}
+
+/// Formatea el reporte de conciliación.
+impl fmt::Display for Reconciliation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let settled = |filter: fn(&TransactionRecord) -> bool| {
+            self.transactions.values().filter(|r| filter(r)).count()
+        };
+        let commits = settled(|r| r.commits > 0);
+        let captures = settled(|r| r.captures > 0);
+        let aborts = settled(|r| r.aborts > 0);
+        let closed = commits + captures + aborts;
+
+        writeln!(f, "Transactions: {}", self.transactions.len())?;
+        writeln!(f, "Committed: {}", commits)?;
+        writeln!(f, "Partially captured: {}", captures)?;
+        writeln!(f, "Aborted: {}", aborts)?;
+        if closed > 0 {
+            writeln!(
+                f,
+                "Commit/abort ratio: {:.2}% / {:.2}%",
+                (commits + captures) as f64 * 100.0 / closed as f64,
+                aborts as f64 * 100.0 / closed as f64
+            )?;
+        }
+
+        writeln!(f, "Revenue per flavor:")?;
+        for (flavor, revenue) in self.revenue_per_flavor() {
+            writeln!(f, "  - {}: {}", flavor, revenue)?;
+        }
+        writeln!(f, "Revenue per screen:")?;
+        for (screen, revenue) in self.revenue_per_screen() {
+            writeln!(f, "  - {}: {}", screen, revenue)?;
+        }
+
+        if self.is_consistent() {
+            writeln!(f, "No inconsistencies found")
+        } else {
+            writeln!(f, "Inconsistencies ({}):", self.inconsistencies.len())?;
+            for inconsistency in &self.inconsistencies {
+                writeln!(f, "  - {}", inconsistency)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reconcile(log: &str) -> Reconciliation {
+        Reconciliation::from_reader(log.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn a_settled_log_is_consistent() {
+        let reconciliation = reconcile(
+            "PREPARE,1,0,ana,Mint,100g;Vanilla,100g;\n\
+             KEY,1,0-1-0\n\
+             COMMIT,1,0,ana,Mint,100g;Vanilla,100g;\n\
+             PREPARE,2,1,,Mint,50g;\n\
+             ABORT,2,OUT_OF_STOCK,1,,Mint,50g;\n\
+             \n\
+             PREPARE,3,1,luis,Chocolate,300g;\n\
+             CAPTURE,3,200,1,luis,Chocolate,300g;\n\
+             REFUND,3,50,1,luis,Chocolate,300g;\n",
+        );
+
+        assert!(
+            reconciliation.is_consistent(),
+            "{:?}",
+            reconciliation.inconsistencies()
+        );
+        assert_eq!(reconciliation.committed_count(), 2);
+        assert_eq!(reconciliation.aborted_count(), 1);
+        let served = reconciliation.served_grams_per_flavor();
+        assert_eq!(served.get("Mint"), Some(&100));
+        assert_eq!(served.get("Chocolate"), Some(&300));
+        let per_flavor = reconciliation.revenue_per_flavor();
+        assert_eq!(per_flavor.get("Mint"), Some(&(100 * PRICE_PER_GRAM)));
+        assert_eq!(per_flavor.get("Chocolate"), Some(&150));
+        let per_screen = reconciliation.revenue_per_screen();
+        assert_eq!(per_screen.get("0"), Some(&(200 * PRICE_PER_GRAM)));
+        assert_eq!(per_screen.get("1"), Some(&150));
+    }
+
+    #[test]
+    fn malformed_lines_are_reported_with_their_number() {
+        let reconciliation = reconcile(
+            "PREPARE\n\
+             COMMIT,abc\n\
+             PREPARE,1,0,ana,Mint,100g;\n\
+             CAPTURE,1\n\
+             REFUND,1,x\n\
+             HOLD,1\n\
+             COMMIT,1,0,ana,Mint,100g;\n",
+        );
+
+        assert_eq!(
+            reconciliation.inconsistencies(),
+            [
+                "Line 1: malformed entry 'PREPARE'",
+                "Line 2: malformed entry 'COMMIT,abc'",
+                "Line 4: malformed entry 'CAPTURE,1'",
+                "Line 5: malformed entry 'REFUND,1,x'",
+                "Line 6: unknown entry 'HOLD,1'",
+            ]
+        );
+        assert_eq!(reconciliation.committed_count(), 1);
+    }
+
+    #[test]
+    fn settlement_errors_are_reported() {
+        let reconciliation = reconcile(
+            "PREPARE,1,0,ana,Mint,100g;\n\
+             PREPARE,2,0,ana,Mint,100g;\n\
+             COMMIT,2,0,ana,Mint,100g;\n\
+             COMMIT,2,0,ana,Mint,100g;\n\
+             ABORT,2,TIMEOUT,0,ana,Mint,100g;\n\
+             COMMIT,3,0,ana,Mint,100g;\n\
+             PREPARE,4,0,ana,Mint,100g;\n\
+             CAPTURE,4,500,0,ana,Mint,100g;\n\
+             REFUND,5,10,0,ana,\n",
+        );
+        let inconsistencies = reconciliation.inconsistencies();
+
+        for expected in [
+            "Line 6: COMMIT for order 3 without PREPARE",
+            "Line 8: CAPTURE of 500 exceeds authorized amount for order 4",
+            "Line 9: REFUND for order 5 that was never charged",
+            "Unmatched PREPARE for order 1",
+            "Duplicate COMMIT for order 2",
+            "Order 2 was both committed and aborted",
+        ] {
+            assert!(
+                inconsistencies.iter().any(|found| found == expected),
+                "{}: {:?}",
+                expected,
+                inconsistencies
+            );
+        }
+    }
+
+    #[test]
+    fn refunds_cannot_exceed_the_captured_amount() {
+        let reconciliation = reconcile(
+            "PREPARE,1,0,ana,Mint,100g;\n\
+             COMMIT,1,0,ana,Mint,100g;\n\
+             REFUND,1,60,0,ana,Mint,100g;\n\
+             REFUND,1,60,0,ana,Mint,100g;\n",
+        );
+
+        assert_eq!(
+            reconciliation.inconsistencies(),
+            ["Line 4: REFUND total for order 1 exceeds captured amount"]
+        );
+        assert_eq!(reconciliation.revenue_per_screen().get("0"), Some(&0));
+    }
+
+    #[test]
+    fn transactions_without_origin_are_charged_to_an_unknown_screen() {
+        let reconciliation = reconcile("PREPARE,1,,,Mint,10g;\nCOMMIT,1,,,Mint,10g;\n");
+
+        assert_eq!(
+            reconciliation.revenue_per_screen().get(UNKNOWN_SCREEN),
+            Some(&(10 * PRICE_PER_GRAM))
+        );
+    }
+
+    #[test]
+    fn oversized_details_do_not_overflow() {
+        let log = format!(
+            "PREPARE,1,0,ana,Mint,{}g;Mint,1g;Vanilla,{}g;\nCOMMIT,1,0,ana,\n",
+            u32::MAX,
+            u32::MAX
+        );
+        let reconciliation = reconcile(&log);
+
+        assert!(reconciliation.is_consistent());
+        assert_eq!(
+            reconciliation.served_grams_per_flavor().get("Mint"),
+            Some(&u32::MAX)
+        );
+        assert_eq!(
+            reconciliation.revenue_per_screen().get("0"),
+            Some(&u32::MAX)
+        );
+    }
+
+    #[test]
+    fn flavor_details_skip_unparseable_grams() {
+        let flavors = parse_flavors(&[
+            "Mint",
+            "100g;Vanilla",
+            "abc;Chocolate",
+            " 20g;Mint",
+            "5g;Lemon",
+        ]);
+
+        assert_eq!(flavors.get("Mint"), Some(&105));
+        assert_eq!(flavors.get("Chocolate"), Some(&20));
+        assert_eq!(flavors.get("Vanilla"), None);
+        assert_eq!(flavors.get("Lemon"), None);
+    }
+}
@@ -0,0 +1,75 @@
+use heladeria::common::logger;
+use heladeria::common::recorder::read_recording;
+use heladeria::ctl::ctl::render_status;
+use heladeria::replay::replay::{render_step, Replay};
+use std::env;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+/// Argumento de línea de comandos que indica pausar la reproducción después de cada mensaje.
+const STEP_ARG: &str = "--step";
+
+/// Punto de entrada principal para el binario `replay`.
+/// Reproduce la grabación del tráfico de un robot o una pantalla (un archivo `.rec`) sobre una
+/// sola instancia del nodo, en un anillo de `network_size` nodos: le entrega en orden los
+/// mensajes que recibió y muestra, después de cada uno, lo que el nodo envió al procesarlo. Al
+/// final muestra el estado del nodo. Con `--step`, la reproducción se pausa después de cada
+/// mensaje: Enter entrega el siguiente, `s` muestra el estado del nodo y `q` termina.
+///
+/// Uso: cargo run --bin replay -- [--step] <grabación> <network_size>
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    let step_by_step = args.iter().any(|arg| arg == STEP_ARG);
+    let positional: Vec<&String> = args[1..].iter().filter(|arg| *arg != STEP_ARG).collect();
+    if positional.len() != 2 {
+        exit_with_usage(&args[0]);
+    }
+    let network_size: usize = positional[1]
+        .parse()
+        .unwrap_or_else(|_| exit_with_usage(&args[0]));
+
+    let mut replay = read_recording(Path::new(positional[0]))
+        .and_then(|recording| Replay::new(recording, network_size))
+        .unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    logger::init("replay", replay.header.node_id);
+    println!("Reproduciendo la grabación de {}", replay.header);
+
+    let start_us = replay.start_us();
+    let mut stdin = io::stdin().lock();
+    while let Some(step) = replay.step() {
+        println!("{}", render_step(&step, start_us));
+        if step_by_step && !wait_for_next(&mut stdin, &replay) {
+            break;
+        }
+    }
+    println!("{}", render_status(&replay.status()));
+}
+
+/// Espera la orden del usuario antes de entregar el siguiente mensaje. Devuelve `false` si la
+/// reproducción tiene que terminar.
+fn wait_for_next(stdin: &mut impl BufRead, replay: &Replay) -> bool {
+    loop {
+        let mut input = String::new();
+        if stdin.read_line(&mut input).unwrap_or(0) == 0 {
+            return false;
+        }
+        match input.trim() {
+            "" => return true,
+            "s" => println!("{}", render_status(&replay.status())),
+            "q" => return false,
+            _ => println!("Enter: siguiente mensaje, s: estado del nodo, q: terminar"),
+        }
+    }
+}
+
+/// Muestra el uso del binario y termina el proceso.
+fn exit_with_usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} [{}] <recording> <network_size>",
+        program, STEP_ARG
+    );
+    std::process::exit(1);
+}
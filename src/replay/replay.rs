@@ -0,0 +1,294 @@
+/// Este módulo implementa la reproducción de una grabación del tráfico sobre un solo robot o una
+/// sola pantalla, para reproducir fuera de la heladería un problema que se vio en ella.
+///
+/// Se crea el nodo que hizo la grabación, con el estado con el que arranca, y se le entregan uno
+/// por uno, en el orden en que llegaron, los mensajes que recibió. Cada mensaje se procesa con la
+/// misma función que lo procesó en la heladería, según el tipo de nodo del que llegó, y se
+/// termina de procesar antes de entregar el siguiente: en la heladería varios mensajes se
+/// procesan a la vez en hilos separados, pero la reproducción siempre sigue el mismo orden. Una
+/// conexión cerrada por el robot o la pantalla anterior del anillo se reproduce como su caída.
+///
+/// Los hilos que inicia el nodo para conectarse con otros nodos no encuentran a nadie si la
+/// heladería no está corriendo en la misma máquina, así que lo que envía el nodo queda en sus
+/// canales: después de cada mensaje se devuelve y se descarta. Además:
+/// - El robot no prepara pedidos. Los tokens que recibe los retiene hasta el momento en que la
+///   grabación muestra que los envió.
+/// - La pantalla no lee pedidos, así que no se reproduce el `AllConnected` que la hace empezar.
+/// - La pantalla no se conecta al gateway, así que se salta el texto que intercambió con él.
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::common::constants::DEFAULT_ORDER_DEADLINE_SECS;
+use crate::common::messages::{MessageType, NodeStatus};
+use crate::common::recorder::{Direction, Encoding, Frame, Peer, Recording, RecordingHeader};
+use crate::decode::timeline::{render_elapsed, render_message};
+use crate::log_warn;
+use crate::robot::admin as robot_admin;
+use crate::robot::robot::Robot;
+use crate::robot::utils as robot_utils;
+use crate::screen::admin as screen_admin;
+use crate::screen::order_source::OrderSource;
+use crate::screen::screen::Screen;
+use crate::screen::screen_actors::OrderWindow;
+use crate::screen::utils as screen_utils;
+
+/// Nodo que hizo la grabación.
+pub enum ReplayNode {
+    Robot(Arc<Robot>),
+    Screen(Arc<Screen>),
+}
+
+impl ReplayNode {
+    /// Crea el nodo indicado por el encabezado de la grabación, en un anillo de `network_size`
+    /// nodos.
+    fn new(header: &RecordingHeader, network_size: usize) -> Result<Self, String> {
+        let Some(id) = header.node_id else {
+            return Err(format!(
+                "La grabación de {} no indica el ID del nodo",
+                header
+            ));
+        };
+        if id >= network_size {
+            return Err(format!(
+                "{} no puede estar en un anillo de {} nodos",
+                header, network_size
+            ));
+        }
+        match header.component.as_str() {
+            "robot" => {
+                let robot = Robot::new(id, network_size);
+                robot.initialize_network(network_size);
+                robot.initialize_orders_table(network_size);
+                robot.initialize_token_table();
+                Ok(ReplayNode::Robot(Arc::new(robot)))
+            }
+            "screen" => {
+                let screen = Screen::new(
+                    id,
+                    network_size,
+                    OrderSource::Interactive,
+                    None,
+                    OrderWindow::default(),
+                    Duration::from_secs(DEFAULT_ORDER_DEADLINE_SECS),
+                    false,
+                );
+                screen.initialize_network();
+                Ok(ReplayNode::Screen(Arc::new(screen)))
+            }
+            component => Err(format!(
+                "No se pueden reproducir grabaciones de {}: solo de robots y pantallas",
+                component
+            )),
+        }
+    }
+
+    /// Procesa un mensaje recibido de un nodo de tipo `peer`.
+    fn deliver(&self, peer: Peer, message: MessageType) {
+        match self {
+            ReplayNode::Robot(robot) => {
+                let my_id = robot.id;
+                match (peer, message) {
+                    (Peer::Robot, message @ MessageType::Prepare(_)) => {
+                        robot_utils::handle_prepare(robot, my_id, message)
+                    }
+                    (Peer::Robot, message) => {
+                        robot_utils::handle_other_messages(robot.clone(), my_id, message)
+                    }
+                    (Peer::Screen, message) => {
+                        robot_utils::handle_screen_message(robot, my_id, message)
+                    }
+                    (Peer::Console, MessageType::AdminRequest(command)) => {
+                        robot_admin::handle_admin_command(robot, command);
+                    }
+                    (Peer::Unknown, MessageType::ScreenIntroduction(intro))
+                        if robot.is_leader(my_id) =>
+                    {
+                        robot_utils::accept_screen(robot, my_id, intro.sender_id, false)
+                    }
+                    (Peer::Unknown, MessageType::NewLeaderIntroduction(screen_id))
+                        if robot.is_leader(my_id) =>
+                    {
+                        robot_utils::accept_screen(robot, my_id, screen_id, true)
+                    }
+                    _ => {}
+                }
+            }
+            ReplayNode::Screen(screen) => {
+                let my_id = screen.id;
+                match (peer, message) {
+                    (Peer::Screen, message @ MessageType::OrderScreen(_)) => {
+                        screen_utils::handle_order_screen(screen, my_id, message)
+                    }
+                    (Peer::Screen, MessageType::AllConnected(_)) => {
+                        log_warn!("AllConnected no se reproduce: la pantalla no lee pedidos");
+                    }
+                    (Peer::Screen, message) => {
+                        screen_utils::handle_other_messages(screen.clone(), my_id, message)
+                    }
+                    (Peer::Robot, message) => screen_utils::handle_robot_message(screen, message),
+                    (Peer::Console, MessageType::AdminRequest(command)) => {
+                        screen_admin::handle_admin_command(screen, command);
+                    }
+                    (Peer::Unknown, MessageType::NewLeaderIntroduction(robot_id)) => {
+                        screen_utils::accept_robot_leader(screen, robot_id)
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    /// Procesa el cierre de una conexión por parte de un nodo de tipo `peer`. Solo importa el
+    /// cierre de la conexión con el nodo anterior del anillo, que indica su caída.
+    fn connection_closed(&self, peer: Peer) {
+        match (self, peer) {
+            (ReplayNode::Robot(robot), Peer::Robot) => {
+                robot_utils::handle_prev_robot_death(robot.clone(), robot.id)
+            }
+            (ReplayNode::Screen(screen), Peer::Screen) => {
+                screen_utils::handle_prev_screen_death(screen, screen.id)
+            }
+            _ => {}
+        }
+    }
+
+    /// Aplica un mensaje que el nodo envió en la heladería a la hora `sent_at`. El robot suelta
+    /// el token que envió, con el stock y la hora con que lo envió: la hora tiene que ser la de
+    /// la grabación para comparar bien las horas de los mensajes `PossibleLostToken`.
+    fn apply_sent(&self, message: MessageType, sent_at: SystemTime) {
+        if let (ReplayNode::Robot(robot), MessageType::Token(token)) = (self, message) {
+            robot.set_token_status(token.flavour.clone(), false);
+            if let Some(info) = robot.tokens_table.lock().unwrap().get_mut(&token.flavour) {
+                info.stock = token.available_ammount;
+                info.last_modification_timestamp = sent_at;
+            }
+        }
+    }
+
+    /// Devuelve y descarta los mensajes que el nodo dejó para enviar, junto con el tipo de nodo
+    /// al que iban. Los tokens que recibió el robot no se envían: quedan retenidos.
+    fn take_sent(&self) -> Vec<(Peer, MessageType)> {
+        let mut sent = Vec::new();
+        match self {
+            ReplayNode::Robot(robot) => {
+                let _ = robot.rx_token_channel.lock().unwrap().try_iter().count();
+                let ring = robot.rx_sender_channel.lock().unwrap();
+                sent.extend(ring.try_iter().map(|message| (Peer::Robot, message)));
+                let screen = robot.rx_screen_sender_channel.lock().unwrap();
+                sent.extend(screen.try_iter().map(|message| (Peer::Screen, message)));
+            }
+            ReplayNode::Screen(screen) => {
+                let ring = screen.rx_sender_channel.lock().unwrap();
+                sent.extend(ring.try_iter().map(|message| (Peer::Screen, message)));
+                let robot = screen.rx_prepare_channel.lock().unwrap();
+                sent.extend(robot.try_iter().map(|message| (Peer::Robot, message)));
+            }
+        }
+        sent
+    }
+}
+
+/// Mensaje de la grabación que se le entregó al nodo, con lo que el nodo envió al procesarlo.
+pub struct ReplayStep {
+    /// Posición del mensaje en la grabación, contando también los mensajes enviados.
+    pub index: usize,
+    pub frame: Frame,
+    pub sent: Vec<(Peer, MessageType)>,
+}
+
+/// Reproducción de una grabación.
+pub struct Replay {
+    pub header: RecordingHeader,
+    node: ReplayNode,
+    frames: Vec<Frame>,
+    next: usize,
+}
+
+impl Replay {
+    /// Crea el nodo que hizo la grabación, en un anillo de `network_size` nodos.
+    pub fn new(recording: Recording, network_size: usize) -> Result<Self, String> {
+        let node = ReplayNode::new(&recording.header, network_size)?;
+        Ok(Replay {
+            header: recording.header,
+            node,
+            frames: recording.frames,
+            next: 0,
+        })
+    }
+
+    /// Hora del primer mensaje de la grabación, en microsegundos desde la época Unix.
+    pub fn start_us(&self) -> u64 {
+        self.frames
+            .first()
+            .map(|frame| frame.timestamp_us)
+            .unwrap_or(0)
+    }
+
+    /// Le entrega al nodo el siguiente mensaje recibido o conexión cerrada de la grabación. Los
+    /// mensajes enviados que se encuentran antes se aplican sin devolverlos. Devuelve `None` si
+    /// ya no quedan mensajes.
+    pub fn step(&mut self) -> Option<ReplayStep> {
+        while let Some(frame) = self.frames.get(self.next).cloned() {
+            let index = self.next;
+            self.next += 1;
+            if frame.encoding == Encoding::Text {
+                continue;
+            }
+            match frame.direction {
+                Direction::Sent => {
+                    if let Some(message) = frame.message() {
+                        let sent_at = UNIX_EPOCH + Duration::from_micros(frame.timestamp_us);
+                        self.node.apply_sent(message, sent_at);
+                    }
+                    continue;
+                }
+                Direction::Received => match frame.message() {
+                    Some(message) => self.node.deliver(frame.peer, message),
+                    None => log_warn!("Mensaje inválido en la posición {}", index),
+                },
+                Direction::Closed => self.node.connection_closed(frame.peer),
+            }
+            return Some(ReplayStep {
+                index,
+                frame,
+                sent: self.node.take_sent(),
+            });
+        }
+        None
+    }
+
+    /// Devuelve el estado del nodo, el mismo que informa a `heladeria-ctl`.
+    pub fn status(&self) -> NodeStatus {
+        match &self.node {
+            ReplayNode::Robot(robot) => robot_admin::node_status(robot),
+            ReplayNode::Screen(screen) => screen_admin::node_status(screen),
+        }
+    }
+}
+
+/// Muestra un mensaje reproducido y, debajo, lo que el nodo envió al procesarlo:
+///
+/// ```text
+/// #41   +    2.503117s  -x robot    conexión cerrada
+///                       -> robot    DeadRobot(DeadRobot { sender_id: 1, dead_robot_id: 2 })
+/// ```
+pub fn render_step(step: &ReplayStep, start_us: u64) -> String {
+    let mut lines = vec![format!(
+        "#{:<4} {}  {} {:<8} {}",
+        step.index,
+        render_elapsed(step.frame.timestamp_us, start_us),
+        step.frame.direction,
+        step.frame.peer,
+        render_message(&step.frame, false)
+    )];
+    for (peer, message) in &step.sent {
+        lines.push(format!(
+            "{:22}{} {:<8} {:?}",
+            "",
+            Direction::Sent,
+            peer,
+            message
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,122 @@
+/// Este módulo implementa los comandos de administración de un robot.
+///
+/// El robot atiende en su puerto de administración los comandos de `heladeria-ctl`. Los que
+/// modifican el anillo se aplican con los mismos mensajes que usan los robots entre sí: retirar un
+/// robot se difunde con `DrainRobot`, la reposición de stock se envía al líder con `Restock` y se
+/// replica con `UpdateStock`, y la elección forzada usa el mensaje `Election`.
+use std::sync::Arc;
+
+use super::robot::Robot;
+use crate::common::admin;
+use crate::common::constants::ROBOT_ADMIN_BASE_PORT;
+use crate::common::flavors::IceCreamFlavor;
+use crate::common::messages::*;
+
+/// Abre el puerto de administración del robot.
+pub fn start_admin_listener(robot: Arc<Robot>) {
+    let port = ROBOT_ADMIN_BASE_PORT + robot.id as u16;
+    admin::serve(port, move |command| handle_admin_command(&robot, command));
+}
+
+/// Aplica un comando de la consola de administración y devuelve la respuesta para la consola.
+pub fn handle_admin_command(robot: &Robot, command: AdminCommand) -> AdminReply {
+    let my_id = robot.id;
+    match command {
+        AdminCommand::Status => AdminReply::Status(node_status(robot)),
+        AdminCommand::Drain(robot_id, drained) => {
+            if !robot.network.lock().unwrap().contains_key(&robot_id) {
+                return AdminReply::Error(format!("El robot {} no está en el anillo", robot_id));
+            }
+            robot.set_drained(robot_id, drained);
+            let drain_msg = MessageType::DrainRobot(DrainRobot {
+                sender_id: my_id,
+                robot_id,
+                drained,
+            });
+            let _ = robot.tx_sender_channel.send(drain_msg);
+            if drained {
+                AdminReply::Done(format!(
+                    "No se le asignan pedidos nuevos al robot {}",
+                    robot_id
+                ))
+            } else {
+                AdminReply::Done(format!(
+                    "Se le vuelven a asignar pedidos al robot {}",
+                    robot_id
+                ))
+            }
+        }
+        AdminCommand::Restock(flavor, grams) => {
+            if grams <= 0 {
+                return AdminReply::Error("Los gramos a reponer deben ser mayores a cero".into());
+            }
+            let restock = Restock {
+                flavor: flavor.clone(),
+                grams,
+            };
+            if robot.is_leader(my_id) {
+                robot.restock(&restock);
+            } else {
+                let _ = robot.tx_sender_channel.send(MessageType::Restock(restock));
+            }
+            AdminReply::Done(format!(
+                "Reposición de {} g de {} enviada al robot líder",
+                grams, flavor
+            ))
+        }
+        AdminCommand::ForceElection => {
+            let leader_id = *robot.leader_id.lock().unwrap();
+            robot.send_leader_election_message(my_id, None);
+            AdminReply::Done(format!(
+                "Elección iniciada desde el robot {} (líder actual: robot {})",
+                my_id, leader_id
+            ))
+        }
+    }
+}
+
+/// Devuelve el estado del robot.
+pub fn node_status(robot: &Robot) -> NodeStatus {
+    let orders = robot
+        .orders_table
+        .lock()
+        .unwrap()
+        .orders_map
+        .iter()
+        .map(|(&robot_id, orders_list)| {
+            let mut order_ids: Vec<usize> = orders_list
+                .orders
+                .iter()
+                .map(|order| order.order_id)
+                .collect();
+            order_ids.sort();
+            (robot_id, order_ids)
+        })
+        .collect();
+    // Cada valor se lee por separado para no retener un lock mientras se toma otro
+    let leader_id = *robot.leader_id.lock().unwrap();
+    let peer_leader_id = *robot.screen_leader_id.lock().unwrap();
+    let ring = robot
+        .network
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(&node, &next)| (node, next))
+        .collect();
+    let stock = robot.stock_table.lock().unwrap().stock_table.clone();
+    NodeStatus {
+        kind: NodeKind::Robot,
+        id: robot.id,
+        leader_id,
+        is_leader: leader_id == robot.id,
+        peer_leader_id,
+        ring,
+        tokens: IceCreamFlavor::iter()
+            .filter(|flavor| robot.has_token(flavor))
+            .cloned()
+            .collect(),
+        stock: Some(stock),
+        orders,
+        drained: robot.drained_robots(),
+    }
+}
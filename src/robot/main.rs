@@ -1,3 +1,5 @@
+use heladeria::common::constants::ROBOT_METRICS_BASE_PORT;
+use heladeria::common::{logger, metrics, recorder};
 use heladeria::robot::robot::Robot;
 use std::env;
 
@@ -30,6 +32,9 @@ fn main() {
     let current_index: usize = args[1].parse().expect("Invalid current_index");
     let total_servers: usize = args[2].parse().expect("Invalid total_servers");
 
+    logger::init("robot", Some(current_index));
+    recorder::init("robot", Some(current_index));
+    metrics::serve(ROBOT_METRICS_BASE_PORT + current_index as u16);
     let robot = Robot::new(current_index, total_servers);
     robot.run(current_index, total_servers);
 }
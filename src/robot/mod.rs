@@ -1,4 +1,6 @@
+pub mod admin;
 pub mod robot;
 pub mod robot_orders_table;
 pub mod stock_table;
+pub mod token_stats;
 pub mod utils;
@@ -4,17 +4,80 @@
 ///
 use super::robot_orders_table::{OrdersList, OrdersTable};
 use super::stock_table::Stock;
-use crate::common::constants::{INITIAL_GRAMS_AMOUNT, SLEEP_FACTOR};
+use super::token_stats::TokenTracker;
+use crate::common::constants::{
+    INITIAL_GRAMS_AMOUNT, SLEEP_FACTOR, TOKEN_STATS_REPORT_INTERVAL_MS,
+    TOKEN_WAIT_CHECK_INTERVAL_MS, TOKEN_WAIT_WARNING_MS,
+};
 use crate::common::flavors::{FlavorInfo, IceCreamFlavor};
 use crate::common::messages::*;
+use crate::common::metrics::{self, Metric, MetricKind};
+use crate::common::recorder::{self, Peer};
+use crate::robot::admin::start_admin_listener;
 use crate::robot::utils::handle_incoming_connection;
+use crate::{log_debug, log_error, log_info, log_warn};
 use crossbeam_channel::{select, unbounded, Receiver, Sender};
-use std::collections::HashMap;
-use std::io::Write;
+use std::collections::{HashMap, HashSet};
 use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, Mutex};
 use std::thread::{self};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Pedidos preparados por el robot.
+pub const ORDERS_PREPARED: Metric = Metric {
+    name: "heladeria_robot_orders_prepared_total",
+    help: "Pedidos preparados por el robot.",
+    kind: MetricKind::Counter,
+};
+
+/// Tokens enviados al siguiente robot del anillo, por sabor.
+pub const TOKENS_PASSED: Metric = Metric {
+    name: "heladeria_robot_tokens_passed_total",
+    help: "Tokens enviados al siguiente robot del anillo, por sabor.",
+    kind: MetricKind::Counter,
+};
+
+/// Segundos que el robot retuvo cada token para servir helado, por sabor.
+pub const TOKEN_HOLD_SECONDS: Metric = Metric {
+    name: "heladeria_robot_token_hold_seconds_total",
+    help: "Segundos que el robot retuvo cada token para servir helado, por sabor.",
+    kind: MetricKind::Counter,
+};
+
+/// Segundos que el robot esperó cada token para preparar pedidos, por sabor.
+pub const TOKEN_WAIT_SECONDS: Metric = Metric {
+    name: "heladeria_robot_token_wait_seconds_total",
+    help: "Segundos que el robot esperó cada token para preparar pedidos, por sabor.",
+    kind: MetricKind::Counter,
+};
+
+/// Esperas de un token que superaron `TOKEN_WAIT_WARNING_MS`, por sabor.
+pub const TOKEN_STARVATIONS: Metric = Metric {
+    name: "heladeria_robot_token_starvations_total",
+    help: "Esperas de un token que superaron el umbral de aviso, por sabor.",
+    kind: MetricKind::Counter,
+};
+
+/// Pedidos asignados al robot que esperan ser preparados.
+pub const PREPARE_QUEUE_LENGTH: Metric = Metric {
+    name: "heladeria_robot_prepare_queue_length",
+    help: "Pedidos asignados al robot que esperan ser preparados.",
+    kind: MetricKind::Gauge,
+};
+
+/// Gramos disponibles de cada sabor según la tabla de stock del robot.
+pub const STOCK_GRAMS: Metric = Metric {
+    name: "heladeria_robot_stock_grams",
+    help: "Gramos disponibles de cada sabor según la tabla de stock del robot.",
+    kind: MetricKind::Gauge,
+};
+
+/// Cambios de líder del anillo observados por el robot.
+pub const ELECTIONS: Metric = Metric {
+    name: "heladeria_robot_elections_total",
+    help: "Cambios de líder del anillo observados por el robot.",
+    kind: MetricKind::Counter,
+};
 
 /// Representa un nodo robot en el sistema distribuido.
 pub struct Robot {
@@ -23,6 +86,8 @@ pub struct Robot {
     pub tokens_table: Arc<Mutex<HashMap<IceCreamFlavor, FlavorInfo>>>,
     pub screen_leader_id: Arc<Mutex<usize>>,
     pub leader_id: Arc<Mutex<usize>>,
+    /// Cantidad de cambios de líder que vio el robot, con la que se registran las elecciones.
+    pub leader_term: Arc<Mutex<u64>>,
     pub stock_table: Arc<Mutex<Stock>>,
     pub network_size: Arc<Mutex<usize>>,
     pub orders_table: Arc<Mutex<OrdersTable>>,
@@ -35,6 +100,14 @@ pub struct Robot {
     pub tx_token_channel: Arc<Sender<MessageType>>,
     pub rx_screen_sender_channel: Arc<Mutex<Receiver<MessageType>>>,
     pub tx_screen_sender_channel: Arc<Sender<MessageType>>,
+    /// Estadísticas de circulación de los tokens medidas por este robot.
+    pub token_tracker: Arc<Mutex<TokenTracker>>,
+    /// Últimas estadísticas de tokens de cada robot del anillo, que mantiene el líder.
+    pub ring_token_stats: Arc<Mutex<HashMap<usize, HashMap<IceCreamFlavor, FlavorTokenStats>>>>,
+    /// Robots a los que no se les asignan pedidos nuevos.
+    pub drained: Arc<Mutex<HashSet<usize>>>,
+    /// Gramos repuestos de cada sabor que todavía no se sumaron a su token. Solo los usa el líder.
+    pub pending_restocks: Arc<Mutex<HashMap<IceCreamFlavor, i32>>>,
 }
 
 impl Robot {
@@ -61,6 +134,7 @@ impl Robot {
             id,
             screen_leader_id: Arc::new(Mutex::new(0)), // inicialmente, pantalla 0 es el lider.
             leader_id: Arc::new(Mutex::new(0)),        // inicialmente, robot 0 es el lider.
+            leader_term: Arc::new(Mutex::new(0)),
             network_size: Arc::new(Mutex::new(network_size)),
             orders_table: Arc::new(Mutex::new(OrdersTable::new())),
             network: Arc::new(Mutex::new(HashMap::new())),
@@ -74,6 +148,10 @@ impl Robot {
             tx_token_channel,
             rx_screen_sender_channel,
             tx_screen_sender_channel,
+            token_tracker: Arc::new(Mutex::new(TokenTracker::new())),
+            ring_token_stats: Arc::new(Mutex::new(HashMap::new())),
+            drained: Arc::new(Mutex::new(HashSet::new())),
+            pending_restocks: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
@@ -91,7 +169,7 @@ impl Robot {
     pub fn print_network(&self) {
         let net = self.network.lock().unwrap();
         for (node, &connected_node) in net.iter() {
-            println!("Robot {} is connected to Robot {}", node, connected_node);
+            log_debug!("Robot {} is connected to Robot {}", node, connected_node);
         }
     }
 
@@ -120,6 +198,32 @@ impl Robot {
         }
     }
 
+    /// Encola un pedido `Prepare` para que lo prepare el robot.
+    pub fn enqueue_prepare(&self, prepare_msg: MessageType) {
+        let _ = self.tx_prepare_channel.send(prepare_msg);
+        self.update_prepare_queue_metric();
+    }
+
+    /// Actualiza la métrica con la cantidad de pedidos que esperan ser preparados.
+    fn update_prepare_queue_metric(&self) {
+        metrics::set(
+            &PREPARE_QUEUE_LENGTH,
+            &[],
+            self.tx_prepare_channel.len() as f64,
+        );
+    }
+
+    /// Actualiza la métrica de stock de cada sabor a partir de la tabla de stock.
+    fn update_stock_metrics(stock: &Stock) {
+        for (flavor, grams) in &stock.stock_table {
+            metrics::set(
+                &STOCK_GRAMS,
+                &[("flavor", &flavor.to_string())],
+                *grams as f64,
+            );
+        }
+    }
+
     /// Inicia el robot, inicializando componentes necesarios y comenzando listeners y handlers.
     pub fn run(self, my_id: usize, network_size: usize) {
         let arc_self: Arc<Robot> = Arc::new(self);
@@ -127,15 +231,24 @@ impl Robot {
         //arc_self.print_network(); // Debug
         arc_self.initialize_orders_table(network_size);
         arc_self.initialize_token_table();
+        Self::update_stock_metrics(&arc_self.stock_table.lock().unwrap());
+        arc_self.update_prepare_queue_metric();
 
         let am_i_leader = my_id == 0;
         let arc_tx_1 = arc_self.clone();
         let arc_tx_2 = arc_self.clone();
+        let arc_tx_3 = arc_self.clone();
 
         thread::spawn(move || {
             arc_tx_1.start_order_handler();
         });
 
+        thread::spawn(move || {
+            arc_tx_3.start_token_stats_reporter();
+        });
+
+        start_admin_listener(arc_self.clone());
+
         if !am_i_leader {
             thread::spawn(move || {
                 if my_id == network_size - 1 {
@@ -199,6 +312,7 @@ impl Robot {
 
     /// Conecta al robot con la pantalla líder en la red.
     pub fn connect_to_screen(&self, is_new_leader_introduction: bool) {
+        recorder::set_peer(Peer::Screen);
         let screen_id = self.screen_leader_id.lock().unwrap();
         let tx_sender_screen = self.tx_screen_sender_channel.clone();
         let screen_addr = format!("127.0.0.1:{}", 3000 + *screen_id);
@@ -213,10 +327,7 @@ impl Robot {
                     robot_intro_msg = MessageType::NewLeaderIntroduction(self.id);
                 }
 
-                let serialized =
-                    serialize_message(&robot_intro_msg).expect("Failed to serialize message");
-
-                let _ = stream.write_all(&serialized);
+                let _ = send_message(&mut stream, &robot_intro_msg);
 
                 let rx_screen = self.rx_screen_sender_channel.lock().unwrap();
                 loop {
@@ -227,10 +338,8 @@ impl Robot {
                             }
                             _ => {
                                 thread::sleep(Duration::from_millis(1));
-                                let serialized = serialize_message(&message)
-                                    .expect("Failed to serialize message");
 
-                                if let Err(_) = stream.write_all(&serialized) {
+                                if let Err(_) = send_message(&mut stream, &message) {
                                     let _ = tx_sender_screen.send(message);
                                     break;
                                 }
@@ -241,7 +350,7 @@ impl Robot {
                 }
             }
             Err(e) => {
-                eprintln!("Failed to Connect to Screen: {}", e);
+                log_error!("Failed to Connect to Screen: {}", e);
             }
         };
     }
@@ -254,7 +363,7 @@ impl Robot {
 
     /// Inicia el listener para aceptar conexiones entrantes.
     pub fn start_listener(self: Arc<Self>) {
-        println!("Robot ID {} online", self.id);
+        log_info!("Robot ID {} online", self.id);
         let my_id = self.id.clone();
         let listener_addr = format!("127.0.0.1:{}", 10000 + my_id);
         let listener = TcpListener::bind(&listener_addr).expect("Failed to bind");
@@ -276,7 +385,7 @@ impl Robot {
                     });
                 }
                 Err(e) => {
-                    eprintln!("{}", e);
+                    log_error!("Connection failed: {}", e);
                 }
             }
         }
@@ -295,17 +404,35 @@ impl Robot {
             .has_enough_stock(&order_details)
     }
 
+    /// Publica a la pantalla líder una foto del stock disponible y de la cola de pedidos de los
+    /// robots, para que las pantallas puedan descartar los pedidos imposibles antes de cobrarlos
+    /// y ajustar su ventana de pedidos en curso.
+    pub fn publish_stock(&self) {
+        let queue_depth = self.orders_table.lock().unwrap().pending_orders();
+        let stock_table = self.stock_table.lock().unwrap();
+        let snapshot_msg = MessageType::StockSnapshot(StockSnapshot {
+            stock: stock_table.stock_table.clone(),
+            timestamp: stock_table.last_modification_timestamp,
+            queue_depth,
+        });
+        drop(stock_table);
+        let _ = self.tx_screen_sender_channel.send(snapshot_msg);
+    }
+
     /// Resta el stock especificado de los detalles del pedido de la tabla de stock.
     pub fn subtract_stock(&self, order_details: HashMap<IceCreamFlavor, i32>) -> SystemTime {
-        self.stock_table
-            .lock()
-            .unwrap()
-            .subtract_stock(&order_details)
+        let mut stock_table = self.stock_table.lock().unwrap();
+        let timestamp = stock_table.subtract_stock(&order_details);
+        Self::update_stock_metrics(&stock_table);
+        timestamp
     }
 
     /// Agrega el stock especificado a los detalles del pedido en la tabla de stock.
     pub fn add_stock(&self, order_details: HashMap<IceCreamFlavor, i32>) -> SystemTime {
-        self.stock_table.lock().unwrap().add_stock(&order_details)
+        let mut stock_table = self.stock_table.lock().unwrap();
+        let timestamp = stock_table.add_stock(&order_details);
+        Self::update_stock_metrics(&stock_table);
+        timestamp
     }
 
     /// Agrega el stock especificado con una marca de tiempo a los detalles del pedido en la tabla de stock.
@@ -314,10 +441,9 @@ impl Robot {
         order_details: HashMap<IceCreamFlavor, i32>,
         timestamp: SystemTime,
     ) {
-        self.stock_table
-            .lock()
-            .unwrap()
-            .add_with_timestamp(&order_details, timestamp);
+        let mut stock_table = self.stock_table.lock().unwrap();
+        stock_table.add_with_timestamp(&order_details, timestamp);
+        Self::update_stock_metrics(&stock_table);
     }
 
     /// Resta el stock especificado con una marca de tiempo de los detalles del pedido en la tabla de stock.
@@ -326,10 +452,9 @@ impl Robot {
         order_details: HashMap<IceCreamFlavor, i32>,
         timestamp: SystemTime,
     ) {
-        self.stock_table
-            .lock()
-            .unwrap()
-            .subtract_with_timestamp(&order_details, timestamp);
+        let mut stock_table = self.stock_table.lock().unwrap();
+        stock_table.subtract_with_timestamp(&order_details, timestamp);
+        Self::update_stock_metrics(&stock_table);
     }
 
     /// Maneja la recepción y procesamiento de pedidos de helado.
@@ -345,9 +470,11 @@ impl Robot {
                 recv(rx_prepare) -> message => {
                     match message {
                         Ok(MessageType::Prepare(order)) => {
-                            println!("Preparando pedido ID: {}", order.order_id);
-                            println!("Detalles del pedido: {:?}", order.order_details);
+                            self.update_prepare_queue_metric();
+                            log_info!(order = order.order_id; "Preparando pedido: {:?}", order.order_details);
                             let mut required_flavours: Vec<IceCreamFlavor> = order.order_details.keys().cloned().collect();
+                            let waiting_since = Instant::now();
+                            let mut starvation_warned = false;
 
                             while required_flavours.len() > 0 {
                                 select! {
@@ -368,10 +495,11 @@ impl Robot {
                                                             *token_in_use_guard = true;
 
                                                             let flavour_clone = token.flavour.clone();
+                                                            self.record_token_wait(&flavour_clone, waiting_since.elapsed());
 
 
                                                             let handle = thread::spawn(move || {
-                                                                let used_token = self_clone.use_token(token, amount);
+                                                                let used_token = self_clone.use_token(token, amount, order.order_id);
                                                                 self_clone.set_token_status(used_token.flavour.clone(), false);
                                                                 let _ = tx_sender_clone.send(MessageType::Token(used_token));
 
@@ -401,6 +529,21 @@ impl Robot {
                                             _ => {}
                                         }
                                     }
+                                    default(Duration::from_millis(TOKEN_WAIT_CHECK_INTERVAL_MS)) => {
+                                        let waited = waiting_since.elapsed();
+                                        if !starvation_warned && waited > Duration::from_millis(TOKEN_WAIT_WARNING_MS) {
+                                            starvation_warned = true;
+                                            log_warn!(
+                                                order = order.order_id;
+                                                "Hace {} ms que espero los tokens de {:?}",
+                                                waited.as_millis(),
+                                                required_flavours
+                                            );
+                                            for flavour in &required_flavours {
+                                                metrics::inc(&TOKEN_STARVATIONS, &[("flavor", &flavour.to_string())]);
+                                            }
+                                        }
+                                    }
                                 }
                             }
 
@@ -409,7 +552,8 @@ impl Robot {
                                 handle.join().unwrap();
                             }
 
-                            //println!("Pedido completo: {:?}", order);
+                            log_info!(order = order.order_id; "Pedido preparado");
+                            metrics::inc(&ORDERS_PREPARED, &[]);
                             if self.is_leader(self.id) {
                                 let commit_msg = MessageType::Commit(Commit { order_id: order.order_id });
                                 let _ = self.tx_screen_sender_channel.send(commit_msg);
@@ -443,12 +587,81 @@ impl Robot {
         }
     }
 
-    /// Simula el uso de un token para preparar helado.
-    pub fn use_token(&self, mut token: Token, ammount: i32) -> Token {
-        println!("Llenando pote de helado con {:?}", token.flavour);
+    /// Registra la llegada del token de `flavor` para medir su vuelta por el anillo.
+    pub fn record_token_arrival(&self, flavor: &IceCreamFlavor) {
+        self.token_tracker.lock().unwrap().record_arrival(flavor);
+    }
+
+    /// Registra que el robot esperó `wait` el token de `flavor` para preparar un pedido.
+    pub fn record_token_wait(&self, flavor: &IceCreamFlavor, wait: Duration) {
+        self.token_tracker.lock().unwrap().record_wait(flavor, wait);
+        metrics::add(
+            &TOKEN_WAIT_SECONDS,
+            &[("flavor", &flavor.to_string())],
+            wait.as_secs_f64(),
+        );
+        log_debug!("Esperé {} ms el token de {:?}", wait.as_millis(), flavor);
+    }
+
+    /// Envía periódicamente al robot líder las estadísticas de tokens de este robot. Si este
+    /// robot es el líder, las guarda directamente.
+    pub fn start_token_stats_reporter(self: Arc<Self>) {
+        loop {
+            thread::sleep(Duration::from_millis(TOKEN_STATS_REPORT_INTERVAL_MS));
+            let report = TokenStatsReport {
+                sender_id: self.id,
+                stats: self.token_tracker.lock().unwrap().report(),
+            };
+            if self.is_leader(self.id) {
+                self.store_token_stats(report);
+            } else {
+                let _ = self.tx_sender_channel.send(MessageType::TokenStats(report));
+            }
+        }
+    }
+
+    /// Guarda las estadísticas de tokens que envió un robot del anillo.
+    pub fn store_token_stats(&self, report: TokenStatsReport) {
+        log_debug!(
+            "Estadísticas de tokens del robot {}: {:?}",
+            report.sender_id,
+            report.stats
+        );
+        self.ring_token_stats
+            .lock()
+            .unwrap()
+            .insert(report.sender_id, report.stats);
+    }
+
+    /// Devuelve la espera promedio de los tokens, en milisegundos, según las últimas
+    /// estadísticas del robot `robot_id`, o `None` si todavía no esperó ninguno.
+    pub fn average_token_wait_ms(&self, robot_id: usize) -> Option<u64> {
+        let ring_token_stats = self.ring_token_stats.lock().unwrap();
+        let stats = ring_token_stats.get(&robot_id)?;
+        let (waits, total_wait_ms) =
+            stats
+                .values()
+                .fold((0u64, 0u64), |(waits, total), flavor_stats| {
+                    (
+                        waits.saturating_add(flavor_stats.waits),
+                        total.saturating_add(flavor_stats.total_wait_ms),
+                    )
+                });
+        total_wait_ms.checked_div(waits)
+    }
+
+    /// Simula el uso de un token para preparar helado para el pedido `order_id`.
+    pub fn use_token(&self, mut token: Token, ammount: i32, order_id: usize) -> Token {
+        log_info!(order = order_id; "Llenando pote de helado con {:?}", token.flavour);
+        let started = Instant::now();
         thread::sleep(Duration::from_millis(SLEEP_FACTOR * ammount as u64));
         token.available_ammount = token.available_ammount - ammount;
-        println!("Termine de usar {:?}", token.flavour);
+        metrics::add(
+            &TOKEN_HOLD_SECONDS,
+            &[("flavor", &token.flavour.to_string())],
+            started.elapsed().as_secs_f64(),
+        );
+        log_info!(order = order_id; "Termine de usar {:?}", token.flavour);
         return token;
     }
 
@@ -461,13 +674,16 @@ impl Robot {
     /// Establece un nuevo robot líder en la red.
     pub fn set_new_leader(&self, my_id: usize, new_leader_id: usize) {
         let mut id = self.leader_id.lock().unwrap();
+        if *id != new_leader_id {
+            metrics::inc(&ELECTIONS, &[]);
+            let mut term = self.leader_term.lock().unwrap();
+            *term += 1;
+            log_info!(leader = new_leader_id, term = *term; "Nuevo lider en la red");
+        }
         *id = new_leader_id;
-        //println!("Nuevo lider en la red: {}", new_leader_id);
 
-        if my_id == new_leader_id {
-            let mut is_leader = self.is_leader.lock().unwrap();
-            *is_leader = true;
-        }
+        // Una elección forzada puede elegir a otro robot mientras el líder anterior sigue vivo.
+        *self.is_leader.lock().unwrap() = my_id == new_leader_id;
     }
 
     /// Verifica si un ID específico está conectado al robot actual.
@@ -494,13 +710,15 @@ impl Robot {
         return *net_size == 2;
     }
 
-    /// Elimina un robot muerto de la tabla de pedidos.
+    /// Elimina un robot muerto de la tabla de pedidos y descarta sus estadísticas de tokens.
     pub fn remove_dead_from_orders_table(&self, dead_id: usize) {
         self.orders_table.lock().unwrap().remove_robot(dead_id);
+        self.ring_token_stats.lock().unwrap().remove(&dead_id);
     }
 
     /// Conecta al siguiente robot en la red mediante TCP.
     pub fn connect_to_next_robot(&self) {
+        recorder::set_peer(Peer::Robot);
         let tx_sender_channel: Arc<Sender<MessageType>> = self.tx_sender_channel.clone();
         let next_robot_id = self.find_next_id();
 
@@ -509,16 +727,13 @@ impl Robot {
                 let next_addr = format!("127.0.0.1:{}", 10000 + next_id);
                 match TcpStream::connect(&next_addr) {
                     Ok(mut stream) => {
-                        println!("Robot {}: Connected to next robot id: {}", self.id, next_id);
+                        log_info!("Connected to next robot id: {}", next_id);
 
                         let robot_intro_msg = MessageType::RobotIntroduction(RobotIntroduction {
                             sender_id: self.id,
                         });
 
-                        let serialized = serialize_message(&robot_intro_msg)
-                            .expect("Failed to serialize message");
-
-                        let _ = stream.write_all(&serialized);
+                        let _ = send_message(&mut stream, &robot_intro_msg);
 
                         let rx_sender_channel = self.rx_sender_channel.lock().unwrap();
                         loop {
@@ -526,7 +741,7 @@ impl Robot {
                                 Ok(message) => {
                                     match message {
                                         MessageType::Kill() => {
-                                            println!(
+                                            log_info!(
                                                 "Received KILL command. Terminating sender thread."
                                             );
                                             break;
@@ -534,10 +749,14 @@ impl Robot {
                                         _ => {
                                             thread::sleep(Duration::from_millis(1));
 
-                                            let serialized = serialize_message(&message)
-                                                .expect("Failed to serialize message");
-
-                                            if let Err(_) = stream.write_all(&serialized) {
+                                            if let Ok(()) = send_message(&mut stream, &message) {
+                                                if let MessageType::Token(ref token) = message {
+                                                    metrics::inc(
+                                                        &TOKENS_PASSED,
+                                                        &[("flavor", &token.flavour.to_string())],
+                                                    );
+                                                }
+                                            } else {
                                                 // Falla enviar por el socket
                                                 // mandamos por canal para que se vuelva a procesar (en el nuevo sender)
                                                 let _ = tx_sender_channel.send(message);
@@ -551,33 +770,101 @@ impl Robot {
                                     }
                                 }
                                 Err(_) => {
-                                    println!("Channel closed");
+                                    log_warn!("Channel closed");
                                     break;
                                 }
                             }
                         }
                     }
                     Err(e) => {
-                        eprintln!("Next Robot is Offline: {}", e);
+                        log_error!("Next Robot is Offline: {}", e);
                     }
                 }
             }
             None => {
-                eprintln!("Error: Next robot not found.");
+                log_error!("Next robot not found");
             }
         }
     }
 
+    /// Devuelve la cantidad de cambios de líder que vio el robot.
+    pub fn leader_term(&self) -> u64 {
+        *self.leader_term.lock().unwrap()
+    }
+
     /// Verifica si el ID especificado es el líder de la red.
     pub fn is_leader(&self, id: usize) -> bool {
         let leader = self.leader_id.lock().unwrap();
         return *leader == id;
     }
 
-    /// Encuentra el robot con menos pedidos pendientes para asignarle un nuevo pedido.
+    /// Encuentra el robot con menos pedidos pendientes para asignarle un nuevo pedido, sin contar
+    /// los retirados. Entre los que tienen la misma cantidad, elige el que menos espera los tokens.
     pub fn find_target_robot(&self) -> Option<usize> {
-        let orders_table = self.orders_table.lock().unwrap();
-        return orders_table.robot_with_least_orders().clone();
+        let drained = self.drained.lock().unwrap().clone();
+        let candidates = self
+            .orders_table
+            .lock()
+            .unwrap()
+            .robots_with_least_orders(&drained);
+        candidates
+            .into_iter()
+            .min_by_key(|&robot_id| (self.average_token_wait_ms(robot_id).unwrap_or(0), robot_id))
+    }
+
+    /// Deja de asignarle pedidos nuevos al robot `robot_id` o, si `drained` es `false`, vuelve a
+    /// asignárselos.
+    pub fn set_drained(&self, robot_id: usize, drained: bool) {
+        let mut drained_robots = self.drained.lock().unwrap();
+        if drained {
+            drained_robots.insert(robot_id);
+        } else {
+            drained_robots.remove(&robot_id);
+        }
+        log_info!(
+            "Robot {} {}",
+            robot_id,
+            if drained {
+                "retirado: no recibe pedidos nuevos"
+            } else {
+                "vuelve a recibir pedidos"
+            }
+        );
+    }
+
+    /// Devuelve los IDs de los robots a los que no se les asignan pedidos nuevos.
+    pub fn drained_robots(&self) -> Vec<usize> {
+        let mut drained: Vec<usize> = self.drained.lock().unwrap().iter().copied().collect();
+        drained.sort();
+        drained
+    }
+
+    /// Repone stock de un sabor. Lo aplica el líder: actualiza su tabla de stock, replica el
+    /// cambio en el anillo con `UpdateStock` y suma los gramos al token cuando vuelva a pasar.
+    pub fn restock(&self, restock: &Restock) {
+        let modified_values = HashMap::from([(restock.flavor.clone(), restock.grams)]);
+        let timestamp = self.add_stock(modified_values.clone());
+        let update_stock_msg = MessageType::UpdateStock(UpdateData {
+            modified_values,
+            timestamp,
+            subtract: false,
+        });
+        let _ = self.tx_sender_channel.send(update_stock_msg);
+        *self
+            .pending_restocks
+            .lock()
+            .unwrap()
+            .entry(restock.flavor.clone())
+            .or_insert(0) += restock.grams;
+        log_info!("Repuestos {} g de {}", restock.grams, restock.flavor);
+        self.publish_stock();
+    }
+
+    /// Suma al token los gramos repuestos de su sabor que todavía no tenía.
+    pub fn restock_token(&self, token: &mut Token) {
+        if let Some(grams) = self.pending_restocks.lock().unwrap().remove(&token.flavour) {
+            token.available_ammount += grams;
+        }
     }
 
     /// Agrega un nuevo pedido a la tabla interna de pedidos.
@@ -593,12 +880,38 @@ impl Robot {
             .add_order_for_robot(robot_id, order_id, order_details);
     }
 
-    /// Elimina un pedido completado de la tabla.
+    /// Elimina un pedido completado de la tabla. Si el robot es el líder, publica la nueva
+    /// profundidad de la cola de pedidos a las pantallas.
     pub fn remove_completed_order(&self, robot_id: usize, order_id: usize) {
         self.orders_table
             .lock()
             .unwrap()
-            .remove_order_for_robot(robot_id, order_id)
+            .remove_order_for_robot(robot_id, order_id);
+        if self.is_leader(self.id) {
+            self.publish_stock();
+        }
+    }
+
+    /// Cancela un pedido cuyo plazo venció en la pantalla que lo tomó: lo saca de la tabla de
+    /// pedidos y avisa al resto de los robots para que hagan lo mismo. El stock no se devuelve,
+    /// porque el robot asignado puede estar sirviéndolo. Si el pedido ya no estaba en la tabla
+    /// (por ejemplo, porque ya se entregó), no hace nada.
+    pub fn cancel_order(&self, order_id: usize) {
+        let robot_id = match self.orders_table.lock().unwrap().find_order_robot(order_id) {
+            Some(robot_id) => robot_id,
+            None => return,
+        };
+        log_warn!(
+            order = order_id;
+            "Pedido cancelado por vencimiento de plazo (asignado al robot {})",
+            robot_id
+        );
+        self.remove_completed_order(robot_id, order_id);
+        let ordered_delivered_msg = MessageType::OrderDelivered(OrderDelivered {
+            robot_id_maker: robot_id,
+            order_id,
+        });
+        let _ = self.tx_sender_channel.send(ordered_delivered_msg);
     }
 
     /// Encuentra el ID del robot del cual se estaba leyendo en la red.
@@ -615,7 +928,7 @@ impl Robot {
     }
 
     /// Envía un mensaje de elección de líder al identificador especificado.
-    pub fn send_leader_election_message(&self, id: usize, dead_leader_id: usize) {
+    pub fn send_leader_election_message(&self, id: usize, dead_leader_id: Option<usize>) {
         let election_msg = MessageType::Election(Election {
             sender_id: id,
             current_candidate_id: id,
@@ -6,9 +6,9 @@
 ///
 /// La estructura `OrdersTable` gestiona múltiples listas de órdenes asociadas a diferentes robots, permitiendo inicialización, adición, eliminación y consultas sobre las órdenes.
 ///
-/// Incluye métodos para determinar el robot con menos órdenes y para eliminar robots y sus órdenes de la tabla.
+/// Incluye métodos para determinar los robots con menos órdenes y para eliminar robots y sus órdenes de la tabla.
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::common::flavors::IceCreamFlavor;
 
@@ -96,20 +96,43 @@ impl OrdersTable {
         }
     }
 
-    /// Método para obtener el ID del robot con menos órdenes (tamaño de OrdersList más pequeño).
-    pub fn robot_with_least_orders(&self) -> Option<usize> {
-        let mut min_orders_count = usize::MAX;
-        let mut min_robot_id = None;
-
-        for (&robot_id, orders_list) in &self.orders_map {
-            let orders_count = orders_list.orders.len();
-            if orders_count < min_orders_count {
-                min_orders_count = orders_count;
-                min_robot_id = Some(robot_id);
-            }
-        }
+    /// Método para obtener el ID del robot al que se le asignó una orden, si sigue en la tabla.
+    pub fn find_order_robot(&self, order_id: usize) -> Option<usize> {
+        self.orders_map
+            .iter()
+            .find(|(_, orders_list)| {
+                orders_list
+                    .orders
+                    .iter()
+                    .any(|order| order.order_id == order_id)
+            })
+            .map(|(&robot_id, _)| robot_id)
+    }
+
+    /// Método para obtener los IDs de los robots con menos órdenes (tamaño de OrdersList más
+    /// pequeño), sin contar los de `excluded`.
+    pub fn robots_with_least_orders(&self, excluded: &HashSet<usize>) -> Vec<usize> {
+        let candidates = || {
+            self.orders_map
+                .iter()
+                .filter(|(robot_id, _)| !excluded.contains(robot_id))
+        };
+        let min_orders_count = candidates()
+            .map(|(_, orders_list)| orders_list.orders.len())
+            .min();
+
+        candidates()
+            .filter(|(_, orders_list)| Some(orders_list.orders.len()) == min_orders_count)
+            .map(|(&robot_id, _)| robot_id)
+            .collect()
+    }
 
-        min_robot_id
+    /// Método para obtener la cantidad de órdenes asignadas a todos los robots.
+    pub fn pending_orders(&self) -> usize {
+        self.orders_map
+            .values()
+            .map(|orders_list| orders_list.orders.len())
+            .sum()
     }
 
     /// Método para eliminar un robot y todas sus órdenes de la tabla.
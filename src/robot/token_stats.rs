@@ -0,0 +1,106 @@
+/// Este módulo define la estructura `TokenTracker`, con la que cada robot mide la circulación de
+/// los tokens de cada sabor.
+///
+/// Un robot registra cada llegada de un token, para medir cuánto tarda en dar la vuelta al anillo,
+/// y cada espera hasta conseguir un token para preparar un pedido. Las estadísticas acumuladas se
+/// envían periódicamente al robot líder como un `TokenStatsReport`.
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::common::flavors::IceCreamFlavor;
+use crate::common::messages::FlavorTokenStats;
+
+/// Estadísticas de circulación de los tokens medidas por un robot.
+#[derive(Debug, Default)]
+pub struct TokenTracker {
+    stats: HashMap<IceCreamFlavor, FlavorTokenStats>,
+    last_arrivals: HashMap<IceCreamFlavor, Instant>,
+}
+
+impl TokenTracker {
+    /// Método constructor para `TokenTracker`, sin estadísticas.
+    pub fn new() -> Self {
+        TokenTracker::default()
+    }
+
+    /// Registra la llegada del token de `flavor` y, si ya había llegado antes, la duración de su
+    /// vuelta por el anillo. Devuelve esa duración.
+    pub fn record_arrival(&mut self, flavor: &IceCreamFlavor) -> Option<Duration> {
+        let now = Instant::now();
+        let round_trip = self
+            .last_arrivals
+            .insert(flavor.clone(), now)
+            .map(|last_arrival| now.duration_since(last_arrival));
+        if let Some(round_trip) = round_trip {
+            self.stats
+                .entry(flavor.clone())
+                .or_default()
+                .add_round_trip(round_trip.as_millis() as u64);
+        }
+        round_trip
+    }
+
+    /// Registra que el robot esperó `wait` el token de `flavor` para preparar un pedido.
+    pub fn record_wait(&mut self, flavor: &IceCreamFlavor, wait: Duration) {
+        self.stats
+            .entry(flavor.clone())
+            .or_default()
+            .add_wait(wait.as_millis() as u64);
+    }
+
+    /// Devuelve las estadísticas de cada sabor medidas hasta el momento.
+    pub fn report(&self) -> HashMap<IceCreamFlavor, FlavorTokenStats> {
+        self.stats.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_arrival_has_no_round_trip() {
+        let mut tracker = TokenTracker::new();
+        assert!(tracker.record_arrival(&IceCreamFlavor::Mint).is_none());
+        assert!(tracker.report().is_empty());
+
+        assert!(tracker.record_arrival(&IceCreamFlavor::Mint).is_some());
+        assert!(tracker.record_arrival(&IceCreamFlavor::Mint).is_some());
+        assert!(tracker.record_arrival(&IceCreamFlavor::Vanilla).is_none());
+        let report = tracker.report();
+        assert_eq!(report.len(), 1);
+        assert_eq!(report[&IceCreamFlavor::Mint].round_trips, 2);
+        assert_eq!(report[&IceCreamFlavor::Mint].waits, 0);
+    }
+
+    #[test]
+    fn waits_are_accumulated_per_flavor() {
+        let mut tracker = TokenTracker::new();
+        tracker.record_wait(&IceCreamFlavor::Chocolate, Duration::from_millis(10));
+        tracker.record_wait(&IceCreamFlavor::Chocolate, Duration::from_millis(30));
+        tracker.record_wait(&IceCreamFlavor::Mint, Duration::from_millis(5));
+
+        let report = tracker.report();
+        let chocolate = &report[&IceCreamFlavor::Chocolate];
+        assert_eq!(chocolate.waits, 2);
+        assert_eq!(chocolate.total_wait_ms, 40);
+        assert_eq!(chocolate.max_wait_ms, 30);
+        assert_eq!(chocolate.avg_wait_ms(), Some(20));
+        assert_eq!(chocolate.avg_round_trip_ms(), None);
+        assert_eq!(report[&IceCreamFlavor::Mint].avg_wait_ms(), Some(5));
+    }
+
+    #[test]
+    fn totals_saturate_instead_of_overflowing() {
+        let mut stats = FlavorTokenStats::default();
+        stats.add_round_trip(u64::MAX);
+        stats.add_round_trip(1);
+        stats.add_wait(u64::MAX);
+        stats.add_wait(u64::MAX);
+
+        assert_eq!(stats.total_round_trip_ms, u64::MAX);
+        assert_eq!(stats.max_round_trip_ms, u64::MAX);
+        assert_eq!(stats.avg_round_trip_ms(), Some(u64::MAX / 2));
+        assert_eq!(stats.total_wait_ms, u64::MAX);
+    }
+}
@@ -1,5 +1,7 @@
 use super::robot::Robot;
+use crate::common::recorder::{self, Peer};
 use crate::common::{constants::BUFFER_SIZE, flavors::IceCreamFlavor, messages::*};
+use crate::{log_error, log_info, log_warn};
 use std::{io::Read, net::TcpStream, sync::Arc, thread, time::SystemTime};
 
 /// Maneja una conexión TCP entrante, esperando un mensaje de introducción que determina
@@ -18,61 +20,55 @@ pub fn handle_incoming_connection(robot: Arc<Robot>, mut socket: TcpStream, my_i
         }
         Ok(n) => {
             read_buffer.extend_from_slice(&buffer[..n]);
-            match deserialize_message(&buffer) {
-                Some(message) => {
-                    match message {
-                        MessageType::RobotIntroduction(_) => {
-                            //println!("Robot introduction received!");
-                            handle_robot_connection(
-                                robot_ref_c,
-                                my_id,
-                                socket.try_clone().unwrap(),
-                            );
-                        }
+            if let Some(message) = take_message(&mut read_buffer) {
+                match message {
+                    MessageType::RobotIntroduction(_) => {
+                        //println!("Robot introduction received!");
+                        handle_robot_connection(
+                            robot_ref_c,
+                            my_id,
+                            resume_reading(read_buffer, socket),
+                        );
+                    }
 
-                        MessageType::ScreenIntroduction(screen_data) => {
-                            //println!("Recibido una Screen Introduction");
-                            if robot.is_leader(my_id) {
-                                //println!("soy lider, manejo la conexion.");
-                                handle_screen_connection(
-                                    robot,
-                                    my_id,
-                                    socket.try_clone().unwrap(),
-                                    screen_data.sender_id,
-                                    false,
-                                )
-                            } else {
-                                //println!("No soy lider. No manejo la conexion");
-                            }
-                        }
-                        MessageType::NewLeaderIntroduction(screen_id) => {
-                            //println!("reciido new leader intro...");
-                            if robot.is_leader(my_id) {
-                                //println!("soy lider, manejo la new conexion.");
-                                handle_screen_connection(
-                                    robot,
-                                    my_id,
-                                    socket.try_clone().unwrap(),
-                                    screen_id,
-                                    true,
-                                )
-                            } else {
-                                //println!("No soy lider. No manejo la new conexion");
-                            }
+                    MessageType::ScreenIntroduction(screen_data) => {
+                        //println!("Recibido una Screen Introduction");
+                        if robot.is_leader(my_id) {
+                            //println!("soy lider, manejo la conexion.");
+                            handle_screen_connection(
+                                robot,
+                                my_id,
+                                resume_reading(read_buffer, socket),
+                                screen_data.sender_id,
+                                false,
+                            )
+                        } else {
+                            //println!("No soy lider. No manejo la conexion");
                         }
-                        _ => {
-                            //eprintln!("Conection requires introduction: {:?}", read_buffer);
+                    }
+                    MessageType::NewLeaderIntroduction(screen_id) => {
+                        //println!("reciido new leader intro...");
+                        if robot.is_leader(my_id) {
+                            //println!("soy lider, manejo la new conexion.");
+                            handle_screen_connection(
+                                robot,
+                                my_id,
+                                resume_reading(read_buffer, socket),
+                                screen_id,
+                                true,
+                            )
+                        } else {
+                            //println!("No soy lider. No manejo la new conexion");
                         }
                     }
-                    read_buffer.clear(); // Clear the buffer
-                }
-                None => {
-                    //println!("No value present");
+                    _ => {
+                        //eprintln!("Conection requires introduction: {:?}", read_buffer);
+                    }
                 }
             }
         }
         Err(e) => {
-            eprintln!("Robot {}: Failed to read from socket; err = {:?}", my_id, e);
+            log_error!("Failed to read from socket; err = {:?}", e);
         }
     }
 }
@@ -86,272 +82,171 @@ pub fn handle_incoming_connection(robot: Arc<Robot>, mut socket: TcpStream, my_i
 /// coincide con el ID del robot actual. En caso contrario, se llama a la función
 /// `handle_other_messages` para seguir procesando el mensaje recibido.
 ///
-pub fn handle_robot_connection(robot: Arc<Robot>, my_id: usize, mut socket: TcpStream) {
+pub fn handle_robot_connection(robot: Arc<Robot>, my_id: usize, mut socket: impl Read) {
+    recorder::set_peer(Peer::Robot);
     let robot_ref = robot.clone();
-    let tx_sender = robot.tx_sender_channel.clone();
-    let tx_prepare = robot.tx_prepare_channel.clone();
     let mut buffer = vec![0; BUFFER_SIZE];
     let mut read_buffer = Vec::new();
 
     loop {
-        let robot_ref_c = robot_ref.clone();
         match socket.read(&mut buffer) {
             Ok(0) => {
-                // Murio el robot donde yo estaba leyendo mensajes.
-                let prev_robot_id = robot_ref_c.find_prev_robot(my_id);
-                // prev robot id es el que murio. Tengo que notificar su baja.
-                if let Some(dead_id) = prev_robot_id {
-                    println!("ID Robot Muerto: {}", dead_id);
-                    if robot.is_net_size_2() {
-                        // si el tamano de la red era 2 y murio el robot, estoy solo.
-                        // update network
-                        Robot::update_network(robot.clone(), dead_id);
-
-                        // kill old sender thread
-                        let _ = tx_sender.send(MessageType::Kill());
-
-                        // start another sender thread
-                        let robot_ref = robot.clone();
-                        thread::spawn(move || {
-                            robot_ref.connect_to_next_robot();
-                        });
-
-                        if robot.is_leader(my_id) {
-                        } else {
-                            robot.set_new_leader(my_id, my_id);
-                            // screen sender thread start todo!()
-                        }
-
-                        for flavor in IceCreamFlavor::iter() {
-                            // para cada gusto. Me fijo si se perdio el token.
-                            if robot.has_token(flavor) {
-                                //println!("Yo tengo el token de {:?}, no se perdio...", flavor);
-                                // Yo tengo el token, no se perdio.
-                            } else {
-                                // Emitir token perdido.
-                                println!("No se encontro el token de {:?}", flavor);
-                                let current_stock = robot.get_flavor_stock(flavor);
-                                let lost_flavour_token = MessageType::Token(Token {
-                                    sender_id: my_id,
-                                    flavour: flavor.clone(),
-                                    last_modified_by_id: my_id,
-                                    last_modification_timestamp: SystemTime::now(),
-                                    available_ammount: current_stock.clone(),
-                                });
-                                // println!(
-                                //     "Nuevo token de {:?} emitido con stock: {:?}",
-                                //     flavor, current_stock
-                                // );
-                                let _ = tx_sender.send(lost_flavour_token);
-                            }
-                        }
-
-                        // Buscar ordenes en la tabla de robot muerto.
-                        let lost_orders = robot.get_robot_orders(dead_id);
-                        println!(
-                            "Las siguientes ordenes las estaba laburando el robot muerto: {:?},",
-                            lost_orders
-                        );
-
-                        robot.remove_dead_from_orders_table(dead_id);
-                        // Stock recuperado por los pedidos perdidos.
-                        recover_stock_from_lost_orders(&lost_orders, &robot, &tx_sender);
-                        // Para los pedidos perdidos, los intento re asignar.
-                        reassign_lost_orders(lost_orders, &robot, my_id);
-
-                        // Inicio screen sender thread con pantalla
-                        let arc_robot = robot.clone();
-                        thread::spawn(move || arc_robot.connect_to_screen(true));
-                    } else {
-                        // Notificar al siguiente robot.
-                        let deadrobot_msg = MessageType::DeadRobot(DeadRobot {
-                            sender_id: my_id,
-                            dead_robot_id: dead_id,
-                        });
-                        let _ = tx_sender.send(deadrobot_msg);
-
-                        // Si el id muerto es el lider, hay que elegir nuevo lider
-                        if robot_ref.is_leader(dead_id) {
-                            robot_ref.send_leader_election_message(my_id, dead_id);
-                        }
-
-                        // Update network
-                        Robot::update_network(robot.clone(), dead_id);
-                    }
-
-                    // Si soy el lider. Revisar si se perdieron tokens.
-                    if robot.is_leader(my_id) {
-                        println!("Robot muerto detectado. Ver si se perdieron tokens.");
-                        recover_lost_tokens(&robot, &tx_sender);
-
-                        // Soy lider, tengo que re asignar pedidos que pertenecian al robot muerto.
-                        // buscar ordenes en la tabla de ese robot.
-                        let lost_orders = robot.get_robot_orders(dead_id);
-                        println!(
-                            "Las siguientes ordenes correspondian al robot muerto: {:?},",
-                            lost_orders
-                        );
-
-                        robot.remove_dead_from_orders_table(dead_id);
-                        let remove_robot_msg = MessageType::RemoveRobot(dead_id.clone());
-                        let _ = tx_sender.send(remove_robot_msg);
-
-                        // Stock recuperado por los pedidos perdidos.
-                        recover_stock_from_lost_orders(&lost_orders, &robot, &tx_sender);
-
-                        // Para los pedidos perdidos, los intento re asignar.
-                        reassign_lost_orders(lost_orders, &robot, my_id);
-                    }
-                    break;
-                } else {
-                    eprintln!("No Value Found");
-                }
+                recorder::record_closed();
+                handle_prev_robot_death(robot, my_id);
                 break;
             }
             Ok(n) => {
                 read_buffer.extend_from_slice(&buffer[..n]);
-                match deserialize_message(&buffer) {
-                    Some(message) => {
-                        match message {
-                            MessageType::Prepare(ref order_data) => {
-                                if order_data.target_id == my_id {
-                                    let _ = tx_prepare.send(message);
-                                } else {
-                                    let _ = tx_sender.send(message);
-                                }
-                            }
-
-                            _ => {
-                                thread::spawn(move || {
-                                    handle_other_messages(
-                                        robot_ref_c.clone(),
-                                        my_id.clone(),
-                                        message,
-                                    );
-                                });
-                            }
+                for message in take_messages(&mut read_buffer) {
+                    let robot_ref_c = robot_ref.clone();
+                    match message {
+                        MessageType::Prepare(_) => handle_prepare(&robot, my_id, message),
+
+                        _ => {
+                            thread::spawn(move || {
+                                handle_other_messages(robot_ref_c.clone(), my_id.clone(), message);
+                            });
                         }
-                        read_buffer.clear(); // Clear the buffer
-                    }
-                    None => {
-                        //
                     }
                 }
             }
             Err(e) => {
-                // Murio el robot donde yo estaba leyendo mensajes.
-                let prev_robot_id = robot_ref_c.find_prev_robot(my_id);
-                // prev robot id es el que murio. Tengo que notificar su baja.
-                if let Some(dead_id) = prev_robot_id {
-                    println!("ID Robot Muerto: {}", dead_id);
-                    if robot.is_net_size_2() {
-                        // si el tamano de la red era 2 y murio el robot, estoy solo.
-                        // update network
-                        Robot::update_network(robot.clone(), dead_id);
-
-                        // kill old sender thread
-                        let _ = tx_sender.send(MessageType::Kill());
-
-                        // start another sender thread
-                        let robot_ref = robot.clone();
-                        thread::spawn(move || {
-                            robot_ref.connect_to_next_robot();
-                        });
+                recorder::record_closed();
+                handle_prev_robot_death(robot, my_id);
+                log_error!("Failed to read from socket; err = {:?}", e);
+                break;
+            }
+        }
+    }
+}
 
-                        if robot.is_leader(my_id) {
-                        } else {
-                            robot.set_new_leader(my_id, my_id);
-                            // screen sender thread start todo!()
-                        }
+/// Encola un mensaje `Prepare` si el pedido está asignado a este robot, o lo reenvía al
+/// siguiente robot del anillo.
+pub fn handle_prepare(robot: &Robot, my_id: usize, message: MessageType) {
+    if let MessageType::Prepare(ref order_data) = message {
+        if order_data.target_id == my_id {
+            robot.enqueue_prepare(message);
+        } else {
+            let _ = robot.tx_sender_channel.send(message);
+        }
+    }
+}
 
-                        for flavor in IceCreamFlavor::iter() {
-                            // para cada gusto. Me fijo si se perdio el token.
-                            if robot.has_token(flavor) {
-                                //println!("Yo tengo el token de {:?}, no se perdio...", flavor);
-                                // Yo tengo el token, no se perdio.
-                            } else {
-                                // Emitir token perdido.
-                                println!("No se encontro el token de {:?}", flavor);
-                                let current_stock = robot.get_flavor_stock(flavor);
-                                let lost_flavour_token = MessageType::Token(Token {
-                                    sender_id: my_id,
-                                    flavour: flavor.clone(),
-                                    last_modified_by_id: my_id,
-                                    last_modification_timestamp: SystemTime::now(),
-                                    available_ammount: current_stock.clone(),
-                                });
-                                // println!(
-                                //     "Nuevo token de {:?} emitido con stock: {:?}",
-                                //     flavor, current_stock
-                                // );
-                                let _ = tx_sender.send(lost_flavour_token);
-                            }
-                        }
+/// Maneja la caída del robot anterior del anillo, del que este robot leía mensajes.
+///
+/// Si el anillo era de dos robots, este robot queda solo: pasa a ser el líder, emite los tokens
+/// que no tiene y se queda con los pedidos del robot caído. Si no, avisa la caída al resto del
+/// anillo y, si el caído era el líder, inicia la elección. Si este robot es el líder, revisa si
+/// se perdieron tokens y reasigna los pedidos del robot caído.
+pub fn handle_prev_robot_death(robot: Arc<Robot>, my_id: usize) {
+    let tx_sender = robot.tx_sender_channel.clone();
+    // Murio el robot donde yo estaba leyendo mensajes.
+    let prev_robot_id = robot.find_prev_robot(my_id);
+    // prev robot id es el que murio. Tengo que notificar su baja.
+    if let Some(dead_id) = prev_robot_id {
+        log_warn!("ID Robot Muerto: {}", dead_id);
+        if robot.is_net_size_2() {
+            // si el tamano de la red era 2 y murio el robot, estoy solo.
+            // update network
+            Robot::update_network(robot.clone(), dead_id);
+
+            // kill old sender thread
+            let _ = tx_sender.send(MessageType::Kill());
+
+            // start another sender thread
+            let robot_ref = robot.clone();
+            thread::spawn(move || {
+                robot_ref.connect_to_next_robot();
+            });
 
-                        // Buscar ordenes en la tabla de robot muerto.
-                        let lost_orders = robot.get_robot_orders(dead_id);
-                        println!(
-                            "Las siguientes ordenes las estaba laburando el robot muerto: {:?},",
-                            lost_orders
-                        );
+            if robot.is_leader(my_id) {
+            } else {
+                robot.set_new_leader(my_id, my_id);
+                // screen sender thread start todo!()
+            }
 
-                        robot.remove_dead_from_orders_table(dead_id);
-                        // Stock recuperado por los pedidos perdidos.
-                        recover_stock_from_lost_orders(&lost_orders, &robot, &tx_sender);
-                        // Para los pedidos perdidos, los intento re asignar.
-                        reassign_lost_orders(lost_orders, &robot, my_id);
+            for flavor in IceCreamFlavor::iter() {
+                // para cada gusto. Me fijo si se perdio el token.
+                if robot.has_token(flavor) {
+                    //println!("Yo tengo el token de {:?}, no se perdio...", flavor);
+                    // Yo tengo el token, no se perdio.
+                } else {
+                    // Emitir token perdido.
+                    log_warn!("No se encontro el token de {:?}", flavor);
+                    let current_stock = robot.get_flavor_stock(flavor);
+                    let lost_flavour_token = MessageType::Token(Token {
+                        sender_id: my_id,
+                        flavour: flavor.clone(),
+                        last_modified_by_id: my_id,
+                        last_modification_timestamp: SystemTime::now(),
+                        available_ammount: current_stock.clone(),
+                    });
+                    // println!(
+                    //     "Nuevo token de {:?} emitido con stock: {:?}",
+                    //     flavor, current_stock
+                    // );
+                    let _ = tx_sender.send(lost_flavour_token);
+                }
+            }
 
-                        // Inicio screen sender thread con pantalla
-                        let arc_robot = robot.clone();
-                        thread::spawn(move || arc_robot.connect_to_screen(true));
-                    } else {
-                        // Notificar al siguiente robot.
-                        let deadrobot_msg = MessageType::DeadRobot(DeadRobot {
-                            sender_id: my_id,
-                            dead_robot_id: dead_id,
-                        });
-                        let _ = tx_sender.send(deadrobot_msg);
+            // Buscar ordenes en la tabla de robot muerto.
+            let lost_orders = robot.get_robot_orders(dead_id);
+            log_info!(
+                "Las siguientes ordenes las estaba laburando el robot muerto: {:?},",
+                lost_orders
+            );
 
-                        // Si el id muerto es el lider, hay que elegir nuevo lider
-                        if robot_ref.is_leader(dead_id) {
-                            robot_ref.send_leader_election_message(my_id, dead_id);
-                        }
+            robot.remove_dead_from_orders_table(dead_id);
+            // Stock recuperado por los pedidos perdidos.
+            recover_stock_from_lost_orders(&lost_orders, &robot, &tx_sender);
+            // Para los pedidos perdidos, los intento re asignar.
+            reassign_lost_orders(lost_orders, &robot, my_id);
 
-                        // Update network
-                        Robot::update_network(robot.clone(), dead_id);
-                    }
+            // Inicio screen sender thread con pantalla
+            let arc_robot = robot.clone();
+            thread::spawn(move || arc_robot.connect_to_screen(true));
+        } else {
+            // Notificar al siguiente robot.
+            let deadrobot_msg = MessageType::DeadRobot(DeadRobot {
+                sender_id: my_id,
+                dead_robot_id: dead_id,
+            });
+            let _ = tx_sender.send(deadrobot_msg);
 
-                    // Si soy el lider. Revisar si se perdieron tokens.
-                    if robot.is_leader(my_id) {
-                        println!("Robot muerto detectado. Ver si se perdieron tokens.");
-                        recover_lost_tokens(&robot, &tx_sender);
-
-                        // Soy lider, tengo que re asignar pedidos que pertenecian al robot muerto.
-                        // buscar ordenes en la tabla de ese robot.
-                        let lost_orders = robot.get_robot_orders(dead_id);
-                        println!(
-                            "Las siguientes ordenes correspondian al robot muerto: {:?},",
-                            lost_orders
-                        );
+            // Si el id muerto es el lider, hay que elegir nuevo lider
+            if robot.is_leader(dead_id) {
+                robot.send_leader_election_message(my_id, Some(dead_id));
+            }
 
-                        robot.remove_dead_from_orders_table(dead_id);
-                        let remove_robot_msg = MessageType::RemoveRobot(dead_id.clone());
-                        let _ = tx_sender.send(remove_robot_msg);
+            // Update network
+            Robot::update_network(robot.clone(), dead_id);
+        }
 
-                        // Stock recuperado por los pedidos perdidos.
-                        recover_stock_from_lost_orders(&lost_orders, &robot, &tx_sender);
+        // Si soy el lider. Revisar si se perdieron tokens.
+        if robot.is_leader(my_id) {
+            log_warn!("Robot muerto detectado. Ver si se perdieron tokens.");
+            recover_lost_tokens(&robot, &tx_sender);
+
+            // Soy lider, tengo que re asignar pedidos que pertenecian al robot muerto.
+            // buscar ordenes en la tabla de ese robot.
+            let lost_orders = robot.get_robot_orders(dead_id);
+            log_info!(
+                "Las siguientes ordenes correspondian al robot muerto: {:?},",
+                lost_orders
+            );
 
-                        // Para los pedidos perdidos, los intento re asignar.
-                        reassign_lost_orders(lost_orders, &robot, my_id);
-                    }
-                    break;
-                } else {
-                    eprintln!("No Value Found");
-                }
-                eprintln!("Robot {}: Failed to read from socket; err = {:?}", my_id, e);
-                break;
-            }
+            robot.remove_dead_from_orders_table(dead_id);
+            let remove_robot_msg = MessageType::RemoveRobot(dead_id.clone());
+            let _ = tx_sender.send(remove_robot_msg);
+
+            // Stock recuperado por los pedidos perdidos.
+            recover_stock_from_lost_orders(&lost_orders, &robot, &tx_sender);
+
+            // Para los pedidos perdidos, los intento re asignar.
+            reassign_lost_orders(lost_orders, &robot, my_id);
         }
+    } else {
+        log_error!("No Value Found");
     }
 }
 
@@ -392,13 +287,13 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
             // Soy lider y se detecto la caida de un robot:
             if robot.is_leader(my_id) {
                 // Ver si se perdieron tokens.
-                println!("Robot muerto detectado. Ver si se perdieron tokens.");
+                log_warn!("Robot muerto detectado. Ver si se perdieron tokens.");
                 recover_lost_tokens(&robot, &tx_sender);
 
                 // Soy lider, tengo que re asignar pedidos que pertenecian al robot muerto.
                 // buscar ordenes en la tabla de ese robot.
                 let lost_orders = robot.get_robot_orders(dead_robot_id);
-                println!(
+                log_info!(
                     "Las siguientes ordenes correspondian al robot muerto: {:?},",
                     lost_orders
                 );
@@ -418,7 +313,7 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
 
             //println!("Recibido possible lost token: {:?}", lost_token_data);
             if robot.is_leader(my_id) {
-                println!("No se encontro el token de {:?}", lost_token_data.flavor);
+                log_warn!("No se encontro el token de {:?}", lost_token_data.flavor);
                 let lost_flavour_token = MessageType::Token(Token {
                     sender_id: my_id,
                     flavour: lost_token_data.flavor.clone(),
@@ -434,7 +329,7 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
             } else {
                 //println!("No soy  lider, me fijo si tengo el token");
                 if robot.has_token(&lost_token_data.flavor) {
-                    println!(
+                    log_info!(
                         "Yo tengo el token de {:?}, no se perdio.",
                         lost_token_data.flavor
                     );
@@ -457,7 +352,7 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
         MessageType::TokenFound(ref flavor) => {
             if robot.is_leader(my_id) {
                 // do nothing
-                println!("Encontrado el token de: {:?}, no se perdio!", flavor);
+                log_info!("Encontrado el token de: {:?}, no se perdio!", flavor);
             } else {
                 let _ = tx_sender.send(message);
             }
@@ -481,7 +376,7 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
             if robot.is_leader(my_id) {
                 // do nothing
             } else {
-                println!("Murio el robot {}, lo saco de la tabla interna", dead_id);
+                log_info!("Murio el robot {}, lo saco de la tabla interna", dead_id);
                 robot.remove_dead_from_orders_table(*dead_id);
                 let _ = tx_sender.send(message);
             }
@@ -554,6 +449,7 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
 
             if current_candidate_id == my_id {
                 // Soy el nuevo lider de la red.
+                let previous_leader_id = *robot.leader_id.lock().unwrap();
                 robot.set_new_leader(my_id, current_candidate_id);
 
                 // Avisar al resto de la red el nuevo lider.
@@ -564,17 +460,26 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
                 });
                 let _ = tx_sender.send(newleader_msg);
 
-                let dead_id = election_data.dead_leader_id;
+                let Some(dead_id) = election_data.dead_leader_id else {
+                    // Elección forzada desde la consola de administración: no se cayó ningún
+                    // robot, así que no hay nada que recuperar. Si antes no era el líder, me
+                    // conecto con la pantalla líder en lugar del líder anterior.
+                    if previous_leader_id != my_id {
+                        let arc_robot = robot.clone();
+                        thread::spawn(move || arc_robot.connect_to_screen(true));
+                    }
+                    return;
+                };
                 //Soy el nuevo lder. Reemplazo al lider "dead_leader_id".
 
                 // Ver si se perdieron tokens.
-                println!("Soy el nuevo lider y murio un robot (viejo lider)... a ver si se perdieron tokens");
+                log_warn!(leader = my_id, term = robot.leader_term(); "Soy el nuevo lider y murio un robot (viejo lider)... a ver si se perdieron tokens");
                 recover_lost_tokens(&robot, &tx_sender);
 
                 // Soy lider, tengo que re asignar pedidos que pertenecian al robot muerto.
                 // buscar ordenes en la tabla de ese robot.
                 let lost_orders = robot.get_robot_orders(dead_id);
-                println!(
+                log_info!(
                     "Las siguientes ordenes las estaba laburando el robot muerto: {:?},",
                     lost_orders
                 );
@@ -628,13 +533,38 @@ pub fn handle_other_messages(robot: Arc<Robot>, my_id: usize, message: MessageTy
                 let _ = tx_sender.send(message);
             }
         }
-        MessageType::Token(ref token_data) => {
+        MessageType::Token(mut token) => {
             // Recibido mensaje de Token
-            robot.set_token_status(token_data.flavour.clone(), true);
-            let _ = robot.tx_token_channel.send(message);
+            robot.record_token_arrival(&token.flavour);
+            robot.restock_token(&mut token);
+            robot.set_token_status(token.flavour.clone(), true);
+            let _ = robot.tx_token_channel.send(MessageType::Token(token));
+        }
+        MessageType::DrainRobot(ref drain_data) => {
+            if drain_data.sender_id != my_id {
+                robot.set_drained(drain_data.robot_id, drain_data.drained);
+                // forward the message
+                let _ = tx_sender.send(message);
+            }
+        }
+        MessageType::Restock(ref restock) => {
+            if robot.is_leader(my_id) {
+                robot.restock(restock);
+            } else {
+                // forward the message
+                let _ = tx_sender.send(message);
+            }
+        }
+        MessageType::TokenStats(ref report) => {
+            if robot.is_leader(my_id) {
+                robot.store_token_stats(report.clone());
+            } else if report.sender_id != my_id {
+                // forward the message
+                let _ = tx_sender.send(message);
+            }
         }
         _ => {
-            println!("Unknown: {:?}", message);
+            log_warn!("Unknown: {:?}", message);
         }
     }
 }
@@ -652,7 +582,7 @@ fn recover_lost_tokens(
         if robot.has_token(flavor) {
             // Yo tengo el token, no se perdio.
         } else {
-            println!(
+            log_info!(
                 "Yo no tengo el token de {:?}, mando msj posible lost",
                 flavor
             );
@@ -728,9 +658,10 @@ fn reassign_lost_orders(
 
                 if robot.has_enough_stock(flavor_map.clone()) {
                     if let Some(target_id) = robot.find_target_robot() {
-                        println!(
-                            "Orden id {} asignada al robot id: {}",
-                            order.order_id, target_id
+                        log_info!(
+                            order = order.order_id;
+                            "Orden asignada al robot id: {}",
+                            target_id
                         );
                         let prepare_msg = MessageType::Prepare(Prepare {
                             sender_id: my_id,
@@ -772,20 +703,22 @@ fn reassign_lost_orders(
                             target_id: my_id,
                             order_details: flavor_map.clone(),
                         });
-                        let _ = robot.tx_prepare_channel.send(prepare_msg);
+                        robot.enqueue_prepare(prepare_msg);
                     }
                 } else {
-                    // Abort order por falta de stock
+                    // Abort order: no hay stock para reasignar el pedido del robot caído
                     let abort_order_msg = MessageType::Abort(Abort {
                         order_id: order_id.clone(),
+                        reason: AbortReason::RobotLost,
                     });
 
                     let _ = robot.tx_screen_sender_channel.send(abort_order_msg);
                 }
             }
+            robot.publish_stock();
         }
         None => {
-            println!("No lost orders.");
+            log_info!("No lost orders.");
         }
     }
 }
@@ -799,125 +732,35 @@ fn reassign_lost_orders(
 pub fn handle_screen_connection(
     robot: Arc<Robot>,
     my_id: usize,
-    mut socket: TcpStream,
+    mut socket: impl Read,
     screen_id: usize,
     is_connected: bool,
 ) {
-    robot.set_screen_leader(screen_id);
-    let update_screen_leader_msg = MessageType::UpdateScreenLeader(screen_id);
-    let _ = robot.tx_sender_channel.send(update_screen_leader_msg);
-
-    // Spawn screen sender thread.
-    if !is_connected {
-        let arc_robot = robot.clone();
-        thread::spawn(move || arc_robot.connect_to_screen(false));
-    }
+    recorder::set_peer(Peer::Screen);
+    accept_screen(&robot, my_id, screen_id, is_connected);
 
-    let tx_sender_robot = robot.tx_sender_channel.clone();
     let tx_sender_screen = robot.tx_screen_sender_channel.clone();
-    let tx_prepare = robot.tx_prepare_channel.clone();
     let mut buffer = vec![0; BUFFER_SIZE];
     let mut read_buffer: Vec<u8> = Vec::new();
 
     loop {
         match socket.read(&mut buffer) {
             Ok(0) => {
+                recorder::record_closed();
                 let kill_msg = MessageType::Kill();
                 let _ = tx_sender_screen.send(kill_msg);
                 break;
             }
             Ok(n) => {
                 read_buffer.extend_from_slice(&buffer[..n]);
-                match deserialize_message(&buffer) {
-                    Some(message) => {
-                        match message {
-                            MessageType::Order(ref order_data) => {
-                                if robot.is_leader(my_id) {
-                                    if robot.has_enough_stock(order_data.order_details.clone()) {
-                                        let update_timestamp =
-                                            robot.subtract_stock(order_data.order_details.clone());
-
-                                        let update_stock_msg =
-                                            MessageType::UpdateStock(UpdateData {
-                                                modified_values: order_data.order_details.clone(),
-                                                timestamp: update_timestamp,
-                                                subtract: true,
-                                            });
-
-                                        let _ = tx_sender_robot.send(update_stock_msg);
-
-                                        if let Some(target_id) = robot.find_target_robot() {
-                                            println!(
-                                                "Orden id {} asignada al robot id: {}",
-                                                order_data.order_id.clone(),
-                                                target_id
-                                            );
-                                            let prepare_msg = MessageType::Prepare(Prepare {
-                                                sender_id: my_id,
-                                                order_id: order_data.order_id.clone(),
-                                                target_id: target_id,
-                                                order_details: order_data.order_details.clone(),
-                                            });
-
-                                            robot.add_new_order(
-                                                target_id,
-                                                order_data.order_id,
-                                                order_data.order_details.clone(),
-                                            );
-
-                                            let neworder_msg = MessageType::NewOrder(OrderData {
-                                                target_id: target_id,
-                                                order_id: order_data.order_id,
-                                                order_details: order_data.order_details.clone(),
-                                            });
-                                            let _ = tx_sender_robot.send(neworder_msg);
-
-                                            let _ = tx_sender_robot.send(prepare_msg);
-                                        } else {
-                                            robot.add_new_order(
-                                                my_id,
-                                                order_data.order_id,
-                                                order_data.order_details.clone(),
-                                            );
-
-                                            let neworder_msg = MessageType::NewOrder(OrderData {
-                                                target_id: my_id,
-                                                order_id: order_data.order_id,
-                                                order_details: order_data.order_details.clone(),
-                                            });
-                                            let _ = tx_sender_robot.send(neworder_msg);
-
-                                            let prepare_msg = MessageType::Prepare(Prepare {
-                                                sender_id: my_id,
-                                                order_id: order_data.order_id.clone(),
-                                                target_id: my_id,
-                                                order_details: order_data.order_details.clone(),
-                                            });
-                                            let _ = tx_prepare.send(prepare_msg);
-                                        }
-                                    } else {
-                                        let abort_order_msg = MessageType::Abort(Abort {
-                                            order_id: order_data.order_id,
-                                        });
-
-                                        let _ = tx_sender_screen.send(abort_order_msg);
-                                    }
-                                }
-                            }
-                            _ => {
-                                //eprintln!("Unknown message type received from Screen, {:?}",message);
-                            }
-                        }
-                        read_buffer.clear(); // Clear the buffer
-                    }
-                    None => {
-                        //
-                    }
+                for message in take_messages(&mut read_buffer) {
+                    handle_screen_message(&robot, my_id, message);
                 }
             }
 
             Err(_) => {
                 //println!("Screen leader murio");
+                recorder::record_closed();
                 let kill_msg = MessageType::Kill();
                 let _ = tx_sender_screen.send(kill_msg);
                 break;
@@ -925,3 +768,193 @@ pub fn handle_screen_connection(
         }
     }
 }
+
+/// Toma a `screen_id` como pantalla líder y lo avisa al resto del anillo. Si la pantalla no
+/// está conectada todavía (`is_connected` es `false`), inicia el hilo que le envía mensajes.
+pub fn accept_screen(robot: &Arc<Robot>, my_id: usize, screen_id: usize, is_connected: bool) {
+    robot.set_screen_leader(screen_id);
+    let update_screen_leader_msg = MessageType::UpdateScreenLeader(screen_id);
+    let _ = robot.tx_sender_channel.send(update_screen_leader_msg);
+
+    // Spawn screen sender thread.
+    if !is_connected {
+        let arc_robot = robot.clone();
+        thread::spawn(move || arc_robot.connect_to_screen(false));
+    }
+    if robot.is_leader(my_id) {
+        robot.publish_stock();
+    }
+}
+
+/// Procesa un mensaje recibido de la pantalla líder. Si el robot es líder y hay stock, el
+/// pedido se asigna a un robot; si no hay stock, se rechaza.
+pub fn handle_screen_message(robot: &Arc<Robot>, my_id: usize, message: MessageType) {
+    match message {
+        MessageType::Order(ref order_data) => {
+            if robot.is_leader(my_id) {
+                if robot.has_enough_stock(order_data.order_details.clone()) {
+                    let update_timestamp = robot.subtract_stock(order_data.order_details.clone());
+
+                    let update_stock_msg = MessageType::UpdateStock(UpdateData {
+                        modified_values: order_data.order_details.clone(),
+                        timestamp: update_timestamp,
+                        subtract: true,
+                    });
+
+                    let _ = robot.tx_sender_channel.send(update_stock_msg);
+
+                    if let Some(target_id) = robot.find_target_robot() {
+                        log_info!(
+                            order = order_data.order_id;
+                            "Orden asignada al robot id: {}",
+                            target_id
+                        );
+                        let prepare_msg = MessageType::Prepare(Prepare {
+                            sender_id: my_id,
+                            order_id: order_data.order_id.clone(),
+                            target_id: target_id,
+                            order_details: order_data.order_details.clone(),
+                        });
+
+                        robot.add_new_order(
+                            target_id,
+                            order_data.order_id,
+                            order_data.order_details.clone(),
+                        );
+
+                        let neworder_msg = MessageType::NewOrder(OrderData {
+                            target_id: target_id,
+                            order_id: order_data.order_id,
+                            order_details: order_data.order_details.clone(),
+                        });
+                        let _ = robot.tx_sender_channel.send(neworder_msg);
+
+                        let _ = robot.tx_sender_channel.send(prepare_msg);
+                    } else {
+                        robot.add_new_order(
+                            my_id,
+                            order_data.order_id,
+                            order_data.order_details.clone(),
+                        );
+
+                        let neworder_msg = MessageType::NewOrder(OrderData {
+                            target_id: my_id,
+                            order_id: order_data.order_id,
+                            order_details: order_data.order_details.clone(),
+                        });
+                        let _ = robot.tx_sender_channel.send(neworder_msg);
+
+                        let prepare_msg = MessageType::Prepare(Prepare {
+                            sender_id: my_id,
+                            order_id: order_data.order_id.clone(),
+                            target_id: my_id,
+                            order_details: order_data.order_details.clone(),
+                        });
+                        robot.enqueue_prepare(prepare_msg);
+                    }
+                } else {
+                    let abort_order_msg = MessageType::Abort(Abort {
+                        order_id: order_data.order_id,
+                        reason: AbortReason::OutOfStock,
+                    });
+
+                    let _ = robot.tx_screen_sender_channel.send(abort_order_msg);
+                }
+                robot.publish_stock();
+            }
+        }
+        MessageType::CancelOrder(ref cancel_data) if robot.is_leader(my_id) => {
+            robot.cancel_order(cancel_data.order_id);
+        }
+        _ => {
+            //eprintln!("Unknown message type received from Screen, {:?}",message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Crea el robot `id` de un anillo de tres robots cuyo líder es `leader_id`.
+    fn robot_with_leader(id: usize, leader_id: usize) -> Arc<Robot> {
+        let robot = Robot::new(id, 3);
+        robot.initialize_network(3);
+        robot.initialize_orders_table(3);
+        robot.set_new_leader(id, leader_id);
+        Arc::new(robot)
+    }
+
+    fn sent_messages(robot: &Robot) -> Vec<MessageType> {
+        robot.rx_sender_channel.lock().unwrap().try_iter().collect()
+    }
+
+    #[test]
+    fn forced_election_keeps_the_live_leader() {
+        let robot = robot_with_leader(0, 2);
+        let details = HashMap::from([(IceCreamFlavor::Chocolate, 100)]);
+        robot
+            .orders_table
+            .lock()
+            .unwrap()
+            .add_order_for_robot(2, 7, details);
+
+        let election = MessageType::Election(Election {
+            sender_id: 1,
+            current_candidate_id: 0,
+            dead_leader_id: None,
+        });
+        handle_other_messages(robot.clone(), 0, election);
+
+        assert!(robot.is_leader(0));
+        assert!(*robot.is_leader.lock().unwrap());
+        let sent = sent_messages(&robot);
+        assert!(matches!(
+            sent.as_slice(),
+            [MessageType::NewLeader(NewLeader {
+                new_leader_id: 0,
+                dead_leader_id: None,
+                ..
+            })]
+        ));
+        assert_eq!(
+            robot.get_robot_orders(2).map(|orders| orders.orders.len()),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn election_after_a_death_removes_the_dead_leader() {
+        let robot = robot_with_leader(0, 2);
+        let election = MessageType::Election(Election {
+            sender_id: 1,
+            current_candidate_id: 0,
+            dead_leader_id: Some(2),
+        });
+        handle_other_messages(robot.clone(), 0, election);
+
+        assert!(robot.is_leader(0));
+        let sent = sent_messages(&robot);
+        assert!(sent
+            .iter()
+            .any(|message| matches!(message, MessageType::RemoveRobot(2))));
+        assert!(robot.get_robot_orders(2).is_none());
+    }
+
+    #[test]
+    fn old_leader_steps_down_when_another_robot_wins() {
+        let robot = robot_with_leader(2, 2);
+        assert!(*robot.is_leader.lock().unwrap());
+
+        let new_leader = MessageType::NewLeader(NewLeader {
+            sender_id: 1,
+            new_leader_id: 0,
+            dead_leader_id: None,
+        });
+        handle_other_messages(robot.clone(), 2, new_leader);
+
+        assert!(robot.is_leader(0));
+        assert!(!*robot.is_leader.lock().unwrap());
+    }
+}
@@ -0,0 +1,59 @@
+use heladeria::launcher::launcher::Launcher;
+use heladeria::reconcile::reconcile::Reconciliation;
+use heladeria::scenario::scenario::Scenario;
+use std::env;
+
+/// Punto de entrada principal para el binario `scenario`.
+/// Acepta un argumento con la ruta del archivo JSON que describe el escenario de fallas.
+/// Levanta la topología del escenario con el launcher, aplica sus eventos (por ejemplo, terminar
+/// un robot a los 3 segundos) y, al terminar, concilia el log del gateway e imprime el reporte.
+/// Termina con código 1 si no se cumple alguna de las condiciones esperadas.
+///
+/// Uso: cargo build && cargo run --bin scenario scenarios/dead_robot_leader.json
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        eprintln!("Usage: {} <scenario-path>", args[0]);
+        std::process::exit(1);
+    }
+
+    let scenario = match Scenario::from_file(&args[1]) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut launcher = match Launcher::new() {
+        Ok(launcher) => launcher,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = scenario.run(&mut launcher) {
+        eprintln!("Error: {}", e);
+        launcher.shutdown();
+        std::process::exit(1);
+    }
+
+    let reconciliation = match Reconciliation::from_file(scenario.log_path()) {
+        Ok(reconciliation) => reconciliation,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+    print!("{}", reconciliation);
+    let failures = scenario.check(&reconciliation);
+    if failures.is_empty() {
+        println!("Escenario OK");
+    } else {
+        println!("Escenario FALLIDO:");
+        for failure in failures {
+            println!("  - {}", failure);
+        }
+        std::process::exit(1);
+    }
+}
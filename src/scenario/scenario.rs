@@ -0,0 +1,294 @@
+/// Este módulo define los escenarios de fallas que ejecuta el binario `scenario`.
+///
+/// Un escenario es una topología del launcher con una duración obligatoria, los eventos que se
+/// aplican a sus procesos en el momento indicado (en segundos desde que termina de levantarse)
+/// y las condiciones que debe cumplir el log del gateway al terminar:
+///
+/// ```json
+/// {
+///   "robots": 2,
+///   "screens": [{ "orders": "orders.json" }, { "orders": "orders.json" }],
+///   "duration_secs": 30,
+///   "events": [{ "at_secs": 3, "kill": "robot 0" }],
+///   "expect": { "min_commits": 4 }
+/// }
+/// ```
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::time::{Duration, Instant};
+
+use crate::common::constants::INITIAL_GRAMS_AMOUNT;
+use crate::launcher::launcher::Launcher;
+use crate::launcher::topology::Topology;
+use crate::reconcile::reconcile::Reconciliation;
+
+/// Acción que un evento aplica sobre un proceso.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// Termina el proceso indicado (por ejemplo, `robot 0`), simulando su caída.
+    Kill(String),
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Kill(name) => write!(f, "kill {}", name),
+        }
+    }
+}
+
+/// Evento del escenario.
+#[derive(Debug, Deserialize)]
+pub struct Event {
+    /// Segundos desde que se terminó de levantar la topología.
+    pub at_secs: u64,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+/// Condiciones que debe cumplir el log del gateway al terminar el escenario. Además, nunca
+/// pueden haberse entregado más gramos de un sabor que el stock inicial.
+#[derive(Debug, Deserialize)]
+pub struct Expectations {
+    /// Si es `true` (el valor predeterminado), el log no puede tener inconsistencias: cada
+    /// autorización debe cerrarse exactamente una vez.
+    #[serde(default = "Expectations::default_consistent")]
+    pub consistent: bool,
+    /// Cantidad mínima de pedidos cobrados.
+    #[serde(default)]
+    pub min_commits: Option<usize>,
+    /// Cantidad mínima de pedidos cancelados.
+    #[serde(default)]
+    pub min_aborts: Option<usize>,
+    /// Cantidad máxima de pedidos cancelados.
+    #[serde(default)]
+    pub max_aborts: Option<usize>,
+}
+
+impl Expectations {
+    fn default_consistent() -> bool {
+        true
+    }
+}
+
+impl Default for Expectations {
+    fn default() -> Self {
+        Expectations {
+            consistent: Self::default_consistent(),
+            min_commits: None,
+            min_aborts: None,
+            max_aborts: None,
+        }
+    }
+}
+
+/// Escenario de fallas: topología, eventos y condiciones esperadas.
+#[derive(Debug, Deserialize)]
+pub struct Scenario {
+    #[serde(flatten)]
+    pub topology: Topology,
+    #[serde(default)]
+    pub events: Vec<Event>,
+    #[serde(default)]
+    pub expect: Expectations,
+    /// Log del gateway que se revisa al terminar. Si no se indica, `transactions.log`.
+    #[serde(default)]
+    pub log: Option<String>,
+}
+
+impl Scenario {
+    /// Log del gateway que se revisa si el escenario no indica otro.
+    pub const DEFAULT_LOG: &'static str = "transactions.log";
+
+    /// Lee y valida el escenario del archivo JSON indicado.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        Self::from_json(&contents).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    /// Lee y valida un escenario en JSON, ordenando sus eventos por momento.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let mut scenario: Scenario = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        scenario.topology.validate()?;
+        let duration = scenario
+            .topology
+            .duration_secs
+            .ok_or("El escenario debe indicar duration_secs")?;
+        let process_names = scenario.topology.process_names();
+        for event in &scenario.events {
+            if event.at_secs > duration {
+                return Err(format!(
+                    "El evento '{}' ocurre después de terminar el escenario",
+                    event.action
+                ));
+            }
+            match event.action {
+                Action::Kill(ref name) if !process_names.contains(name) => {
+                    return Err(format!("No existe el proceso {}", name));
+                }
+                Action::Kill(_) => {}
+            }
+        }
+        scenario.events.sort_by_key(|event| event.at_secs);
+        Ok(scenario)
+    }
+
+    /// Devuelve el log del gateway que se revisa al terminar.
+    pub fn log_path(&self) -> &str {
+        self.log.as_deref().unwrap_or(Self::DEFAULT_LOG)
+    }
+
+    /// Levanta la topología, aplica cada evento en su momento y la detiene cuando se cumple la
+    /// duración del escenario.
+    pub fn run(&self, launcher: &mut Launcher) -> Result<(), String> {
+        launcher.launch(&self.topology)?;
+        let duration = Duration::from_secs(self.topology.duration_secs.unwrap_or(0));
+        let started = Instant::now();
+        for event in &self.events {
+            launcher.run(Some(
+                Duration::from_secs(event.at_secs).saturating_sub(started.elapsed()),
+            ));
+            println!("[scenario] t={}s: {}", event.at_secs, event.action);
+            match event.action {
+                Action::Kill(ref name) => launcher.kill(name)?,
+            }
+        }
+        launcher.run(Some(duration.saturating_sub(started.elapsed())));
+        launcher.shutdown();
+        Ok(())
+    }
+
+    /// Verifica las condiciones esperadas sobre la conciliación del log, devolviendo las que no
+    /// se cumplen.
+    pub fn check(&self, reconciliation: &Reconciliation) -> Vec<String> {
+        let mut failures = Vec::new();
+        if self.expect.consistent {
+            failures.extend(reconciliation.inconsistencies().iter().cloned());
+        }
+        let committed = reconciliation.committed_count();
+        if let Some(min_commits) = self.expect.min_commits {
+            if committed < min_commits {
+                failures.push(format!(
+                    "Se cobraron {} pedidos, se esperaban al menos {}",
+                    committed, min_commits
+                ));
+            }
+        }
+        let aborted = reconciliation.aborted_count();
+        if let Some(min_aborts) = self.expect.min_aborts {
+            if aborted < min_aborts {
+                failures.push(format!(
+                    "Se cancelaron {} pedidos, se esperaban al menos {}",
+                    aborted, min_aborts
+                ));
+            }
+        }
+        if let Some(max_aborts) = self.expect.max_aborts {
+            if aborted > max_aborts {
+                failures.push(format!(
+                    "Se cancelaron {} pedidos, se esperaban a lo sumo {}",
+                    aborted, max_aborts
+                ));
+            }
+        }
+        for (flavor, grams) in reconciliation.served_grams_per_flavor() {
+            if i64::from(grams) > i64::from(INITIAL_GRAMS_AMOUNT) {
+                failures.push(format!(
+                    "Se entregaron {} g de {}, más que el stock inicial de {} g",
+                    grams, flavor, INITIAL_GRAMS_AMOUNT
+                ));
+            }
+        }
+        failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOPOLOGY: &str = r#""robots": 2, "screens": [{"orders": "a.json"}, {"orders": "b.json"}], "duration_secs": 30"#;
+
+    fn scenario(fields: &str) -> Result<Scenario, String> {
+        Scenario::from_json(&format!("{{{}{}}}", TOPOLOGY, fields))
+    }
+
+    fn reconcile(log: &str) -> Reconciliation {
+        Reconciliation::from_reader(log.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn events_are_sorted_by_time() {
+        let scenario = scenario(
+            r#", "events": [{"at_secs": 9, "kill": "robot 1"}, {"at_secs": 3, "kill": "screen 0"}]"#,
+        )
+        .unwrap();
+        let times: Vec<u64> = scenario.events.iter().map(|event| event.at_secs).collect();
+        assert_eq!(times, [3, 9]);
+        assert_eq!(scenario.events[0].action.to_string(), "kill screen 0");
+        assert!(scenario.expect.consistent);
+        assert_eq!(scenario.log_path(), Scenario::DEFAULT_LOG);
+    }
+
+    #[test]
+    fn invalid_scenarios_are_rejected() {
+        let without_duration = Scenario::from_json(
+            r#"{"robots": 2, "screens": [{"orders": "a.json"}, {"orders": "b.json"}]}"#,
+        );
+        assert!(without_duration.is_err());
+        assert!(scenario(r#", "events": [{"at_secs": 31, "kill": "robot 0"}]"#).is_err());
+        assert!(scenario(r#", "events": [{"at_secs": 1, "kill": "robot 2"}]"#).is_err());
+        assert!(scenario(r#", "events": [{"at_secs": 1, "restart": "robot 0"}]"#).is_err());
+        assert!(scenario(r#", "events": [{"at_secs": 1}]"#).is_err());
+    }
+
+    #[test]
+    fn unmet_expectations_are_reported() {
+        let scenario = scenario(
+            r#", "expect": {"min_commits": 2, "min_aborts": 1, "max_aborts": 0}, "log": "x.log""#,
+        )
+        .unwrap();
+        assert_eq!(scenario.log_path(), "x.log");
+
+        let log = reconcile(
+            "PREPARE,1,0,ana,Mint,100g;\n\
+             COMMIT,1,0,ana,Mint,100g;\n\
+             PREPARE,2,0,ana,Mint,100g;\n",
+        );
+        let failures = scenario.check(&log);
+        assert_eq!(failures.len(), 3, "{:?}", failures);
+        assert!(failures[1].contains("se esperaban al menos 2"));
+        assert!(failures[2].contains("se esperaban al menos 1"));
+
+        let log = reconcile(
+            "PREPARE,1,0,ana,Mint,100g;\nCOMMIT,1,0,ana,Mint,100g;\n\
+             PREPARE,2,0,ana,Mint,100g;\nCOMMIT,2,0,ana,Mint,100g;\n\
+             PREPARE,3,0,ana,Mint,100g;\nABORT,3,OUT_OF_STOCK,0,ana,Mint,100g;\n",
+        );
+        let failures = scenario.check(&log);
+        assert_eq!(failures.len(), 1, "{:?}", failures);
+        assert!(failures[0].contains("a lo sumo 0"));
+    }
+
+    #[test]
+    fn serving_more_than_the_initial_stock_fails() {
+        let scenario = scenario("").unwrap();
+        for grams in [INITIAL_GRAMS_AMOUNT as u32 + 1, u32::MAX] {
+            let log = reconcile(&format!(
+                "PREPARE,1,0,ana,Mint,{}g;\nCOMMIT,1,0,ana,Mint,{}g;\n",
+                grams, grams
+            ));
+            let failures = scenario.check(&log);
+            assert_eq!(failures.len(), 1, "{:?}", failures);
+            assert!(failures[0].contains("Mint"));
+        }
+
+        let log = reconcile(&format!(
+            "PREPARE,1,0,ana,Mint,{0}g;\nCOMMIT,1,0,ana,Mint,{0}g;\n",
+            INITIAL_GRAMS_AMOUNT
+        ));
+        assert!(scenario.check(&log).is_empty());
+    }
+}
@@ -0,0 +1,61 @@
+/// Este módulo implementa los comandos de administración de una pantalla.
+///
+/// La pantalla atiende en su puerto de administración el comando `Status` de `heladeria-ctl`,
+/// con su vista del anillo de pantallas, los líderes, la última foto del stock y los pedidos en
+/// curso. Los comandos que modifican el anillo de robots deben enviarse a un robot.
+use std::sync::Arc;
+
+use super::screen::Screen;
+use crate::common::admin;
+use crate::common::constants::SCREEN_ADMIN_BASE_PORT;
+use crate::common::messages::{AdminCommand, AdminReply, NodeKind, NodeStatus};
+
+/// Abre el puerto de administración de la pantalla.
+pub fn start_admin_listener(screen: Arc<Screen>) {
+    let port = SCREEN_ADMIN_BASE_PORT + screen.id as u16;
+    admin::serve(port, move |command| handle_admin_command(&screen, command));
+}
+
+/// Aplica un comando de la consola de administración y devuelve la respuesta para la consola.
+pub fn handle_admin_command(screen: &Screen, command: AdminCommand) -> AdminReply {
+    match command {
+        AdminCommand::Status => AdminReply::Status(node_status(screen)),
+        _ => AdminReply::Error(
+            "Las pantallas solo responden a consultas de estado; envíe el comando a un robot"
+                .to_string(),
+        ),
+    }
+}
+
+/// Devuelve el estado de la pantalla.
+pub fn node_status(screen: &Screen) -> NodeStatus {
+    // Cada valor se lee por separado para no retener un lock mientras se toma otro
+    let leader_id = *screen.leader_id.lock().unwrap();
+    let peer_leader_id = *screen.robot_leader_id.lock().unwrap();
+    let ring = screen
+        .network
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(&node, &next)| (node, next))
+        .collect();
+    let stock = screen
+        .stock
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|snapshot| snapshot.stock.clone());
+    let orders = screen.order_table.lock().unwrap().orders_by_screen();
+    NodeStatus {
+        kind: NodeKind::Screen,
+        id: screen.id,
+        leader_id,
+        is_leader: screen.i_am_leader(),
+        peer_leader_id,
+        ring,
+        tokens: Vec::new(),
+        stock,
+        orders,
+        drained: Vec::new(),
+    }
+}
@@ -0,0 +1,441 @@
+/// Este módulo implementa la API HTTP opcional de la pantalla.
+///
+/// Permite que un front-end externo (por ejemplo, un kiosco) envíe pedidos, consulte su estado y
+/// liste los pedidos pendientes de la pantalla. Los pedidos siguen el mismo camino que los leídos
+/// de archivo (ScreenActor -> ProcessorActor), y el HttpActor actualiza su estado a partir de los
+/// `OrderStatus` que le informa el ProcessorActor.
+///
+/// Endpoints:
+/// - `POST /orders`: recibe un pedido (`{"flavors": [{"name": "Mint", "grams": 100}], "customer": "Ana"}`)
+///   y devuelve su identificador.
+/// - `GET /orders/<id>`: devuelve el estado de un pedido.
+/// - `GET /orders`: lista los pedidos pendientes (sin entregar ni cancelar).
+use actix::{Actor, Context, Handler, Recipient};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::screen_actors::{Order, OrderStatus, Orders};
+use crate::common::constants::HTTP_TIMEOUT_MS;
+use crate::{log_error, log_info};
+
+const ORDERS_PATH: &str = "/orders";
+/// Tamaño máximo, en bytes, del cuerpo de un pedido HTTP.
+const MAX_BODY_LENGTH: usize = 8 * 1024;
+
+/// Estado de un pedido recibido por HTTP.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum OrderState {
+    /// Esperando la autorización del pago.
+    Pending,
+    /// Pago autorizado, el pedido se está preparando.
+    Authorized,
+    /// Pago rechazado por el gateway.
+    Rejected,
+    /// Pedido descartado sin cobrar por falta de stock.
+    #[serde(rename = "out_of_stock")]
+    OutOfStock,
+    /// Pedido entregado y cobrado.
+    Committed,
+    /// Pedido cancelado, sin cobro.
+    Aborted,
+}
+
+/// Pedido recibido por HTTP junto con su estado.
+#[derive(Debug, Clone, Serialize)]
+struct TrackedOrder {
+    id: String,
+    order_id: Option<u32>,
+    status: OrderState,
+    customer: String,
+    details: String,
+}
+
+/// Pedidos recibidos por HTTP, compartidos entre el servidor y el HttpActor.
+#[derive(Debug, Default)]
+struct OrderTracker {
+    next_id: u64,
+    orders: BTreeMap<String, TrackedOrder>,
+    ids_by_order_id: HashMap<u32, String>,
+}
+
+impl OrderTracker {
+    /// Registra un nuevo pedido pendiente, asignándole un identificador.
+    fn add(&mut self, order: &mut Order) -> TrackedOrder {
+        self.next_id += 1;
+        let id = format!("web-{}", self.next_id);
+        order.set_id(id.clone());
+        let tracked = TrackedOrder {
+            id: id.clone(),
+            order_id: None,
+            status: OrderState::Pending,
+            customer: order.customer_reference(),
+            details: order.to_string(),
+        };
+        self.orders.insert(id, tracked.clone());
+        tracked
+    }
+
+    /// Actualiza el estado del pedido correspondiente, si fue recibido por HTTP.
+    fn update(&mut self, status: OrderStatus) {
+        let (id, order_id, state) = match status {
+            OrderStatus::Authorized {
+                id: Some(id),
+                order_id,
+                ..
+            } => {
+                self.ids_by_order_id.insert(order_id, id.clone());
+                (id, Some(order_id), OrderState::Authorized)
+            }
+            OrderStatus::Rejected { id: Some(id), .. } => (id, None, OrderState::Rejected),
+            OrderStatus::OutOfStock { id: Some(id), .. } => (id, None, OrderState::OutOfStock),
+            OrderStatus::Committed { order_id } => match self.ids_by_order_id.remove(&order_id) {
+                Some(id) => (id, Some(order_id), OrderState::Committed),
+                None => return,
+            },
+            OrderStatus::Aborted { order_id, .. } => match self.ids_by_order_id.remove(&order_id) {
+                Some(id) => (id, Some(order_id), OrderState::Aborted),
+                None => return,
+            },
+            _ => return,
+        };
+        if let Some(tracked) = self.orders.get_mut(&id) {
+            tracked.status = state;
+            tracked.order_id = order_id.or(tracked.order_id);
+        }
+    }
+
+    /// Devuelve los pedidos que todavía no fueron entregados, rechazados ni cancelados.
+    fn pending(&self) -> Vec<TrackedOrder> {
+        self.orders
+            .values()
+            .filter(|order| matches!(order.status, OrderState::Pending | OrderState::Authorized))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Representa un actor HttpActor que atiende la API HTTP de la pantalla.
+pub struct HttpActor {
+    port: u16,
+    screen: Recipient<Orders>,
+    tracker: Arc<Mutex<OrderTracker>>,
+}
+
+impl HttpActor {
+    /// Crea una nueva instancia de HttpActor que escuchará en `port` y enviará los pedidos al ScreenActor.
+    pub fn new(port: u16, screen: Recipient<Orders>) -> Self {
+        HttpActor {
+            port,
+            screen,
+            tracker: Arc::new(Mutex::new(OrderTracker::default())),
+        }
+    }
+}
+
+impl Actor for HttpActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Context<Self>) {
+        let address = format!("127.0.0.1:{}", self.port);
+        let listener = match TcpListener::bind(&address) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log_error!("No se pudo iniciar la API HTTP en {}: {}", address, e);
+                return;
+            }
+        };
+        log_info!("API HTTP escuchando en {}", address);
+        let screen = self.screen.clone();
+        let tracker = self.tracker.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => handle_request(stream, &screen, &tracker),
+                    Err(e) => log_error!("Connection failed: {}", e),
+                }
+            }
+        });
+    }
+}
+
+/// Actualiza el estado de los pedidos recibidos por HTTP.
+impl Handler<OrderStatus> for HttpActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: OrderStatus, _ctx: &mut Context<Self>) {
+        self.tracker.lock().unwrap().update(msg);
+    }
+}
+
+/// Lee un pedido HTTP, lo atiende y escribe la respuesta. Si el cliente no termina de enviar el
+/// pedido o de leer la respuesta en `HTTP_TIMEOUT_MS`, se abandona la conexión.
+fn handle_request(
+    mut stream: TcpStream,
+    screen: &Recipient<Orders>,
+    tracker: &Arc<Mutex<OrderTracker>>,
+) {
+    let timeout = Some(Duration::from_millis(HTTP_TIMEOUT_MS));
+    if let Err(e) = stream
+        .set_read_timeout(timeout)
+        .and_then(|_| stream.set_write_timeout(timeout))
+    {
+        log_error!("No se pudo configurar la conexión HTTP: {}", e);
+        return;
+    }
+    let (status, body) = match read_request(&stream) {
+        Ok((method, path, body)) => route(&method, &path, &body, screen, tracker),
+        Err((status, e)) => (status, error_body(&e)),
+    };
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason_phrase(status),
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+/// Lee la línea inicial, los encabezados y el cuerpo de un pedido HTTP. Si no se puede leer,
+/// devuelve el código de estado de la respuesta y el motivo: 413 si el cuerpo supera
+/// `MAX_BODY_LENGTH` bytes o 400 en cualquier otro caso.
+fn read_request<R: Read>(stream: R) -> Result<(String, String, Vec<u8>), (u16, String)> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(bad_request)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts
+        .next()
+        .ok_or_else(|| bad_request("Missing method"))?
+        .to_string();
+    let path = parts
+        .next()
+        .ok_or_else(|| bad_request("Missing path"))?
+        .to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).map_err(bad_request)?;
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| bad_request("Invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_LENGTH {
+        return Err((413, "Request body too large".to_string()));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+    Ok((method, path, body))
+}
+
+/// Resuelve el endpoint correspondiente, devolviendo el código de estado y el cuerpo JSON.
+fn route(
+    method: &str,
+    path: &str,
+    body: &[u8],
+    screen: &Recipient<Orders>,
+    tracker: &Arc<Mutex<OrderTracker>>,
+) -> (u16, String) {
+    let path = path.trim_end_matches('/');
+    match (method, path) {
+        ("POST", ORDERS_PATH) => submit_order(body, screen, tracker),
+        ("GET", ORDERS_PATH) => {
+            let pending = tracker.lock().unwrap().pending();
+            (200, serde_json::to_string(&pending).unwrap())
+        }
+        (_, ORDERS_PATH) => (405, error_body("Method not allowed")),
+        ("GET", _) => {
+            let id = match path
+                .strip_prefix(ORDERS_PATH)
+                .and_then(|id| id.strip_prefix('/'))
+            {
+                Some(id) => id,
+                None => return (404, error_body("Not found")),
+            };
+            match tracker.lock().unwrap().orders.get(id) {
+                Some(order) => (200, serde_json::to_string(order).unwrap()),
+                None => (404, error_body("Order not found")),
+            }
+        }
+        _ => (404, error_body("Not found")),
+    }
+}
+
+/// Valida un pedido recibido por HTTP y lo envía al ScreenActor.
+fn submit_order(
+    body: &[u8],
+    screen: &Recipient<Orders>,
+    tracker: &Arc<Mutex<OrderTracker>>,
+) -> (u16, String) {
+    let mut order: Order = match serde_json::from_slice(body) {
+        Ok(order) => order,
+        Err(e) => return (400, error_body(&format!("Invalid order: {}", e))),
+    };
+    let invalid_flavors = order.invalid_flavors();
+    if !invalid_flavors.is_empty() {
+        let msg = format!("Invalid flavors: {}", invalid_flavors.join(", "));
+        return (400, error_body(&msg));
+    }
+    if order.to_string().is_empty() {
+        return (400, error_body("Order has no flavors"));
+    }
+
+    let tracked = tracker.lock().unwrap().add(&mut order);
+    if screen.do_send(Orders::new(vec![order])).is_err() {
+        tracker.lock().unwrap().orders.remove(&tracked.id);
+        return (503, error_body("Screen is not accepting orders"));
+    }
+    (201, serde_json::to_string(&tracked).unwrap())
+}
+
+/// Error de lectura de un pedido HTTP mal formado.
+fn bad_request(message: impl ToString) -> (u16, String) {
+    (400, message.to_string())
+}
+
+/// Cuerpo JSON de una respuesta de error.
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "error": message }).to_string()
+}
+
+/// Frase asociada a cada código de estado HTTP utilizado.
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        _ => "Service Unavailable",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::messages::AbortReason;
+
+    fn authorized(id: &str, order_id: u32) -> OrderStatus {
+        OrderStatus::Authorized {
+            id: Some(id.to_string()),
+            order_id,
+            customer: String::new(),
+            details: String::new(),
+        }
+    }
+
+    #[test]
+    fn tracked_orders_follow_their_status() {
+        let mut tracker = OrderTracker::default();
+        let mut first = Order::new(vec![("Mint".to_string(), 100)], "ana".to_string());
+        let mut second = Order::new(vec![("Vanilla".to_string(), 50)], String::new());
+        let mut third = Order::new(vec![("Chocolate".to_string(), 50)], String::new());
+        assert_eq!(tracker.add(&mut first).id, "web-1");
+        assert_eq!(tracker.add(&mut second).id, "web-2");
+        assert_eq!(tracker.add(&mut third).id, "web-3");
+        assert_eq!(first.client_id(), "web-1");
+
+        tracker.update(authorized("web-1", 7));
+        tracker.update(authorized("web-2", 8));
+        tracker.update(OrderStatus::Rejected {
+            id: Some("web-3".to_string()),
+            customer: String::new(),
+            details: String::new(),
+        });
+        assert_eq!(tracker.orders["web-1"].order_id, Some(7));
+        assert_eq!(tracker.pending().len(), 2);
+
+        tracker.update(OrderStatus::Committed { order_id: 7 });
+        tracker.update(OrderStatus::Aborted {
+            order_id: 8,
+            reason: AbortReason::OutOfStock,
+        });
+        assert_eq!(tracker.orders["web-1"].status, OrderState::Committed);
+        assert_eq!(tracker.orders["web-2"].status, OrderState::Aborted);
+        assert_eq!(tracker.orders["web-3"].status, OrderState::Rejected);
+        assert!(tracker.pending().is_empty());
+    }
+
+    #[test]
+    fn orders_from_other_sources_are_not_tracked() {
+        let mut tracker = OrderTracker::default();
+        tracker.update(OrderStatus::Authorized {
+            id: None,
+            order_id: 1,
+            customer: String::new(),
+            details: String::new(),
+        });
+        tracker.update(authorized("file-1", 2));
+        tracker.update(OrderStatus::Committed { order_id: 3 });
+        assert!(tracker.orders.is_empty());
+        assert!(tracker.pending().is_empty());
+    }
+
+    #[test]
+    fn requests_are_read_with_their_body() {
+        let request = "POST /orders HTTP/1.1\r\nHost: x\r\ncontent-length: 4\r\n\r\nbody";
+        let (method, path, body) = read_request(request.as_bytes()).unwrap();
+        assert_eq!(method, "POST");
+        assert_eq!(path, "/orders");
+        assert_eq!(body, b"body");
+
+        let (_, _, body) = read_request("GET /orders HTTP/1.1\r\n\r\n".as_bytes()).unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn malformed_requests_are_rejected() {
+        for request in [
+            "",
+            "GET\r\n\r\n",
+            "POST /orders HTTP/1.1\r\nContent-Length: cuatro\r\n\r\nbody",
+            "POST /orders HTTP/1.1\r\nContent-Length: 10\r\n\r\nbody",
+        ] {
+            let error = read_request(request.as_bytes()).unwrap_err();
+            assert_eq!(error.0, 400, "{:?}", request);
+        }
+    }
+
+    #[test]
+    fn oversized_bodies_are_rejected_before_reading_them() {
+        let request = "POST /orders HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n{}";
+        assert_eq!(read_request(request.as_bytes()).unwrap_err().0, 413);
+
+        let body = "x".repeat(MAX_BODY_LENGTH);
+        let request = format!(
+            "POST /orders HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        assert_eq!(
+            read_request(request.as_bytes()).unwrap().2.len(),
+            MAX_BODY_LENGTH
+        );
+        assert_eq!(reason_phrase(413), "Payload Too Large");
+    }
+
+    #[test]
+    fn errors_are_json() {
+        assert_eq!(error_body("Not found"), r#"{"error":"Not found"}"#);
+        assert_eq!(reason_phrase(405), "Method Not Allowed");
+        assert_eq!(reason_phrase(503), "Service Unavailable");
+    }
+}
@@ -1,10 +1,29 @@
 extern crate actix;
+use heladeria::common::constants::{DEFAULT_ORDER_DEADLINE_SECS, SCREEN_METRICS_BASE_PORT};
+use heladeria::common::{logger, metrics, recorder};
+use heladeria::screen::order_reader::OrderFormat;
+use heladeria::screen::order_source::OrderSource;
 use heladeria::screen::screen::Screen;
+use heladeria::screen::screen_actors::OrderWindow;
+use std::path::Path;
+use std::time::Duration;
 use std::{env, fs};
 
 #[cfg(test)]
 mod test;
 
+/// Argumento de línea de comandos que habilita la API HTTP de pedidos.
+const HTTP_ARG: &str = "--http";
+/// Argumento de línea de comandos que indica la cantidad máxima de pedidos en curso.
+const WINDOW_ARG: &str = "--window";
+/// Argumento de línea de comandos que indica la profundidad objetivo de la cola de los robots.
+const QUEUE_TARGET_ARG: &str = "--queue-target";
+/// Argumento de línea de comandos que indica el plazo de entrega de los pedidos, en segundos.
+const DEADLINE_ARG: &str = "--deadline";
+/// Argumento de línea de comandos que habilita descartar, sin cobrarlos, los pedidos para los que
+/// no alcanza el stock publicado por el robot líder.
+const PRECHECK_STOCK_ARG: &str = "--precheck-stock";
+
 /// Precondiciones de la Red de Screens:
 ///          - Tamaño mínimo: 2.
 ///          - Los nodos screen se instancian con los ID's en orden (0,1...,n).
@@ -12,21 +31,111 @@ mod test;
 ///
 /// Punto de entrada principal para el binario `screen`.
 /// Acepta tres argumentos: `server_id`, `total_servers` y `orders_path`.
-/// Valida que `orders_path` sea un archivo JSON válido y luego crea e inicia una instancia de `Screen`.
+/// Valida que `orders_path` sea un archivo de pedidos válido y luego crea e inicia una instancia de `Screen`.
+/// `orders_path` puede ser un archivo JSON, NDJSON o CSV, un directorio vigilado del que se leen
+/// los archivos que se depositen en él, o `-` para leer pedidos NDJSON de la entrada estándar.
+/// Si en lugar de `orders_path` se indica `--interactive`, los pedidos se toman desde la terminal.
+/// Opcionalmente, `--http <port>` habilita la API HTTP de pedidos en ese puerto, `--window <n>`
+/// limita a `n` los pedidos en curso de la pantalla, `--queue-target <n>` achica esa ventana a
+/// medida que la cola de pedidos de los robots se acerca a `n`, `--deadline <s>` cancela los
+/// pedidos que no se entregaron `s` segundos después de autorizarlos y `--precheck-stock`
+/// descarta sin cobrarlos los pedidos para los que no alcanza el stock publicado por el robot
+/// líder.
 ///
 /// Uso: cargo run --bin screen 0 3 (indicando screen id 0, screens totales en la red = 3)
 fn main() {
     let args: Vec<String> = env::args().collect();
-    if args.len() != 4 {
-        eprintln!("Usage: {} <server_id> <total_servers>", args[0]);
-        std::process::exit(1);
+    if args.len() < 4 {
+        exit_with_usage(&args[0]);
+    }
+
+    let mut http_port = None;
+    let mut order_window = OrderWindow::default();
+    let mut order_deadline = Duration::from_secs(DEFAULT_ORDER_DEADLINE_SECS);
+    let precheck_stock = args[4..].iter().any(|arg| arg == PRECHECK_STOCK_ARG);
+    let options: Vec<&String> = args[4..]
+        .iter()
+        .filter(|arg| *arg != PRECHECK_STOCK_ARG)
+        .collect();
+    for option in options.chunks(2) {
+        if option.len() < 2 {
+            exit_with_usage(&args[0]);
+        }
+        match option[0].as_str() {
+            HTTP_ARG => http_port = Some(option[1].parse::<u16>().expect("Invalid http port")),
+            WINDOW_ARG => {
+                order_window.max_in_flight = match option[1].parse::<usize>() {
+                    Ok(window) if window > 0 => Some(window),
+                    _ => panic!("Invalid window"),
+                }
+            }
+            QUEUE_TARGET_ARG => {
+                order_window.queue_target =
+                    Some(option[1].parse::<usize>().expect("Invalid queue target"))
+            }
+            DEADLINE_ARG => {
+                order_deadline = match option[1].parse::<u64>() {
+                    Ok(deadline) if deadline > 0 => Duration::from_secs(deadline),
+                    _ => panic!("Invalid deadline"),
+                }
+            }
+            _ => exit_with_usage(&args[0]),
+        }
     }
 
     let current_index: usize = args[1].parse().expect("Invalid current_index");
     let total_servers: usize = args[2].parse().expect("Invalid total_servers");
-    let orders_path: &str = &args[3];
+    let order_source = OrderSource::from_arg(&args[3]);
+    logger::init("screen", Some(current_index));
+    recorder::init("screen", Some(current_index));
+    metrics::serve(SCREEN_METRICS_BASE_PORT + current_index as u16);
+
+    // Check if orders_path is a valid orders file
+    if let OrderSource::File(ref orders_path) = order_source {
+        validate_orders_file(orders_path);
+    }
+
+    let screen = Screen::new(
+        current_index,
+        total_servers,
+        order_source,
+        http_port,
+        order_window,
+        order_deadline,
+        precheck_stock,
+    );
+    screen.run(current_index, total_servers);
+}
 
-    // Check if orders_path is a valid JSON file
+/// Muestra el uso del binario y termina el proceso.
+fn exit_with_usage(program: &str) -> ! {
+    eprintln!(
+        "Usage: {} <server_id> <total_servers> <orders_path | {}> [{} <port>] [{} <n>] [{} <n>] [{} <s>] [{}]",
+        program,
+        OrderSource::INTERACTIVE_ARG,
+        HTTP_ARG,
+        WINDOW_ARG,
+        QUEUE_TARGET_ARG,
+        DEADLINE_ARG,
+        PRECHECK_STOCK_ARG
+    );
+    std::process::exit(1);
+}
+
+/// Termina el proceso si `orders_path` no es un archivo de pedidos válido. Los archivos NDJSON y
+/// CSV se validan pedido por pedido al leerlos.
+fn validate_orders_file(orders_path: &str) {
+    match OrderFormat::from_path(Path::new(orders_path)) {
+        Some(OrderFormat::Json) => {}
+        Some(_) => return,
+        None => {
+            eprintln!(
+                "Error: {} is not a .json, .ndjson or .csv file.",
+                orders_path
+            );
+            std::process::exit(1);
+        }
+    }
     if let Ok(contents) = fs::read_to_string(orders_path) {
         if let Ok(_json_value) = serde_json::from_str::<serde_json::Value>(&contents) {
             println!("JSON file is valid: {}", orders_path);
@@ -38,7 +147,4 @@ fn main() {
         eprintln!("Error: Unable to read file {}", orders_path);
         std::process::exit(1);
     }
-
-    let screen = Screen::new(current_index, total_servers, orders_path.to_string());
-    screen.run(current_index, total_servers);
 }
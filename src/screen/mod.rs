@@ -1,4 +1,11 @@
+pub mod admin;
+pub mod http;
+pub mod order_reader;
+pub mod order_source;
 pub mod order_table;
+pub mod progress;
 pub mod screen;
 pub mod screen_actors;
+pub mod summary;
+pub mod terminal;
 pub mod utils;
@@ -0,0 +1,374 @@
+/// Este módulo implementa la lectura de pedidos desde los distintos formatos de entrada.
+///
+/// Además del documento JSON completo (`{"orders": [...]}`), los pedidos pueden leerse de a uno
+/// desde JSON delimitado por líneas (NDJSON) o CSV, lo que permite procesar archivos grandes,
+/// la entrada estándar o los archivos que se depositan en un directorio vigilado a medida que
+/// llegan, sin esperar a tener el lote completo.
+use actix::Recipient;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use super::screen_actors::{Order, Orders};
+use crate::common::constants::WATCH_INTERVAL_MS;
+use crate::{log_error, log_info, log_warn};
+
+/// Subdirectorio del directorio vigilado al que se mueven los archivos ya procesados.
+const PROCESSED_DIR: &str = "processed";
+
+/// Formato de un archivo de pedidos, determinado por su extensión.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderFormat {
+    /// Documento JSON con todos los pedidos (`.json`).
+    Json,
+    /// Un pedido JSON por línea (`.ndjson` o `.jsonl`).
+    Ndjson,
+    /// Una fila `order,customer,flavor,grams` por sabor (`.csv`).
+    Csv,
+}
+
+impl OrderFormat {
+    /// Determina el formato de un archivo de pedidos a partir de su extensión.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Some(OrderFormat::Json),
+            Some("ndjson") | Some("jsonl") => Some(OrderFormat::Ndjson),
+            Some("csv") => Some(OrderFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// Destino de los pedidos leídos, que numera cada pedido según su posición en el origen.
+struct OrderSink<'a> {
+    deliver: Box<dyn FnMut(Orders) -> Result<(), String> + 'a>,
+    source: Option<&'a str>,
+    sent: usize,
+}
+
+impl<'a> OrderSink<'a> {
+    fn new(recipient: &'a Recipient<Orders>, source: Option<&'a str>) -> Self {
+        Self::with_delivery(source, move |orders| {
+            recipient.do_send(orders).map_err(|e| e.to_string())
+        })
+    }
+
+    /// Crea un destino que entrega los pedidos con `deliver`.
+    fn with_delivery(
+        source: Option<&'a str>,
+        deliver: impl FnMut(Orders) -> Result<(), String> + 'a,
+    ) -> Self {
+        OrderSink {
+            deliver: Box::new(deliver),
+            source,
+            sent: 0,
+        }
+    }
+
+    /// Envía los pedidos al ScreenActor. Si se leyeron de un archivo, cada pedido se identifica
+    /// como `<archivo>#<posición>` para el registro de avance de la pantalla. Los pedidos sin
+    /// identificador propio reciben esa misma posición (`stdin#<posición>` si se leyeron de la
+    /// entrada estándar), que no cambia si la pantalla se reinicia.
+    fn send(&mut self, mut orders: Vec<Order>) -> Result<(), String> {
+        for order in orders.iter_mut() {
+            self.sent += 1;
+            let position = format!("{}#{}", self.source.unwrap_or("stdin"), self.sent);
+            if !order.has_id() {
+                order.set_id(position.clone());
+            }
+            if self.source.is_some() {
+                order.set_entry(position);
+            }
+        }
+        (self.deliver)(Orders::new(orders))
+    }
+}
+
+/// Lee los pedidos de `reader` en el formato indicado y los envía a `recipient` a medida que se
+/// leen. `source` es el archivo del que provienen, si lo hay. Devuelve la cantidad de pedidos
+/// enviados.
+pub fn read_orders<R: BufRead>(
+    reader: R,
+    format: OrderFormat,
+    recipient: &Recipient<Orders>,
+    source: Option<&str>,
+) -> Result<usize, String> {
+    let mut sink = OrderSink::new(recipient, source);
+    read_into(reader, format, &mut sink)?;
+    Ok(sink.sent)
+}
+
+/// Lee los pedidos de `reader` en el formato indicado y los envía a `sink`.
+fn read_into<R: BufRead>(
+    reader: R,
+    format: OrderFormat,
+    sink: &mut OrderSink,
+) -> Result<(), String> {
+    match format {
+        OrderFormat::Json => {
+            let orders: Orders = serde_json::from_reader(reader).map_err(|e| e.to_string())?;
+            sink.send(orders.into_orders())
+        }
+        OrderFormat::Ndjson => read_ndjson(reader, sink),
+        OrderFormat::Csv => read_csv(reader, sink),
+    }
+}
+
+/// Lee un pedido JSON por línea, ignorando las líneas vacías o inválidas.
+fn read_ndjson<R: BufRead>(reader: R, sink: &mut OrderSink) -> Result<(), String> {
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Order>(&line) {
+            Ok(order) => sink.send(vec![order])?,
+            Err(e) => log_warn!("Pedido inválido en la línea {}: {}", line_number + 1, e),
+        }
+    }
+    Ok(())
+}
+
+/// Pedido que se está armando a partir de las filas de un CSV.
+struct CsvOrder {
+    id: String,
+    customer: String,
+    flavors: Vec<(String, i32)>,
+}
+
+impl CsvOrder {
+    /// Arma el pedido y lo envía a `sink`.
+    fn send(self, sink: &mut OrderSink) -> Result<(), String> {
+        let mut order = Order::new(self.flavors, self.customer);
+        if !self.id.is_empty() {
+            order.set_id(self.id);
+        }
+        sink.send(vec![order])
+    }
+}
+
+/// Lee filas `order,customer,flavor,grams`. Las filas consecutivas con el mismo `order` forman
+/// un único pedido, que se envía en cuanto aparece una fila de otro pedido.
+fn read_csv<R: BufRead>(reader: R, sink: &mut OrderSink) -> Result<(), String> {
+    let mut current: Option<CsvOrder> = None;
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let fields: Vec<&str> = line.split(',').map(|field| field.trim()).collect();
+        if line.trim().is_empty() || (line_number == 0 && fields[0] == "order") {
+            continue;
+        }
+        let grams = match (
+            fields.len(),
+            fields.get(3).map(|grams| grams.parse::<i32>()),
+        ) {
+            (4, Some(Ok(grams))) => grams,
+            _ => {
+                log_warn!("Fila inválida en la línea {}: {}", line_number + 1, line);
+                continue;
+            }
+        };
+
+        if let Some(order) = current.take() {
+            if order.id == fields[0] {
+                current = Some(order);
+            } else {
+                order.send(sink)?;
+            }
+        }
+        current
+            .get_or_insert_with(|| CsvOrder {
+                id: fields[0].to_string(),
+                customer: fields[1].to_string(),
+                flavors: Vec::new(),
+            })
+            .flavors
+            .push((fields[2].to_string(), grams));
+    }
+    if let Some(order) = current {
+        order.send(sink)?;
+    }
+    Ok(())
+}
+
+/// Lee el archivo de pedidos ubicado en `path`, con el formato indicado por su extensión.
+pub fn read_file(path: &Path, sink: &Recipient<Orders>) -> Result<usize, String> {
+    let format = OrderFormat::from_path(path)
+        .ok_or_else(|| format!("Unknown order file format: {}", path.display()))?;
+    let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path.display(), e))?;
+    read_orders(BufReader::new(file), format, sink, path.to_str())
+}
+
+/// Vigila el directorio `dir`, leyendo en orden alfabético cada archivo de pedidos nuevo y
+/// moviéndolo luego al subdirectorio `processed`. No retorna mientras la pantalla acepte pedidos.
+pub fn watch_directory(dir: &Path, sink: &Recipient<Orders>) {
+    let processed_dir = dir.join(PROCESSED_DIR);
+    if let Err(e) = fs::create_dir_all(&processed_dir) {
+        log_error!("Unable to create {}: {}", processed_dir.display(), e);
+        return;
+    }
+    log_info!("[FileReader] Vigilando el directorio {}", dir.display());
+    while sink.connected() {
+        for path in pending_files(dir) {
+            match read_file(&path, sink) {
+                Ok(count) => log_info!(
+                    "[FileReader] {} pedidos leídos de {}",
+                    count,
+                    path.display()
+                ),
+                Err(e) => log_error!("{}", e),
+            }
+            let destination = processed_dir.join(path.file_name().unwrap());
+            if let Err(e) = fs::rename(&path, &destination) {
+                log_error!("Unable to move {}: {}", path.display(), e);
+                return;
+            }
+        }
+        thread::sleep(Duration::from_millis(WATCH_INTERVAL_MS));
+    }
+}
+
+/// Devuelve, ordenados por nombre, los archivos de pedidos presentes en `dir`.
+fn pending_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && OrderFormat::from_path(path).is_some())
+            .collect(),
+        Err(e) => {
+            log_error!("Unable to read {}: {}", dir.display(), e);
+            Vec::new()
+        }
+    };
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lee los pedidos de `input` como si vinieran del archivo `source`.
+    fn read(input: &str, format: OrderFormat, source: Option<&str>) -> Result<Vec<Order>, String> {
+        let mut orders = Vec::new();
+        let mut sink = OrderSink::with_delivery(source, |batch: Orders| {
+            orders.extend(batch.into_orders());
+            Ok(())
+        });
+        read_into(input.as_bytes(), format, &mut sink)?;
+        drop(sink);
+        Ok(orders)
+    }
+
+    #[test]
+    fn json_documents_are_read_whole() {
+        let orders = read(
+            "{\"orders\": [{\"flavors\": [{\"name\": \"Mint\", \"grams\": 100}]}, \
+             {\"flavors\": [{\"name\": \"Vanilla\", \"grams\": 50}]}]}",
+            OrderFormat::Json,
+            Some("orders.json"),
+        )
+        .unwrap();
+
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[1].client_id(), "orders.json#2");
+        assert!(read("{\"orders\": [", OrderFormat::Json, None).is_err());
+    }
+
+    #[test]
+    fn formats_are_chosen_by_extension() {
+        assert_eq!(
+            OrderFormat::from_path(Path::new("a/orders.json")),
+            Some(OrderFormat::Json)
+        );
+        assert_eq!(
+            OrderFormat::from_path(Path::new("orders.jsonl")),
+            Some(OrderFormat::Ndjson)
+        );
+        assert_eq!(
+            OrderFormat::from_path(Path::new("orders.ndjson")),
+            Some(OrderFormat::Ndjson)
+        );
+        assert_eq!(
+            OrderFormat::from_path(Path::new("orders.csv")),
+            Some(OrderFormat::Csv)
+        );
+        assert_eq!(OrderFormat::from_path(Path::new("orders.txt")), None);
+        assert_eq!(OrderFormat::from_path(Path::new("orders")), None);
+    }
+
+    #[test]
+    fn ndjson_skips_blank_and_invalid_lines() {
+        let orders = read(
+            "{\"flavors\": [{\"name\": \"Mint\", \"grams\": 100}], \"customer\": \"ana\"}\n\
+             \n\
+             {\"flavors\": \n\
+             not json\n\
+             {\"id\": \"A-7\", \"flavors\": [{\"name\": \"Vanilla\", \"grams\": 50}]}\n",
+            OrderFormat::Ndjson,
+            Some("orders.ndjson"),
+        )
+        .unwrap();
+
+        assert_eq!(orders.len(), 2);
+        assert_eq!(orders[0].describe(), "Mint 100g");
+        assert_eq!(orders[0].customer_reference(), "ana");
+        // Los pedidos sin identificador propio se identifican por su posición en el origen
+        assert_eq!(orders[0].client_id(), "orders.ndjson#1");
+        assert_eq!(orders[1].client_id(), "A-7");
+    }
+
+    #[test]
+    fn csv_groups_consecutive_rows_of_the_same_order() {
+        let orders = read(
+            "order,customer,flavor,grams\n\
+             1,ana,Mint,100\n\
+             1,ana,Vanilla,150\n\
+             2,,Chocolate,250\n\
+             \n\
+             1,luis,Mint,50\n",
+            OrderFormat::Csv,
+            None,
+        )
+        .unwrap();
+
+        let described: Vec<(String, String, String)> = orders
+            .iter()
+            .map(|order| {
+                (
+                    order.client_id().to_string(),
+                    order.customer_reference(),
+                    order.describe(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            described,
+            [
+                ("1".into(), "ana".into(), "Mint 100g, Vanilla 150g".into()),
+                ("2".into(), "".into(), "Chocolate 250g".into()),
+                ("1".into(), "luis".into(), "Mint 50g".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_skips_malformed_rows() {
+        let orders = read(
+            "1,ana,Mint\n\
+             1,ana,Mint,cien\n\
+             1,ana,Mint,100,extra\n\
+             order,customer,flavor,grams\n\
+             ,,Vanilla,30\n",
+            OrderFormat::Csv,
+            Some("orders.csv"),
+        )
+        .unwrap();
+
+        // El encabezado solo se saltea en la primera línea: más adelante es una fila inválida
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].describe(), "Vanilla 30g");
+        assert_eq!(orders[0].client_id(), "orders.csv#1");
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::Path;
+
+/// Origen de los pedidos que procesa una pantalla.
+#[derive(Debug, Clone)]
+pub enum OrderSource {
+    /// Archivo de pedidos, en formato JSON (`{"orders": [...]}`), NDJSON o CSV según su extensión.
+    File(String),
+    /// Pedidos NDJSON leídos de la entrada estándar a medida que llegan.
+    Stdin,
+    /// Directorio vigilado, del que se leen los archivos de pedidos que se depositan en él.
+    Directory(String),
+    /// Pedidos ingresados por un cliente desde la terminal.
+    Interactive,
+}
+
+impl OrderSource {
+    /// Argumento de línea de comandos que habilita el modo interactivo.
+    pub const INTERACTIVE_ARG: &'static str = "--interactive";
+    /// Argumento de línea de comandos que indica leer los pedidos de la entrada estándar.
+    pub const STDIN_ARG: &'static str = "-";
+
+    /// Interpreta el argumento de línea de comandos que indica el origen de los pedidos.
+    pub fn from_arg(arg: &str) -> Self {
+        if arg == Self::INTERACTIVE_ARG {
+            OrderSource::Interactive
+        } else if arg == Self::STDIN_ARG {
+            OrderSource::Stdin
+        } else if Path::new(arg).is_dir() {
+            OrderSource::Directory(arg.to_string())
+        } else {
+            OrderSource::File(arg.to_string())
+        }
+    }
+}
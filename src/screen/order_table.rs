@@ -1,15 +1,33 @@
 //use heladeria::common::flavors::IceCreamFlavor;
 use serde::{Deserialize, Serialize};
 //use serde_json;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::SystemTime;
 
 use crate::common::flavors::IceCreamFlavor;
 
-/// Representa una tabla de pedidos, mapeando order_id a (screen_id, detalles, completado).
+/// Datos de un pedido en la tabla: (screen_id, ID del pedido en la pantalla, detalles, plazo de
+/// entrega, completado).
+type OrderEntry = (
+    i32,
+    Option<String>,
+    HashMap<IceCreamFlavor, i32>,
+    SystemTime,
+    bool,
+);
+
+/// Pedido sacado de la tabla: (screen_id, ID del pedido en la pantalla, detalles).
+type RemovedOrder = (i32, Option<String>, HashMap<IceCreamFlavor, i32>);
+
+/// Pedido vencido: (order_id, ID del pedido en la pantalla, detalles).
+type ExpiredOrder = (i32, Option<String>, HashMap<IceCreamFlavor, i32>);
+
+/// Representa una tabla de pedidos, mapeando order_id a (screen_id, ID del pedido en la
+/// pantalla, detalles, plazo de entrega, completado).
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct OrderTable {
-    orders: HashMap<i32, (i32, HashMap<IceCreamFlavor, i32>, bool)>, // order_id -> (screen_id, details, boolean)
+    orders: HashMap<i32, OrderEntry>, // order_id -> (screen_id, client_id, details, deadline, boolean)
 }
 
 impl OrderTable {
@@ -25,14 +43,19 @@ impl OrderTable {
     //     serde_json::from_str(json_str).unwrap()
     // }
 
-    /// Agrega un pedido a la tabla.
+    /// Agrega un pedido a la tabla, que debe entregarse antes de `deadline`.
     pub fn add_order(
         &mut self,
         screen_id: i32,
         order_id: i32,
+        client_id: Option<String>,
         details: HashMap<IceCreamFlavor, i32>,
+        deadline: SystemTime,
     ) {
-        self.orders.insert(order_id, (screen_id, details, false)); // Initialize boolean as false
+        self.orders.insert(
+            order_id,
+            (screen_id, client_id, details, deadline, false), // Initialize boolean as false
+        );
     }
 
     // Mark an order as completed
@@ -42,19 +65,52 @@ impl OrderTable {
     //     }
     // }
 
-    /// Elimina un pedido de la tabla.
-    pub fn remove_order(&mut self, order_id: i32) -> Option<(i32, HashMap<IceCreamFlavor, i32>)> {
-        if let Some((screen_id, details, _)) = self.orders.remove(&order_id) {
-            Some((screen_id, details))
+    /// Elimina un pedido de la tabla, devolviendo (screen_id, client_id, detalles).
+    pub fn remove_order(&mut self, order_id: i32) -> Option<RemovedOrder> {
+        if let Some((screen_id, client_id, details, _, _)) = self.orders.remove(&order_id) {
+            Some((screen_id, client_id, details))
         } else {
             None
         }
     }
 
+    /// Elimina de la tabla los pedidos de la pantalla `screen_id` cuyo plazo venció antes de
+    /// `now`.
+    pub fn take_expired_orders(&mut self, screen_id: i32, now: SystemTime) -> Vec<ExpiredOrder> {
+        let expired: Vec<i32> = self
+            .orders
+            .iter()
+            .filter(|(_, (owner_id, _, _, deadline, _))| *owner_id == screen_id && *deadline <= now)
+            .map(|(order_id, _)| *order_id)
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|order_id| {
+                self.remove_order(order_id)
+                    .map(|(_, client_id, details)| (order_id, client_id, details))
+            })
+            .collect()
+    }
+
+    /// Devuelve los IDs de los pedidos de la tabla, agrupados por la pantalla que los tomó.
+    pub fn orders_by_screen(&self) -> BTreeMap<usize, Vec<usize>> {
+        let mut orders_by_screen: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (order_id, (screen_id, _, _, _, _)) in &self.orders {
+            orders_by_screen
+                .entry(*screen_id as usize)
+                .or_default()
+                .push(*order_id as usize);
+        }
+        for order_ids in orders_by_screen.values_mut() {
+            order_ids.sort();
+        }
+        orders_by_screen
+    }
+
     /// Transfiere pedidos de una pantalla a otra.
     pub fn transfer_orders(&mut self, from_screen_id: i32, to_screen_id: i32) -> Vec<i32> {
         let mut changed_orders = Vec::new();
-        for (order_id, (screen_id, _, _)) in self.orders.iter_mut() {
+        for (order_id, (screen_id, _, _, _, _)) in self.orders.iter_mut() {
             if *screen_id == from_screen_id {
                 *screen_id = to_screen_id;
                 changed_orders.push(*order_id);
@@ -0,0 +1,217 @@
+use crate::{log_error, log_warn};
+/// Este módulo implementa el registro de avance de una pantalla sobre sus archivos de pedidos.
+///
+/// Cada pantalla anota en `screen_<id>_progress.log` qué pedidos de sus archivos fueron
+/// autorizados (junto con el ID asignado por el gateway) o rechazados, y cuáles de los
+/// autorizados se entregaron o cancelaron. Si la pantalla se reinicia, descarta los pedidos ya
+/// anotados y retoma el archivo donde lo había dejado, sin volver a cobrar pedidos ya autorizados.
+///
+/// Formato de las líneas del registro:
+/// - `AUTHORIZED,<order_id>,<archivo>#<n>`
+/// - `REJECTED,<archivo>#<n>`
+/// - `COMMITTED,<order_id>`
+/// - `ABORTED,<order_id>`
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+
+const AUTHORIZED: &str = "AUTHORIZED";
+const REJECTED: &str = "REJECTED";
+const COMMITTED: &str = "COMMITTED";
+const ABORTED: &str = "ABORTED";
+
+/// Registro de avance de una pantalla.
+pub struct ProgressJournal {
+    path: String,
+    file: Option<File>,
+    processed: HashSet<String>,
+    open_orders: HashMap<u32, String>,
+}
+
+impl ProgressJournal {
+    /// Devuelve la ruta del registro de avance de la pantalla `screen_id`.
+    pub fn path(screen_id: usize) -> String {
+        format!("screen_{}_progress.log", screen_id)
+    }
+
+    /// Carga el registro de avance ubicado en `path`, si existe. El archivo se crea recién al
+    /// anotar el primer pedido.
+    pub fn open(path: &str) -> Self {
+        let mut journal = ProgressJournal {
+            path: path.to_string(),
+            file: None,
+            processed: HashSet::new(),
+            open_orders: HashMap::new(),
+        };
+        if let Ok(contents) = fs::read_to_string(path) {
+            for line in contents.lines() {
+                journal.apply_line(line);
+            }
+        }
+        journal
+    }
+
+    /// Aplica una línea del registro al estado en memoria.
+    fn apply_line(&mut self, line: &str) {
+        let (command, rest) = line.split_once(',').unwrap_or((line, ""));
+        match command {
+            AUTHORIZED => {
+                if let Some((Ok(order_id), entry)) = rest
+                    .split_once(',')
+                    .filter(|(_, entry)| !entry.is_empty())
+                    .map(|(order_id, entry)| (order_id.parse::<u32>(), entry))
+                {
+                    self.processed.insert(entry.to_string());
+                    self.open_orders.insert(order_id, entry.to_string());
+                    return;
+                }
+            }
+            REJECTED if !rest.is_empty() => {
+                self.processed.insert(rest.to_string());
+                return;
+            }
+            COMMITTED | ABORTED => {
+                if let Ok(order_id) = rest.parse::<u32>() {
+                    self.open_orders.remove(&order_id);
+                    return;
+                }
+            }
+            _ => {}
+        }
+        log_warn!("Línea inválida en el registro de avance: {}", line);
+    }
+
+    /// Agrega una línea al registro y la aplica al estado en memoria.
+    fn write_line(&mut self, line: String) {
+        if self.file.is_none() {
+            match OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+            {
+                Ok(file) => self.file = Some(file),
+                Err(e) => log_error!(
+                    "No se pudo abrir el registro de avance {}: {}",
+                    self.path,
+                    e
+                ),
+            }
+        }
+        if let Some(ref mut file) = self.file {
+            if let Err(e) = writeln!(file, "{}", line) {
+                log_error!("No se pudo escribir el registro de avance: {}", e);
+            }
+        }
+        self.apply_line(&line);
+    }
+
+    /// Devuelve `true` si el pedido de la posición `entry` ya fue autorizado o rechazado.
+    pub fn is_processed(&self, entry: &str) -> bool {
+        self.processed.contains(entry)
+    }
+
+    /// Cantidad de pedidos ya autorizados o rechazados.
+    pub fn processed_count(&self) -> usize {
+        self.processed.len()
+    }
+
+    /// IDs de los pedidos autorizados que todavía no se entregaron ni cancelaron.
+    pub fn open_orders(&self) -> Vec<u32> {
+        let mut order_ids: Vec<u32> = self.open_orders.keys().cloned().collect();
+        order_ids.sort_unstable();
+        order_ids
+    }
+
+    /// Anota que el gateway autorizó el pedido de la posición `entry` con el ID `order_id`.
+    pub fn record_authorized(&mut self, entry: &str, order_id: u32) {
+        self.write_line(format!("{},{},{}", AUTHORIZED, order_id, entry));
+    }
+
+    /// Anota que el gateway rechazó el pedido de la posición `entry`.
+    pub fn record_rejected(&mut self, entry: &str) {
+        self.write_line(format!("{},{}", REJECTED, entry));
+    }
+
+    /// Anota la entrega (`committed`) o cancelación de un pedido autorizado por esta pantalla.
+    /// Los pedidos que no figuran en el registro (por ejemplo, los recibidos de una pantalla
+    /// caída) se ignoran.
+    pub fn record_settled(&mut self, order_id: u32, committed: bool) {
+        if !self.open_orders.contains_key(&order_id) {
+            return;
+        }
+        let command = if committed { COMMITTED } else { ABORTED };
+        self.write_line(format!("{},{}", command, order_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Ruta de un registro de avance propio de cada test, que se borra si ya existía.
+    fn journal_path(name: &str) -> String {
+        let path = env::temp_dir().join(format!("progress_{}_{}.log", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn progress_survives_a_restart() {
+        let path = journal_path("restart");
+        let mut journal = ProgressJournal::open(&path);
+        journal.record_authorized("orders.json#1", 10);
+        journal.record_rejected("orders.json#2");
+        journal.record_authorized("orders.json#3", 11);
+        journal.record_settled(10, true);
+
+        let reopened = ProgressJournal::open(&path);
+        let _ = fs::remove_file(&path);
+
+        for entry in ["orders.json#1", "orders.json#2", "orders.json#3"] {
+            assert!(reopened.is_processed(entry));
+        }
+        assert!(!reopened.is_processed("orders.json#4"));
+        assert_eq!(reopened.processed_count(), 3);
+        assert_eq!(reopened.open_orders(), [11]);
+    }
+
+    #[test]
+    fn orders_of_other_screens_are_not_recorded() {
+        let path = journal_path("other_screens");
+        let mut journal = ProgressJournal::open(&path);
+        journal.record_settled(99, false);
+
+        // Si no se anotó ningún pedido, no se crea el archivo
+        assert!(fs::metadata(&path).is_err());
+        assert!(journal.open_orders().is_empty());
+    }
+
+    #[test]
+    fn malformed_lines_are_ignored() {
+        let path = journal_path("malformed");
+        fs::write(
+            &path,
+            "AUTHORIZED,1,orders.json#1\n\
+             AUTHORIZED,x,orders.json#2\n\
+             AUTHORIZED,3\n\
+             AUTHORIZED,4,\n\
+             REJECTED\n\
+             REJECTED,\n\
+             COMMITTED,abc\n\
+             ABORTED\n\
+             PAUSED,1\n\
+             \n\
+             AUTHORIZED,5,orders.json#5\n\
+             ABORTED,5\n",
+        )
+        .unwrap();
+        let journal = ProgressJournal::open(&path);
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(journal.processed_count(), 2);
+        assert!(journal.is_processed("orders.json#1"));
+        assert!(journal.is_processed("orders.json#5"));
+        assert_eq!(journal.open_orders(), [1]);
+    }
+}
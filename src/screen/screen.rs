@@ -11,21 +11,41 @@ use actix::{Actor, System};
 // use heladeria::common::constants::GATEWAY_ADDRESS;
 // use heladeria::common::messages::*;
 use std::collections::HashMap;
-use std::io::Write;
 use std::net::{TcpListener, TcpStream};
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread::{self};
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use crate::common::constants::GATEWAY_ADDRESS;
+use crate::common::constants::{
+    DEADLINE_CHECK_INTERVAL_MS, GATEWAY_ADDRESS, GATEWAY_STANDBY_ADDRESS,
+};
+use crate::common::flavors::IceCreamFlavor;
 use crate::common::messages::*;
+use crate::common::metrics::{self, Metric, MetricKind};
+use crate::common::recorder::{self, Peer};
 use crate::screen::utils::handle_incoming_connection;
+use crate::{log_debug, log_error, log_info, log_warn};
 
+use super::admin::start_admin_listener;
+use super::http::HttpActor;
+use super::order_source::OrderSource;
 use super::order_table::OrderTable;
+use super::progress::ProgressJournal;
 use super::screen_actors::{
-    AbortOrder, Address, CommitOrder, FileReaderActor, ProcessorActor, ReadFile, ScreenActor,
+    AbortOrder, Address, CommitOrder, FileReaderActor, OrderWindow, ProcessorActor, ReadFile,
+    ReadStdin, ScreenActor, SubscribeStatus, WatchDirectory,
+};
+use super::summary::SummaryActor;
+use super::terminal::TerminalActor;
+
+/// Cambios de líder de las pantallas observados por la pantalla.
+pub const ELECTIONS: Metric = Metric {
+    name: "heladeria_screen_elections_total",
+    help: "Cambios de líder de las pantallas observados por la pantalla.",
+    kind: MetricKind::Counter,
 };
+
 /// La estructura `Screen` representa una pantalla en la red.
 /// Contiene información sobre el liderazgo, identificación,
 /// tamaño de la red, tabla de pedidos y canales de comunicación.
@@ -34,8 +54,12 @@ pub struct Screen {
     pub id: usize,
     pub robot_leader_id: Arc<Mutex<usize>>,
     pub leader_id: Arc<Mutex<usize>>,
+    /// Cantidad de cambios de líder que vio la pantalla, con la que se registran las elecciones.
+    pub leader_term: Arc<Mutex<u64>>,
     pub network_size: Arc<Mutex<usize>>,
     pub order_table: Arc<Mutex<OrderTable>>,
+    /// Última foto del stock publicada por el robot líder, si ya llegó alguna.
+    pub stock: Arc<Mutex<Option<StockSnapshot>>>,
     pub network: Arc<Mutex<HashMap<usize, usize>>>,
     pub rx_sender_channel: Arc<Mutex<Receiver<MessageType>>>,
     pub tx_sender_channel: Arc<Sender<MessageType>>,
@@ -43,13 +67,31 @@ pub struct Screen {
     pub tx_prepare_channel: Arc<Sender<MessageType>>,
     pub rx_robot_sender_channel: Arc<Mutex<Option<Receiver<MessageType>>>>,
     pub tx_robot_sender_channel: Arc<Sender<MessageType>>,
-    pub orders_path: String,
+    pub order_source: OrderSource,
+    pub http_port: Option<u16>,
+    pub order_window: OrderWindow,
+    pub order_deadline: Duration,
+    /// Si es `true`, los pedidos para los que no alcanza el stock publicado por el robot líder se
+    /// descartan sin cobrarlos.
+    pub precheck_stock: bool,
 }
 /// Implementación de la estructura `Screen`.
 /// Contiene funciones para inicializar, manejar y depurar la red y los actores.
 impl Screen {
-    /// Crea una nueva instancia de `Screen` con el ID, tamaño de red y ruta de pedidos especificados.
-    pub fn new(id: usize, network_size: usize, orders_path: String) -> Screen {
+    /// Crea una nueva instancia de `Screen` con el ID, tamaño de red y origen de pedidos especificados.
+    /// Si se indica `http_port`, la pantalla también recibe pedidos por HTTP en ese puerto.
+    /// `order_window` limita la cantidad de pedidos en curso de la pantalla y `order_deadline` es
+    /// el plazo de entrega de los pedidos que no indican uno propio. Con `precheck_stock`, los
+    /// pedidos para los que no alcanza el stock publicado por el robot líder no se cobran.
+    pub fn new(
+        id: usize,
+        network_size: usize,
+        order_source: OrderSource,
+        http_port: Option<u16>,
+        order_window: OrderWindow,
+        order_deadline: Duration,
+        precheck_stock: bool,
+    ) -> Screen {
         let (tx, rx) = mpsc::channel();
         let rx_sender_channel = Arc::new(Mutex::new(rx));
         let tx_sender_channel = Arc::new(tx);
@@ -66,8 +108,10 @@ impl Screen {
             is_leader: Arc::new(Mutex::new(id == 0)),
             id,
             leader_id: Arc::new(Mutex::new(0)), // inicialmente, screen 0 es el lider.
+            leader_term: Arc::new(Mutex::new(0)),
             robot_leader_id: Arc::new(Mutex::new(0)), // inicialmente, robot 0 es el lider.
             order_table: Arc::new(Mutex::new(OrderTable::new())),
+            stock: Arc::new(Mutex::new(None)),
             network_size: Arc::new(Mutex::new(network_size)),
             network: Arc::new(Mutex::new(HashMap::new())),
             rx_sender_channel,
@@ -76,7 +120,11 @@ impl Screen {
             tx_prepare_channel,
             rx_robot_sender_channel,
             tx_robot_sender_channel,
-            orders_path,
+            order_source,
+            http_port,
+            order_window,
+            order_deadline,
+            precheck_stock,
         }
     }
 
@@ -95,31 +143,99 @@ impl Screen {
     pub fn print_network(&self) {
         let net = self.network.lock().unwrap();
         for (node, &connected_node) in net.iter() {
-            println!("Screen {} is connected to Screen {}", node, connected_node);
+            log_debug!("Screen {} is connected to Screen {}", node, connected_node);
         }
     }
 
     /// Inicializa los actores necesarios y gestiona la recepción y procesamiento de mensajes.
     pub async fn initialize_actors(self: Arc<Self>, rx: mpsc::Receiver<MessageType>) {
         // Crear ProcessorActor
-        let gateway_address = GATEWAY_ADDRESS.to_string();
-        let processor_actor =
-            match ProcessorActor::new(self.id, gateway_address, self.tx_sender_channel.clone()) {
-                Ok(actor) => actor.start(),
-                Err(err) => {
-                    eprintln!("Failed to create ProcessorActor: {}", err);
-                    std::process::exit(1);
-                }
-            };
+        let gateway_addresses = vec![
+            GATEWAY_ADDRESS.to_string(),
+            GATEWAY_STANDBY_ADDRESS.to_string(),
+        ];
+        // Cargar el registro de avance, para retomar los archivos de pedidos tras un reinicio
+        let progress = ProgressJournal::open(&ProgressJournal::path(self.id));
+        if progress.processed_count() > 0 {
+            log_info!(
+                "Retomando pedidos: {} ya procesados, autorizados sin entregar: {:?}",
+                progress.processed_count(),
+                progress.open_orders()
+            );
+        }
+        let progress = Arc::new(Mutex::new(progress));
+        let summary_actor = SummaryActor::new(self.id).start();
+        let processor_actor = ProcessorActor::new(
+            self.id,
+            gateway_addresses,
+            self.tx_sender_channel.clone(),
+            progress.clone(),
+            self.order_deadline,
+        )
+        .start();
+        processor_actor.do_send(SubscribeStatus {
+            listener: summary_actor.clone().recipient(),
+        });
 
         // Crear ScreenActor
-        let screen_actor = ScreenActor::new(processor_actor.clone().recipient()).start();
-
-        // Crear FileReaderActor
-        let file_reader_actor = FileReaderActor::new(screen_actor.clone().recipient()).start();
+        let screen_actor = ScreenActor::new(
+            processor_actor.clone(),
+            self.order_window,
+            self.stock.clone(),
+            self.precheck_stock,
+            progress,
+            summary_actor,
+        )
+        .start();
+
+        match self.order_source {
+            OrderSource::File(ref orders_path) => {
+                // Crear FileReaderActor
+                let file_reader_actor = FileReaderActor::new(
+                    screen_actor.clone().recipient(),
+                    screen_actor.clone().recipient(),
+                )
+                .start();
+
+                // Ejemplo de cómo enviar un mensaje al FileReaderActor
+                file_reader_actor.do_send(ReadFile::new(orders_path.clone()));
+            }
+            OrderSource::Stdin => {
+                let file_reader_actor = FileReaderActor::new(
+                    screen_actor.clone().recipient(),
+                    screen_actor.clone().recipient(),
+                )
+                .start();
+                file_reader_actor.do_send(ReadStdin);
+            }
+            OrderSource::Directory(ref path) => {
+                let file_reader_actor = FileReaderActor::new(
+                    screen_actor.clone().recipient(),
+                    screen_actor.clone().recipient(),
+                )
+                .start();
+                file_reader_actor.do_send(WatchDirectory::new(path.clone()));
+            }
+            OrderSource::Interactive => {
+                // Crear TerminalActor y suscribirlo al estado de los pedidos
+                let terminal_actor = TerminalActor::new(
+                    screen_actor.clone().recipient(),
+                    screen_actor.clone().recipient(),
+                )
+                .start();
+                processor_actor.do_send(SubscribeStatus {
+                    listener: terminal_actor.recipient(),
+                });
+            }
+        }
 
-        // Ejemplo de cómo enviar un mensaje al FileReaderActor
-        file_reader_actor.do_send(ReadFile::new(self.orders_path.clone()));
+        if let Some(port) = self.http_port {
+            // Crear HttpActor y suscribirlo al estado de los pedidos
+            let http_actor = HttpActor::new(port, screen_actor.clone().recipient()).start();
+            processor_actor.do_send(SubscribeStatus {
+                listener: http_actor.recipient(),
+            });
+        }
         //let mut rx = self.rx_robot_sender_channel.lock().unwrap();
 
         thread::spawn(move || {
@@ -131,7 +247,7 @@ impl Screen {
                 let message = match rx.recv() {
                     Ok(message) => message,
                     Err(_) => {
-                        println!("Error al recibir mensaje. Saliendo del bucle.");
+                        log_error!("Error al recibir mensaje. Saliendo del bucle.");
                         break;
                     }
                 };
@@ -147,11 +263,12 @@ impl Screen {
                     MessageType::Abort(abort_order) => {
                         let abort_order_msg = AbortOrder {
                             order_id: abort_order.order_id as u32,
+                            reason: abort_order.reason,
                         };
                         processor_actor.do_send(abort_order_msg);
                     }
                     _ => {
-                        println!("Mensaje no reconocido recibido en rx_robot_sender_channel.");
+                        log_warn!("Mensaje no reconocido recibido en rx_robot_sender_channel.");
                     }
                 }
             }
@@ -165,7 +282,13 @@ impl Screen {
         channel
     }
     /// Inicia la gestión de pedidos en un nuevo hilo, ejecutando el sistema Actix y los actores necesarios.
+    /// También inicia el hilo que cancela los pedidos de la pantalla cuyo plazo venció.
     pub fn start_orders(self: Arc<Self>, rx: mpsc::Receiver<MessageType>) {
+        let arc_deadlines = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(DEADLINE_CHECK_INTERVAL_MS));
+            arc_deadlines.expire_orders();
+        });
         let arc_order = self.clone();
         let _system_thread_handle = thread::spawn(move || {
             let system = System::new();
@@ -207,12 +330,14 @@ impl Screen {
             arc_self.clone().start_orders(arc_self.get_robot_channel());
         }
 
+        start_admin_listener(arc_self.clone());
+
         // Ejemplo de llamada a start_listener desde Arc<Self>
         arc_self.start_listener();
     }
     /// Comienza a escuchar conexiones TCP entrantes y maneja cada conexión en un hilo separado.
     pub fn start_listener(self: Arc<Self>) {
-        println!("Screen ID {} online", self.id);
+        log_info!("Screen ID {} online", self.id);
         let my_id = self.id.clone();
         let listener_addr = format!("127.0.0.1:{}", 3000 + my_id);
         let listener = TcpListener::bind(&listener_addr).expect("Failed to bind");
@@ -222,11 +347,7 @@ impl Screen {
             match stream {
                 Ok(socket) => {
                     // Incoming connection
-                    println!(
-                        "Screen {}: Accepted connection from {}",
-                        my_id,
-                        socket.peer_addr().unwrap()
-                    );
+                    log_debug!("Accepted connection from {}", socket.peer_addr().unwrap());
 
                     let nested_thread_self = Arc::clone(&self);
                     thread::spawn(move || {
@@ -234,14 +355,16 @@ impl Screen {
                     });
                 }
                 Err(e) => {
-                    eprintln!("{}", e);
+                    log_error!("Connection failed: {}", e);
                 }
             }
         }
     }
     /// Conecta la pantalla líder con el robot líder y maneja la comunicación entre ellos.
     pub fn connect_robot(self: Arc<Self>, is_connected: bool) {
-        println!("Soy el lider. Tengo que intercambiar mensajes con robot lider...");
+        recorder::set_peer(Peer::Robot);
+        let term = *self.leader_term.lock().unwrap();
+        log_info!(leader = self.id, term = term; "Soy el lider. Tengo que intercambiar mensajes con robot lider...");
         let rx_prepare_channel = self.rx_prepare_channel.clone();
         let tx_prepare_channel = self.tx_prepare_channel.clone();
 
@@ -271,7 +394,7 @@ impl Screen {
         let socket = match TcpStream::connect(robot_addr) {
             Ok(sock) => Some(sock),
             Err(e) => {
-                eprintln!("Unable to connect to robot: {:?}", e);
+                log_error!("Unable to connect to robot: {:?}", e);
                 None
             }
         };
@@ -280,7 +403,7 @@ impl Screen {
         let mut socket = match socket {
             Some(socket) => socket,
             None => {
-                eprintln!(
+                log_error!(
                     "No se pudo conectar a ninguno de los puertos especificados. Saliendo..."
                 );
                 return;
@@ -295,35 +418,34 @@ impl Screen {
             intro_msg = MessageType::NewLeaderIntroduction(self.id);
         }
 
-        let serialized = serialize_message(&intro_msg).expect("Failed to serialize message");
-        let _ = socket.write_all(&serialized);
+        let _ = send_message(&mut socket, &intro_msg);
 
-        thread::spawn(move || loop {
-            match rx_prepare_channel.lock().unwrap().recv() {
-                Ok(message) => match message {
-                    MessageType::Order(ref _prepare_order) => {
-                        let serialized =
-                            serialize_message(&message).expect("Failed to serialize message");
-
-                        if let Err(e) = socket.write_all(&serialized) {
-                            eprintln!("Error al enviar por el socket: {}", e);
+        thread::spawn(move || {
+            recorder::set_peer(Peer::Robot);
+            loop {
+                match rx_prepare_channel.lock().unwrap().recv() {
+                    Ok(message) => match message {
+                        MessageType::Order(_) | MessageType::CancelOrder(_) => {
+                            if let Err(e) = send_message(&mut socket, &message) {
+                                log_error!("Error al enviar por el socket: {}", e);
+                                //self.connect_robot();
+                                let _ = tx_prepare_channel.send(message);
+                                break;
+                            }
+                        }
+                        MessageType::Kill() => {
+                            log_info!("Kill recibido. Cierro thread");
                             //self.connect_robot();
-                            let _ = tx_prepare_channel.send(message);
                             break;
                         }
-                    }
-                    MessageType::Kill() => {
-                        println!("Kill recibido. Cierro thread");
-                        //self.connect_robot();
+                        _ => {
+                            log_warn!("Mensaje no reconocido en el canal prepare orders.");
+                        }
+                    },
+                    Err(_) => {
+                        log_warn!("Canal cerrado. Terminando hilo de envío al socket.");
                         break;
                     }
-                    _ => {
-                        println!("Mensaje no reconocido en el canal prepare orders.");
-                    }
-                },
-                Err(_) => {
-                    println!("Canal cerrado. Terminando hilo de envío al socket.");
-                    break;
                 }
             }
         });
@@ -337,8 +459,13 @@ impl Screen {
     /// Actualiza el líder de la red y establece si el ID actual es el nuevo líder.
     pub fn set_new_leader(&self, my_id: usize, new_leader_id: usize) {
         let mut id = self.leader_id.lock().unwrap();
+        let mut term = self.leader_term.lock().unwrap();
+        if *id != new_leader_id {
+            metrics::inc(&ELECTIONS, &[]);
+            *term += 1;
+        }
         *id = new_leader_id;
-        println!("Nuevo lider en la red: {}", new_leader_id);
+        log_info!(leader = new_leader_id, term = *term; "Nuevo lider en la red");
 
         if my_id == new_leader_id {
             let mut is_leader = self.is_leader.lock().unwrap();
@@ -364,6 +491,7 @@ impl Screen {
     }
     /// Conecta a la siguiente pantalla en la red y maneja la comunicación entre pantallas.
     pub fn connect_to_next_screen(&self) {
+        recorder::set_peer(Peer::Screen);
         let tx_sender_channel: Arc<Sender<MessageType>> = self.tx_sender_channel.clone();
         let next_screen_id = self.find_next_id();
 
@@ -372,53 +500,48 @@ impl Screen {
                 let next_addr = format!("127.0.0.1:{}", 3000 + next_id);
                 match TcpStream::connect(&next_addr) {
                     Ok(mut stream) => {
-                        println!(
-                            "Screen {}: Connected to next screen id: {}",
-                            self.id, next_id
-                        );
+                        log_info!("Connected to next screen id: {}", next_id);
                         let rx_sender_channel = self.rx_sender_channel.lock().unwrap();
                         loop {
                             match rx_sender_channel.recv() {
                                 Ok(message) => {
                                     match message {
                                         MessageType::Kill() => {
-                                            println!(
+                                            log_info!(
                                                 "Received KILL command. Terminating sender thread."
                                             );
                                             break;
                                         }
                                         _ => {
                                             thread::sleep(Duration::from_millis(1));
-                                            let serialized = serialize_message(&message)
-                                                .expect("Failed to serialize message");
 
-                                            if let Err(e) = stream.write_all(&serialized) {
+                                            if let Err(e) = send_message(&mut stream, &message) {
                                                 // Falla enviar por el socket
                                                 // mandamos por canal para que se vuelva a procesar (en el nuevo sender)
                                                 let _ = tx_sender_channel.send(message);
-                                                eprintln!(
-                                            "screen {}: Failed to write to stream; err = {:?}",
-                                            self.id, e
-                                        );
+                                                log_error!(
+                                                    "Failed to write to stream; err = {:?}",
+                                                    e
+                                                );
                                                 return;
                                             }
                                         }
                                     }
                                 }
                                 Err(_) => {
-                                    println!("Channel closed");
+                                    log_warn!("Channel closed");
                                     break;
                                 }
                             }
                         }
                     }
                     Err(e) => {
-                        eprintln!("Failed to Connect to next screen: {}", e);
+                        log_error!("Failed to Connect to next screen: {}", e);
                     }
                 }
             }
             None => {
-                eprintln!("Error: Next screen not found.");
+                log_error!("Next screen not found");
             }
         }
     }
@@ -447,7 +570,7 @@ impl Screen {
         return reading_from_id;
     }
     /// Envía un mensaje de elección de líder a través del canal de envío.
-    pub fn send_leader_election_message(&self, id: usize, dead_leader_id: usize) {
+    pub fn send_leader_election_message(&self, id: usize, dead_leader_id: Option<usize>) {
         let election_msg = MessageType::Election(Election {
             sender_id: id,
             current_candidate_id: id,
@@ -455,14 +578,28 @@ impl Screen {
         });
         let _ = self.tx_sender_channel.send(election_msg);
     }
+    /// Guarda la foto del stock recibida, salvo que sea más vieja que la que ya se tiene.
+    pub fn apply_stock_snapshot(&self, snapshot: &StockSnapshot) {
+        let mut stock = self.stock.lock().unwrap();
+        if stock
+            .as_ref()
+            .is_some_and(|current| current.timestamp > snapshot.timestamp)
+        {
+            return;
+        }
+        *stock = Some(snapshot.clone());
+    }
     /// Aplica una orden recibida, añadiéndola a la tabla de órdenes y, si es líder, enviando un mensaje de orden.
     pub fn apply_order(self: Arc<Self>, order: &OrderScreen) {
         let detalle = &order.order_details;
+        let deadline = SystemTime::now() + Duration::from_millis(order.deadline_ms);
         let mut order_table = self.order_table.lock().unwrap();
         order_table.add_order(
             order.sender_id as i32,
             order.order_id as i32,
+            order.client_id.clone(),
             detalle.clone(),
+            deadline,
         );
         if self.i_am_leader() {
             let order_msg = MessageType::Order(Order {
@@ -476,17 +613,21 @@ impl Screen {
     pub fn abort_order(self: Arc<Self>, abort: &Abort) {
         let mut order_table = self.order_table.lock().unwrap();
         let order_id = abort.order_id as i32;
-        if let Some((screen_id, details)) = order_table.remove_order(order_id) {
+        if let Some((screen_id, client_id, details)) = order_table.remove_order(order_id) {
             // Verificar si el screen_id es igual a self.id
             if screen_id == self.id as i32 {
                 let msg = MessageType::Abort(Abort {
                     order_id: abort.order_id,
+                    reason: abort.reason,
                 });
                 self.tx_robot_sender_channel.send(msg).unwrap();
-                println!("Pedido {} ABORTADO:", order_id);
-                for (flavor, quantity) in details {
-                    println!("  - {}: {}", flavor, quantity);
-                }
+                log_warn!(
+                    order = order_id;
+                    "Pedido {} ABORTADO ({}): {}",
+                    client_id.as_deref().unwrap_or("-"),
+                    abort.reason.description(),
+                    describe_details(&details)
+                );
             }
         }
     }
@@ -494,18 +635,71 @@ impl Screen {
     pub fn commit_order(self: Arc<Self>, commit: &Commit) {
         let mut order_table = self.order_table.lock().unwrap();
         let order_id = commit.order_id as i32;
-        if let Some((screen_id, details)) = order_table.remove_order(order_id) {
+        if let Some((screen_id, client_id, details)) = order_table.remove_order(order_id) {
             // Verificar si el screen_id es igual a self.id
             if screen_id == self.id as i32 {
                 let msg = MessageType::Commit(Commit {
                     order_id: commit.order_id,
                 });
                 self.tx_robot_sender_channel.send(msg).unwrap();
-                println!("Pedido {} TERMINADO:", order_id);
-                for (flavor, quantity) in details {
-                    println!("  - {}: {}", flavor, quantity);
-                }
+                log_info!(
+                    order = order_id;
+                    "Pedido {} TERMINADO: {}",
+                    client_id.as_deref().unwrap_or("-"),
+                    describe_details(&details)
+                );
+            }
+        }
+    }
+    /// Cancela los pedidos de esta pantalla cuyo plazo venció: los aborta en el gateway y avisa
+    /// al resto del anillo para que los saquen de su tabla y, desde la pantalla líder, al robot
+    /// líder para que no los reasigne. Si los robots los terminan después, se ignoran.
+    pub fn expire_orders(&self) {
+        let expired = self
+            .order_table
+            .lock()
+            .unwrap()
+            .take_expired_orders(self.id as i32, SystemTime::now());
+        for (order_id, client_id, details) in expired {
+            let reason = AbortReason::DeadlineExpired;
+            let msg = MessageType::Abort(Abort {
+                order_id: order_id as usize,
+                reason,
+            });
+            let _ = self.tx_robot_sender_channel.send(msg);
+            log_warn!(
+                order = order_id;
+                "Pedido {} ABORTADO ({}): {}",
+                client_id.as_deref().unwrap_or("-"),
+                reason.description(),
+                describe_details(&details)
+            );
+
+            let cancel = CancelOrder {
+                sender_id: self.id,
+                order_id: order_id as usize,
+            };
+            if self.i_am_leader() {
+                let _ = self
+                    .tx_prepare_channel
+                    .send(MessageType::CancelOrder(cancel.clone()));
             }
+            let _ = self
+                .tx_sender_channel
+                .send(MessageType::CancelOrder(cancel));
+        }
+    }
+    /// Saca de la tabla un pedido cancelado por otra pantalla y, si es líder, le pide al robot
+    /// líder que lo cancele.
+    pub fn cancel_order(&self, cancel: &CancelOrder) {
+        self.order_table
+            .lock()
+            .unwrap()
+            .remove_order(cancel.order_id as i32);
+        if self.i_am_leader() {
+            let _ = self
+                .tx_prepare_channel
+                .send(MessageType::CancelOrder(cancel.clone()));
         }
     }
     /// Transfiere las órdenes de un nodo muerto a otro nodo especificado.
@@ -513,7 +707,7 @@ impl Screen {
         let mut order_table = self.order_table.lock().unwrap();
         let changed_orders = order_table.transfer_orders(dead_id as i32, transfer_id as i32);
         if transfer_id == self.id {
-            println!("Recibo los pedidos: {:?}", changed_orders);
+            log_info!("Recibo los pedidos: {:?}", changed_orders);
         }
     }
     /// Actualiza la red eliminando un nodo muerto y actualizando las conexiones de los nodos restantes.
@@ -551,6 +745,17 @@ impl Screen {
             *network.get_mut(&dead_id).unwrap() = new_id;
         }
 
-        println!("Updated network: {:?}", network); // debug print
+        log_debug!("Updated network: {:?}", network);
     }
 }
+
+/// Describe los gramos de cada sabor de un pedido en una línea (`Mint 100g, Vanilla 150g`),
+/// ordenados por sabor.
+fn describe_details(details: &HashMap<IceCreamFlavor, i32>) -> String {
+    let mut flavors: Vec<String> = details
+        .iter()
+        .map(|(flavor, grams)| format!("{} {}g", flavor, grams))
+        .collect();
+    flavors.sort();
+    flavors.join(", ")
+}
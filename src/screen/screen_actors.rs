@@ -5,56 +5,185 @@ use actix::AsyncContext;
 use actix::{Actor, Context, Handler, Message, Recipient};
 // use heladeria::common::flavors::{default_flavors, IceCreamFlavor};
 // use heladeria::common::messages::*;
-use crate::common::constants::{FIRST_BATCH, MULTIPLICATION_BATCH};
+use crate::common::constants::{
+    FIRST_BATCH, GATEWAY_RETRY_INITIAL_DELAY_MS, GATEWAY_RETRY_MAX_DELAY_MS, MULTIPLICATION_BATCH,
+    QUEUE_CHECK_INTERVAL_MS,
+};
 use crate::common::flavors::default_flavors;
 use crate::common::flavors::IceCreamFlavor;
-use crate::common::messages::{MessageType, OrderScreen};
+use crate::common::messages::{AbortReason, MessageType, OrderScreen, StockSnapshot};
+use crate::common::recorder::{Peer, RecordedStream};
+use crate::gateway::gateway::Gateway;
+use crate::screen::order_reader::{self, OrderFormat};
+use crate::screen::progress::ProgressJournal;
+use crate::screen::summary::{DiscardedOrder, SummaryActor, WriteSummary};
+use crate::{log_error, log_info, log_warn};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::collections::HashSet;
-use std::fs::File;
-use std::io::BufReader;
+use std::collections::VecDeque;
+use std::io;
+use std::io::ErrorKind;
 use std::io::Read;
 use std::io::Write;
 use std::net::TcpStream;
+use std::path::Path;
 use std::str::FromStr;
 use std::sync::mpsc::Sender;
-use std::sync::Arc;
-const PREPARE: &'static str = "PREPARE";
-const COMMIT: &'static str = "COMMIT";
-const ABORT: &'static str = "ABORT";
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+const PREPARE: &str = "PREPARE";
+const COMMIT: &str = "COMMIT";
+const ABORT: &str = "ABORT";
+const SCREEN: &str = "SCREEN";
+const KEY: &str = "KEY";
 use actix::Addr;
 
+/// Ventana de pedidos en curso de una pantalla. La pantalla empieza enviando a autorizar
+/// `FIRST_BATCH` pedidos y, por cada pedido que termina, envía `MULTIPLICATION_BATCH`, de modo
+/// que la ventana crece en uno con cada pedido entregado o cancelado.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrderWindow {
+    /// Si se indica, cantidad máxima de pedidos enviados a autorizar que todavía no se
+    /// entregaron, cancelaron ni rechazaron.
+    pub max_in_flight: Option<usize>,
+    /// Si se indica, la ventana se achica cuando la cantidad de pedidos esperando en los robots
+    /// (según la última foto publicada por el robot líder) se acerca a este valor.
+    pub queue_target: Option<usize>,
+}
+
+impl OrderWindow {
+    /// Devuelve el límite de la ventana después de que termina un pedido con el límite `limit`.
+    pub fn grow(&self, limit: usize) -> usize {
+        let grown = limit + MULTIPLICATION_BATCH - 1;
+        self.max_in_flight.map_or(grown, |max| grown.min(max))
+    }
+
+    /// Cantidad de pedidos que pueden estar en curso con el límite `limit`. Con una profundidad
+    /// objetivo y una cola de `queue_depth` pedidos en los robots, la ventana se limita a los
+    /// `in_flight` pedidos en curso más el lugar libre en la cola, sin bajar de un pedido para
+    /// que la pantalla nunca se detenga.
+    pub fn size(&self, limit: usize, in_flight: usize, queue_depth: Option<usize>) -> usize {
+        let limit = self.max_in_flight.map_or(limit, |max| limit.min(max));
+        match (self.queue_target, queue_depth) {
+            (Some(target), Some(queue_depth)) => {
+                let free_slots = target.saturating_sub(queue_depth);
+                (in_flight + free_slots).min(limit).max(1)
+            }
+            _ => limit.max(1),
+        }
+    }
+}
+
 /// Representa un actor ScreenActor responsable del procesamiento de pedidos.
 pub struct ScreenActor {
     valid_flavors: HashSet<IceCreamFlavor>,
-    processor: Recipient<PrepareOrder>,
-    orders: Orders,
+    processor: Addr<ProcessorActor>,
+    orders: VecDeque<Order>,
+    window: OrderWindow,
+    window_limit: usize,
+    in_flight: usize,
+    stock: Arc<Mutex<Option<StockSnapshot>>>,
+    precheck_stock: bool,
+    progress: Arc<Mutex<ProgressJournal>>,
+    summary: Addr<SummaryActor>,
+    input_finished: bool,
 }
 
 impl ScreenActor {
-    /// Crea una nueva instancia de ScreenActor.
-    pub fn new(processor: Recipient<PrepareOrder>) -> Self {
+    /// Crea una nueva instancia de ScreenActor, que descarta los pedidos que figuran como ya
+    /// procesados en el registro de avance de la pantalla y mantiene en curso a lo sumo los
+    /// pedidos que permite `window`. Con `precheck_stock`, los pedidos que no alcanzan con el
+    /// último `stock` publicado por el robot líder se descartan sin cobrarlos. Al terminar sus
+    /// pedidos le pide a `summary` el resumen.
+    pub fn new(
+        processor: Addr<ProcessorActor>,
+        window: OrderWindow,
+        stock: Arc<Mutex<Option<StockSnapshot>>>,
+        precheck_stock: bool,
+        progress: Arc<Mutex<ProgressJournal>>,
+        summary: Addr<SummaryActor>,
+    ) -> Self {
         let valid_flavors = default_flavors();
         ScreenActor {
             valid_flavors,
             processor,
-            orders: Orders { orders: Vec::new() },
+            orders: VecDeque::new(),
+            window,
+            window_limit: FIRST_BATCH,
+            in_flight: 0,
+            stock,
+            precheck_stock,
+            progress,
+            summary,
+            input_finished: false,
+        }
+    }
+    /// Cantidad de pedidos que pueden estar en curso, según la cola de pedidos de los robots
+    /// publicada por el robot líder.
+    fn window_size(&self) -> usize {
+        let queue_depth = self
+            .stock
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|snapshot| snapshot.queue_depth);
+        self.window
+            .size(self.window_limit, self.in_flight, queue_depth)
+    }
+    /// Envía a autorizar pedidos mientras haya lugar en la ventana.
+    fn fill_window(&mut self) {
+        while self.in_flight < self.window_size() && self.take_and_process_order() {
+            self.in_flight += 1;
+        }
+    }
+    /// Envía a autorizar pedidos mientras haya lugar en la ventana y, si ya no quedan pedidos
+    /// por leer ni en curso, pide el resumen.
+    fn dispatch_orders(&mut self) {
+        self.fill_window();
+        if self.input_finished && self.in_flight == 0 {
+            self.summary.do_send(WriteSummary);
         }
     }
     /// Verifica si un sabor es válido.
     fn is_valid_flavor(&self, flavor: &IceCreamFlavor) -> bool {
         self.valid_flavors.contains(flavor)
     }
-    /// Toma y procesa un pedido.
-    fn take_and_process_order(&mut self) {
-        if let Some(order) = self.orders.orders.pop() {
-            if self.validate_flavours(order.clone()) {
-                let _ = self.processor.do_send(PrepareOrder { order: order });
+    /// Toma y procesa el pedido más antiguo que se puede preparar, respetando el orden en que
+    /// llegaron y descartando los anteriores que no se pueden preparar. Devuelve `false` si no
+    /// quedaban pedidos por procesar.
+    fn take_and_process_order(&mut self) -> bool {
+        while let Some(order) = self.orders.pop_front() {
+            if !self.validate_flavours(order.clone()) {
+                self.summary.do_send(DiscardedOrder {
+                    out_of_stock: false,
+                });
+            } else if !self.is_in_stock(&order) {
+                log_warn!(
+                    "Pedido {} DESCARTADO sin cobrar por falta de stock",
+                    order.client_id()
+                );
+                self.summary.do_send(DiscardedOrder { out_of_stock: true });
+                self.processor.do_send(OutOfStockOrder { order });
             } else {
-                self.take_and_process_order()
+                self.processor.do_send(PrepareOrder { order: order });
+                return true;
             }
         }
+        false
+    }
+    /// Verifica si el último stock publicado por el robot líder alcanza para el pedido. Si no se
+    /// habilitó la verificación o todavía no se recibió ningún stock, se deja que los robots lo
+    /// decidan.
+    fn is_in_stock(&self, order: &Order) -> bool {
+        if !self.precheck_stock {
+            return true;
+        }
+        match *self.stock.lock().unwrap() {
+            Some(ref snapshot) => snapshot.has_enough_stock(&order.details()),
+            None => true,
+        }
     }
     /// Valida los sabores de un pedido.
     fn validate_flavours(&mut self, order: Order) -> bool {
@@ -76,29 +205,45 @@ impl ScreenActor {
             }
         });
         if !is_valid_order {
-            println!("Pedido DESCARTADO ya que tiene estos sabores no existentes:");
-            for flavor in invalid_flavors {
-                println!("- {}", flavor);
-            }
+            log_warn!(
+                "Pedido {} DESCARTADO ya que tiene estos sabores no existentes: {}",
+                order.client_id(),
+                invalid_flavors.join(", ")
+            );
         }
         is_valid_order
     }
 }
 
-/// Implementación del actor para ScreenActor.
+/// Implementación del actor para ScreenActor. Con una profundidad objetivo, revisa
+/// periódicamente si se liberó lugar en la cola de los robots.
 impl Actor for ScreenActor {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        if self.window.queue_target.is_some() {
+            ctx.run_interval(
+                Duration::from_millis(QUEUE_CHECK_INTERVAL_MS),
+                |screen, _ctx| screen.fill_window(),
+            );
+        }
+    }
 }
 
 /// Representa un actor FileReaderActor responsable de leer archivos de pedidos.
 pub struct FileReaderActor {
     file_reader: Recipient<Orders>,
+    input_finished: Recipient<InputFinished>,
 }
 
 impl FileReaderActor {
-    /// Crea una nueva instancia de FileReaderActor.
-    pub fn new(file_reader: Recipient<Orders>) -> Self {
-        FileReaderActor { file_reader }
+    /// Crea una nueva instancia de FileReaderActor, que avisa a `input_finished` cuando no
+    /// quedan pedidos por leer.
+    pub fn new(file_reader: Recipient<Orders>, input_finished: Recipient<InputFinished>) -> Self {
+        FileReaderActor {
+            file_reader,
+            input_finished,
+        }
     }
 }
 /// Implementación del actor para FileReaderActor.
@@ -109,82 +254,273 @@ impl Actor for FileReaderActor {
 /// Representa un actor ProcessorActor responsable de gestionar pedidos autorizados.
 pub struct ProcessorActor {
     id: usize,
-    gateway: TcpStream,
+    gateway: Option<RecordedStream>,
+    gateway_addresses: Vec<String>,
+    gateway_index: usize,
+    retry_delay: Duration,
+    reconnecting: bool,
+    pending_orders: VecDeque<Order>,
+    pending_settlements: VecDeque<String>,
     sender_channel: Arc<Sender<MessageType>>,
     screen_address: Option<Addr<ScreenActor>>,
+    status_listeners: Vec<Recipient<OrderStatus>>,
+    authorized_orders: HashSet<u32>,
+    progress: Arc<Mutex<ProgressJournal>>,
+    deadline: Duration,
+    /// Prefijo de las claves de autorización, distinto en cada ejecución de la pantalla.
+    run_id: u128,
+    next_prepare_key: u64,
 }
 
 impl Actor for ProcessorActor {
     type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Context<Self>) {
+        if self.gateway.is_none() {
+            self.schedule_reconnect(ctx);
+        }
+    }
 }
 
 impl ProcessorActor {
-    /// Crea una nueva instancia de ProcessorActor.
+    /// Crea una nueva instancia de ProcessorActor, conectándose al primer gateway disponible.
+    /// Si ninguno responde, el actor arranca desconectado y reintenta la conexión al iniciar.
+    /// El resultado de cada pedido se anota en el registro de avance `progress`. Los pedidos
+    /// que no indican su propio plazo de entrega se envían con el plazo `deadline`.
     pub fn new(
         id: usize,
-        gateway_addr: String,
+        gateway_addresses: Vec<String>,
         sender_channel: Arc<Sender<MessageType>>,
-    ) -> Result<Self, String> {
-        match TcpStream::connect(gateway_addr) {
-            Ok(gateway) => Ok(ProcessorActor {
-                id,
-                gateway,
-                sender_channel,
-                screen_address: None,
-            }),
-            Err(e) => Err(format!("Failed to connect to local server: {}", e)),
+        progress: Arc<Mutex<ProgressJournal>>,
+        deadline: Duration,
+    ) -> Self {
+        let mut processor = ProcessorActor {
+            id,
+            gateway: None,
+            gateway_addresses,
+            gateway_index: 0,
+            retry_delay: Duration::from_millis(GATEWAY_RETRY_INITIAL_DELAY_MS),
+            reconnecting: false,
+            pending_orders: VecDeque::new(),
+            pending_settlements: VecDeque::new(),
+            sender_channel,
+            screen_address: None,
+            status_listeners: Vec::new(),
+            authorized_orders: HashSet::new(),
+            progress,
+            deadline,
+            run_id: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis()),
+            next_prepare_key: 0,
+        };
+        if let Err(e) = processor.connect() {
+            log_error!("{}", e);
         }
+        processor
     }
 
-    /// Escribe datos en el socket TCP.
-    pub fn write(&mut self, buf: Vec<u8>) -> Result<(), String> {
-        let _ = self.gateway.write_all(&buf).map_err(|e| e.to_string());
+    /// Se conecta al primer gateway disponible, comenzando por el último utilizado, y le
+    /// informa el ID de esta pantalla para que registre el origen de cada transacción.
+    fn connect(&mut self) -> Result<(), String> {
+        let (gateway, gateway_index) =
+            Self::connect_gateway(&self.gateway_addresses, self.gateway_index)?;
+        self.gateway = Some(RecordedStream::new(gateway, Peer::Gateway));
+        self.gateway_index = gateway_index;
+        let screen_msg = format!("{},{},", SCREEN, self.id);
+        if let Err(e) = self.write(screen_msg.into_bytes()) {
+            self.gateway = None;
+            return Err(e);
+        }
         Ok(())
     }
-    /// Lee datos del socket TCP.
+
+    /// Se conecta al primer gateway disponible, comenzando por el de la posición `start`.
+    fn connect_gateway(addresses: &[String], start: usize) -> Result<(TcpStream, usize), String> {
+        let mut last_error = String::from("No gateway addresses configured");
+        for offset in 0..addresses.len() {
+            let index = (start + offset) % addresses.len();
+            match TcpStream::connect(&addresses[index]) {
+                Ok(gateway) => return Ok((gateway, index)),
+                Err(e) => last_error = format!("{}: {}", addresses[index], e),
+            }
+        }
+        Err(format!("Failed to connect to gateway: {}", last_error))
+    }
+
+    /// Descarta la conexión actual y programa un reintento de conexión con el gateway.
+    /// Mientras haya un reintento pendiente no se programa otro.
+    fn schedule_reconnect(&mut self, ctx: &mut Context<Self>) {
+        self.gateway = None;
+        if self.reconnecting {
+            return;
+        }
+        self.reconnecting = true;
+        log_warn!(
+            "Reintentando conexion con el gateway en {} ms",
+            self.retry_delay.as_millis()
+        );
+        ctx.run_later(self.retry_delay, |act, ctx| {
+            act.reconnecting = false;
+            act.reconnect(ctx);
+        });
+    }
+
+    /// Intenta reconectarse al gateway. Si lo logra, reenvía los COMMIT/ABORT pendientes y
+    /// vuelve a autorizar los pedidos encolados; si no, duplica la espera hasta el máximo.
+    fn reconnect(&mut self, ctx: &mut Context<Self>) {
+        match self.connect() {
+            Ok(()) => {
+                log_info!(
+                    "Conectado al gateway {}",
+                    self.gateway_addresses[self.gateway_index]
+                );
+                self.retry_delay = Duration::from_millis(GATEWAY_RETRY_INITIAL_DELAY_MS);
+                self.flush_settlements(ctx);
+                for order in self.pending_orders.drain(..) {
+                    ctx.address().do_send(PrepareOrder { order });
+                }
+            }
+            Err(e) => {
+                log_error!("{}", e);
+                self.retry_delay =
+                    (self.retry_delay * 2).min(Duration::from_millis(GATEWAY_RETRY_MAX_DELAY_MS));
+                self.schedule_reconnect(ctx);
+            }
+        }
+    }
+
+    /// Verifica que el gateway no haya cerrado la conexión, sin consumir datos del socket.
+    fn is_connected(&self) -> bool {
+        let gateway = match self.gateway {
+            Some(ref gateway) => gateway.get_ref(),
+            None => return false,
+        };
+        if gateway.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let mut buffer = [0; 1];
+        let connected = match gateway.peek(&mut buffer) {
+            Ok(bytes_read) => bytes_read > 0,
+            Err(e) => e.kind() == ErrorKind::WouldBlock,
+        };
+        connected && gateway.set_nonblocking(false).is_ok()
+    }
+
+    /// Escribe datos en el socket TCP. Lo escrito se graba si la pantalla está grabando.
+    pub fn write(&mut self, buf: Vec<u8>) -> Result<(), String> {
+        if !self.is_connected() {
+            return Err("Gateway connection closed".to_string());
+        }
+        match self.gateway {
+            Some(ref mut gateway) => gateway.write_all(&buf).map_err(|e| e.to_string()),
+            None => Err("Gateway connection closed".to_string()),
+        }
+    }
+    /// Lee datos del socket TCP. Lo leído se graba si la pantalla está grabando.
     pub fn read(&mut self, buffer_size: usize) -> Result<String, String> {
+        let gateway = match self.gateway {
+            Some(ref mut gateway) => gateway,
+            None => return Err("Gateway connection closed".to_string()),
+        };
         let mut buffer = vec![0; buffer_size];
-        let bytes_read = self.gateway.read(&mut buffer).map_err(|e| e.to_string())?;
+        let bytes_read = gateway.read(&mut buffer).map_err(|e| e.to_string())?;
+        if bytes_read == 0 {
+            return Err("Gateway connection closed".to_string());
+        }
         let response = String::from_utf8_lossy(&buffer[..bytes_read])
             .trim()
             .to_string();
         Ok(response)
     }
-    /// Realiza un commit para un pedido autorizado.
-    fn commit(&mut self, order_id: u32) -> Result<(), String> {
-        let reply = format!("{},{},", COMMIT, order_id);
-        self.write(reply.into_bytes()).unwrap();
-        Ok(())
+    /// Encola el cierre de un pedido (`COMMIT,<id>,` o `ABORT,<id>,<motivo>,`) y envía los
+    /// cierres pendientes.
+    fn settle(&mut self, settlement: String, ctx: &mut Context<Self>) {
+        self.pending_settlements.push_back(settlement);
+        self.flush_settlements(ctx);
     }
-    /// Aborta un pedido no autorizado.
-    fn abort(&mut self, order_id: u32) -> Result<(), String> {
-        let reply = format!("{},{},", ABORT, order_id);
-        self.write(reply.into_bytes()).unwrap();
-        Ok(())
+    /// Envía al gateway los cierres pendientes en orden. Si la conexión falla, los conserva
+    /// para reenviarlos al reconectarse.
+    fn flush_settlements(&mut self, ctx: &mut Context<Self>) {
+        if self.gateway.is_none() {
+            return;
+        }
+        while let Some(settlement) = self.pending_settlements.front().cloned() {
+            if let Err(e) = self.write(settlement.clone().into_bytes()) {
+                log_error!("Error al enviar {} al gateway: {}", settlement, e);
+                self.schedule_reconnect(ctx);
+                return;
+            }
+            self.pending_settlements.pop_front();
+        }
+    }
+    /// Libera el lugar de un pedido en la ventana del ScreenActor. `settled` indica si el pedido
+    /// se entregó o canceló, en lugar de ser rechazado.
+    fn release_order(&self, settled: bool) {
+        if let Some(ref screen_address) = self.screen_address {
+            screen_address.do_send(ReleaseOrder { settled });
+        }
+    }
+    /// Informa el nuevo estado de un pedido a los actores suscriptos.
+    fn notify_status(&self, status: OrderStatus) {
+        for listener in &self.status_listeners {
+            let _ = listener.do_send(status.clone());
+        }
     }
     /// Imprime los detalles de un pedido autorizado.
     fn print_authorized(&mut self, order: Order, order_id: u32) {
-        println!("Pedido AUTORIZADO con ID {}:", order_id);
-        for flavor in order.flavors {
-            println!("  - {}: {}", flavor.name, flavor.grams);
-        }
+        log_info!(
+            order = order_id;
+            "Pedido {} AUTORIZADO: {}",
+            order.client_id(),
+            order.describe()
+        );
     }
     /// Imprime los detalles de un pedido rechazado.
     fn print_rejected(&mut self, order: Order) {
-        println!("Pedido RECHAZADO:");
-
-        for flavor in order.flavors {
-            println!("  - {}: {}", flavor.name, flavor.grams);
-        }
+        log_info!(
+            "Pedido {} RECHAZADO: {}",
+            order.client_id(),
+            order.describe()
+        );
     }
-    /// Autoriza un pedido con el servidor remoto.
-    fn authorize(&mut self, order: &str) -> Result<u32, String> {
-        let prepare_msg = format!("{},{}", PREPARE, order);
-        let _ = self.write(prepare_msg.into_bytes());
+    /// Autoriza un pedido con el servidor remoto, informando la referencia del cliente.
+    ///
+    /// El PREPARE va precedido de la clave del pedido (`KEY,<clave>`), la misma en cada
+    /// reintento, para que el gateway no retenga dos veces el pago de un pedido cuya respuesta
+    /// se perdió.
+    fn authorize(&mut self, order: &mut Order) -> Result<u32, String> {
+        if order.prepare_key.is_none() {
+            order.prepare_key = Some(format!(
+                "{}-{}-{}",
+                self.id, self.run_id, self.next_prepare_key
+            ));
+            self.next_prepare_key += 1;
+        }
+        let prepare_msg = format!(
+            "{},{},{},{},{}",
+            KEY,
+            order.prepare_key.as_deref().unwrap_or_default(),
+            PREPARE,
+            order.customer_reference(),
+            order.to_string()
+        );
+        self.write(prepare_msg.into_bytes())?;
         let response = self.read(1024)?;
         //println!(" Received: {}", response);
         // Handle response
         if response.starts_with(ABORT) {
+            // El gateway puede informar por qué no autorizó el pedido (`ABORT,<motivo>`)
+            if let Some(reason) = response
+                .strip_prefix(ABORT)
+                .and_then(|r| r.strip_prefix(','))
+            {
+                log_warn!(
+                    "El gateway no autorizó el pedido {}: {}",
+                    order.client_id(),
+                    reason
+                );
+            }
             return Ok(0);
         }
         if response.starts_with(COMMIT) {
@@ -193,7 +529,7 @@ impl ProcessorActor {
                 let order_id = match parts[1].parse::<u32>() {
                     Ok(id) => Ok(id),
                     Err(_) => {
-                        eprintln!("Invalid order ID in COMMIT message");
+                        log_error!("Invalid order ID in COMMIT message");
                         Err("Invalid order ID".to_string())
                     }
                 };
@@ -202,23 +538,24 @@ impl ProcessorActor {
         }
         Err("Unexpected response format".to_string())
     }
-    /// Envía un pedido autorizado al ScreenActor.
+    /// Envía un pedido autorizado al anillo de pantallas, con su plazo de entrega.
     fn send_authorized_order(&mut self, order: Order, order_id: u32) {
-        let mut order_details = HashMap::new();
-        for flavor in order.flavors.iter() {
-            if let Ok(valid_flavor) = IceCreamFlavor::from_str(&flavor.name) {
-                *order_details.entry(valid_flavor).or_insert(0) += flavor.grams;
-            }
-        }
+        let order_details = order.details();
+        let deadline = order
+            .deadline_secs
+            .map(Duration::from_secs)
+            .unwrap_or(self.deadline);
         let order_msg = MessageType::OrderScreen(OrderScreen {
             sender_id: self.id,
             order_id: order_id as usize,
+            client_id: order.id,
             order_details,
+            deadline_ms: deadline.as_millis() as u64,
         });
 
         // Enviar el mensaje Prepare
         if let Err(e) = self.sender_channel.send(order_msg) {
-            eprintln!("Error al enviar el mensaje Order: {:?}", e);
+            log_error!("Error al enviar el mensaje Order: {:?}", e);
         }
     }
 }
@@ -248,12 +585,94 @@ struct Flavor {
 #[derive(Debug, Deserialize, Message, Clone)]
 #[rtype(result = "()")]
 pub struct Order {
+    /// Identificador del pedido asignado por quien lo envía a la pantalla, si lo tiene.
+    #[serde(default)]
+    id: Option<String>,
     flavors: Vec<Flavor>,
+    /// Referencia del cliente que realizó el pedido, registrada por el gateway.
+    #[serde(default)]
+    customer: String,
+    /// Plazo, en segundos desde la autorización, para entregar el pedido. Si no se indica, se
+    /// usa el de la pantalla.
+    #[serde(default)]
+    deadline_secs: Option<u64>,
+    /// Posición del pedido en su archivo de origen (`<archivo>#<n>`), usada por el registro de
+    /// avance de la pantalla. No forma parte del formato de entrada.
+    #[serde(skip)]
+    entry: Option<String>,
+    /// Clave con la que se pidió autorizar el pedido al gateway, que se repite si hay que volver
+    /// a pedirlo. No forma parte del formato de entrada.
+    #[serde(skip)]
+    prepare_key: Option<String>,
     //total_grams: u32,
 }
 
 impl Order {
-    fn to_string(&self) -> String {
+    /// Crea un pedido a partir de los gramos de cada sabor y la referencia del cliente.
+    pub fn new(flavors: Vec<(String, i32)>, customer: String) -> Self {
+        Order {
+            id: None,
+            flavors: flavors
+                .into_iter()
+                .map(|(name, grams)| Flavor { name, grams })
+                .collect(),
+            customer,
+            deadline_secs: None,
+            entry: None,
+            prepare_key: None,
+        }
+    }
+
+    /// Asigna el identificador del pedido.
+    pub fn set_id(&mut self, id: String) {
+        self.id = Some(id);
+    }
+
+    /// Devuelve `true` si el pedido ya tiene un identificador.
+    pub fn has_id(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// Devuelve el identificador del pedido, o `-` si no tiene.
+    pub fn client_id(&self) -> &str {
+        self.id.as_deref().unwrap_or("-")
+    }
+
+    /// Asigna la posición del pedido en su archivo de origen.
+    pub fn set_entry(&mut self, entry: String) {
+        self.entry = Some(entry);
+    }
+
+    /// Devuelve los gramos pedidos de cada sabor válido.
+    pub fn details(&self) -> HashMap<IceCreamFlavor, i32> {
+        let mut order_details = HashMap::new();
+        for flavor in self.flavors.iter() {
+            if let Ok(valid_flavor) = IceCreamFlavor::from_str(&flavor.name) {
+                *order_details.entry(valid_flavor).or_insert(0) += flavor.grams;
+            }
+        }
+        order_details
+    }
+
+    /// Devuelve los sabores del pedido que no existen o tienen una cantidad de gramos inválida.
+    pub fn invalid_flavors(&self) -> Vec<String> {
+        self.flavors
+            .iter()
+            .filter(|flavor| IceCreamFlavor::from_str(&flavor.name).is_err() || flavor.grams <= 0)
+            .map(|flavor| flavor.name.clone())
+            .collect()
+    }
+
+    /// Describe los gramos de cada sabor del pedido en una línea (`Mint 100g, Vanilla 150g`).
+    pub fn describe(&self) -> String {
+        self.flavors
+            .iter()
+            .map(|flavor| format!("{} {}g", flavor.name, flavor.grams))
+            .collect::<Vec<String>>()
+            .join(", ")
+    }
+
+    pub fn to_string(&self) -> String {
         let mut order_string = String::new();
 
         for flavor in &self.flavors {
@@ -262,6 +681,18 @@ impl Order {
 
         order_string
     }
+
+    /// Devuelve la referencia del cliente sin los separadores del protocolo del gateway. Si es
+    /// igual a un comando del gateway, se devuelve entre comillas para que el gateway no la tome
+    /// como el comienzo de otro comando.
+    pub fn customer_reference(&self) -> String {
+        let reference = self.customer.replace([',', ';'], " ").trim().to_string();
+        if Gateway::is_command(&reference) {
+            format!("\"{}\"", reference)
+        } else {
+            reference
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Message, Clone)]
@@ -270,16 +701,78 @@ pub struct Orders {
     orders: Vec<Order>,
 }
 
+impl Orders {
+    /// Crea un lote de pedidos.
+    pub fn new(orders: Vec<Order>) -> Self {
+        Orders { orders }
+    }
+
+    /// Devuelve los pedidos del lote.
+    pub fn into_orders(self) -> Vec<Order> {
+        self.orders
+    }
+}
+
 impl Handler<ReadFile> for FileReaderActor {
     //type Result = ResponseActFuture<Self, ()>;
     type Result = ();
     fn handle(&mut self, msg: ReadFile, _ctx: &mut Context<Self>) -> () {
-        println!("[FileReader] Me mandaron a leer este archivo: {}", msg.name);
+        log_info!("[FileReader] Me mandaron a leer este archivo: {}", msg.name);
 
-        let file = File::open(msg.name).expect("Unable to open file");
-        let reader = BufReader::new(file);
-        let orders: Orders = serde_json::from_reader(reader).expect("Unable to parse JSON");
-        let _ = self.file_reader.do_send(orders);
+        // La lectura se hace en otro hilo para no bloquear a los actores mientras se leen
+        // los pedidos de a uno (NDJSON o CSV)
+        let file_reader = self.file_reader.clone();
+        let input_finished = self.input_finished.clone();
+        thread::spawn(move || {
+            if let Err(e) = order_reader::read_file(Path::new(&msg.name), &file_reader) {
+                log_error!("[FileReader] Error al leer {}: {}", msg.name, e);
+            }
+            let _ = input_finished.do_send(InputFinished);
+        });
+    }
+}
+
+/// Mensaje para leer pedidos NDJSON de la entrada estándar a medida que llegan.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct ReadStdin;
+
+impl Handler<ReadStdin> for FileReaderActor {
+    type Result = ();
+    fn handle(&mut self, _msg: ReadStdin, _ctx: &mut Context<Self>) {
+        log_info!("[FileReader] Leyendo pedidos de la entrada estándar");
+        let file_reader = self.file_reader.clone();
+        let input_finished = self.input_finished.clone();
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let result =
+                order_reader::read_orders(stdin.lock(), OrderFormat::Ndjson, &file_reader, None);
+            if let Err(e) = result {
+                log_error!("[FileReader] Error al leer la entrada estándar: {}", e);
+            }
+            let _ = input_finished.do_send(InputFinished);
+        });
+    }
+}
+
+/// Mensaje para vigilar un directorio, leyendo los archivos de pedidos que se depositen en él.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WatchDirectory {
+    path: String,
+}
+
+impl WatchDirectory {
+    pub fn new(path: String) -> Self {
+        WatchDirectory { path }
+    }
+}
+
+impl Handler<WatchDirectory> for FileReaderActor {
+    type Result = ();
+    fn handle(&mut self, msg: WatchDirectory, _ctx: &mut Context<Self>) {
+        let file_reader = self.file_reader.clone();
+        thread::spawn(move || order_reader::watch_directory(Path::new(&msg.path), &file_reader));
     }
 }
 
@@ -290,33 +783,62 @@ impl Handler<ReadFile> for FileReaderActor {
 impl Handler<Orders> for ScreenActor {
     type Result = ();
 
-    fn handle(&mut self, msg: Orders, ctx: &mut Context<Self>) {
-        self.orders.orders.extend(msg.orders.clone());
-        // Crear un nuevo mensaje SendOrder con el número de pedidos a enviar
-        let send_order_msg = SendOrder {
-            send_new_orders: FIRST_BATCH,
-        };
+    fn handle(&mut self, msg: Orders, _ctx: &mut Context<Self>) {
+        // Se descartan los pedidos que ya se autorizaron o rechazaron antes de un reinicio
+        let progress = self.progress.lock().unwrap();
+        let (processed, orders): (Vec<Order>, Vec<Order>) =
+            msg.orders.into_iter().partition(|order| {
+                order
+                    .entry
+                    .as_ref()
+                    .is_some_and(|entry| progress.is_processed(entry))
+            });
+        drop(progress);
+        if !processed.is_empty() {
+            log_info!(
+                "Se descartan {} pedidos ya procesados antes del reinicio",
+                processed.len()
+            );
+        }
+        self.orders.extend(orders);
+        self.dispatch_orders();
+    }
+}
+
+/// Mensaje que informa al ScreenActor que no le van a llegar más pedidos de su origen (se
+/// terminó de leer el archivo o se cerró la entrada estándar).
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct InputFinished;
 
-        // Enviar el mensaje SendOrder
-        ctx.address().do_send(send_order_msg);
+impl Handler<InputFinished> for ScreenActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: InputFinished, _ctx: &mut Context<Self>) {
+        self.input_finished = true;
+        self.dispatch_orders();
     }
 }
 
+/// Mensaje que informa al ScreenActor que uno de sus pedidos dejó de estar en curso, liberando
+/// lugar en la ventana. `settled` indica si se entregó o canceló, en cuyo caso la ventana crece;
+/// si se rechazó el pago, solo se reemplaza por otro.
 #[derive(Message)]
 #[rtype(result = "()")]
-pub struct SendOrder {
-    send_new_orders: usize,
+pub struct ReleaseOrder {
+    pub settled: bool,
 }
 
-/// El FileReaderActor enviará el detalle de todos los pedidos leídos al ScreenActor
-/// ScreenActor le manda lo leido del archivo a processor para autorizarlo
-impl Handler<SendOrder> for ScreenActor {
+/// ScreenActor le manda a processor los pedidos pendientes que entran en la ventana
+impl Handler<ReleaseOrder> for ScreenActor {
     type Result = ();
 
-    fn handle(&mut self, msg: SendOrder, _ctx: &mut Context<Self>) {
-        for _ in 0..msg.send_new_orders {
-            self.take_and_process_order();
+    fn handle(&mut self, msg: ReleaseOrder, _ctx: &mut Context<Self>) {
+        self.in_flight = self.in_flight.saturating_sub(1);
+        if msg.settled {
+            self.window_limit = self.window.grow(self.window_limit);
         }
+        self.dispatch_orders();
     }
 }
 
@@ -335,26 +857,76 @@ pub struct PrepareOrder {
 impl Handler<PrepareOrder> for ProcessorActor {
     type Result = ();
 
-    fn handle(&mut self, msg: PrepareOrder, _ctx: &mut Context<Self>) -> () {
-        let order_str = msg.order.to_string();
-        match self.authorize(&order_str) {
+    fn handle(&mut self, mut msg: PrepareOrder, ctx: &mut Context<Self>) -> () {
+        if self.gateway.is_none() {
+            // Sin conexión, el pedido espera a que se recupere el gateway
+            self.pending_orders.push_back(msg.order);
+            return;
+        }
+        match self.authorize(&mut msg.order) {
             Ok(order_id) => {
+                if let Some(ref entry) = msg.order.entry {
+                    let mut progress = self.progress.lock().unwrap();
+                    if order_id > 0 {
+                        progress.record_authorized(entry, order_id);
+                    } else {
+                        progress.record_rejected(entry);
+                    }
+                }
                 if order_id > 0 {
+                    self.authorized_orders.insert(order_id);
+                    self.notify_status(OrderStatus::Authorized {
+                        id: msg.order.id.clone(),
+                        order_id,
+                        customer: msg.order.customer_reference(),
+                        details: msg.order.to_string(),
+                    });
                     //Envio pedido al anillo
                     self.send_authorized_order(msg.order.clone(), order_id);
                     self.print_authorized(msg.order, order_id);
                 } else {
-                    if let Some(ref screen_address) = self.screen_address {
-                        screen_address.do_send(SendOrder { send_new_orders: 1 });
-                    } //Autorizo otro pedido porque este fue rechazado
+                    self.notify_status(OrderStatus::Rejected {
+                        id: msg.order.id.clone(),
+                        customer: msg.order.customer_reference(),
+                        details: msg.order.to_string(),
+                    });
+                    //Autorizo otro pedido porque este fue rechazado
+                    self.release_order(false);
                     self.print_rejected(msg.order);
                 }
             }
-            Err(e) => eprintln!("Error al autorizar pedido: {}", e),
+            Err(e) => {
+                log_error!("Error al autorizar pedido: {}", e);
+                // Se reencola el pedido para autorizarlo cuando se recupere la conexión. Se
+                // reenvía con la misma clave: si el gateway ya lo había autorizado, responde con
+                // la misma orden en lugar de retener el pago otra vez.
+                self.pending_orders.push_back(msg.order);
+                self.schedule_reconnect(ctx);
+            }
         }
     }
 }
 
+/// Pedido que el ScreenActor descartó sin cobrarlo porque no hay stock para prepararlo.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct OutOfStockOrder {
+    order: Order,
+}
+
+/// El ProcessorActor informa el descarte a los actores suscriptos
+impl Handler<OutOfStockOrder> for ProcessorActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: OutOfStockOrder, _ctx: &mut Context<Self>) {
+        self.notify_status(OrderStatus::OutOfStock {
+            id: msg.order.id.clone(),
+            customer: msg.order.customer_reference(),
+            details: msg.order.to_string(),
+        });
+    }
+}
+
 /*
 ------------------------------------------ COMMIT/ABORT ORDER---------------------------------------------
 */
@@ -369,6 +941,7 @@ pub struct CommitOrder {
 #[rtype(result = "()")]
 pub struct AbortOrder {
     pub order_id: u32,
+    pub reason: AbortReason,
 }
 
 #[derive(Message)]
@@ -377,18 +950,59 @@ pub struct Address {
     pub screen_addr: Addr<ScreenActor>,
 }
 
+/// Estado de un pedido informado por el ProcessorActor a medida que avanza.
+#[derive(Message, Debug, Clone)]
+#[rtype(result = "()")]
+pub enum OrderStatus {
+    /// El gateway autorizó el pago y el pedido se envió a preparar.
+    Authorized {
+        id: Option<String>,
+        order_id: u32,
+        customer: String,
+        details: String,
+    },
+    /// El gateway rechazó el pago.
+    Rejected {
+        id: Option<String>,
+        customer: String,
+        details: String,
+    },
+    /// No hay stock para el pedido, que se descartó sin cobrarlo.
+    OutOfStock {
+        id: Option<String>,
+        customer: String,
+        details: String,
+    },
+    /// El pedido se entregó y se cobró.
+    Committed { order_id: u32 },
+    /// El pedido no se pudo preparar y se canceló el cobro.
+    Aborted { order_id: u32, reason: AbortReason },
+}
+
+/// Suscribe un actor a los cambios de estado de los pedidos del ProcessorActor.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct SubscribeStatus {
+    pub listener: Recipient<OrderStatus>,
+}
+
 /// ScreenActor lider enviará los pedidos realizados/cancelados al ProcessorActor
 /// Este los registrará en el gateway
 impl Handler<CommitOrder> for ProcessorActor {
     type Result = ();
 
-    fn handle(&mut self, msg: CommitOrder, _ctx: &mut Context<Self>) -> () {
+    fn handle(&mut self, msg: CommitOrder, ctx: &mut Context<Self>) -> () {
         //let screen_addr = msg.screen_addr;
-        let _ = self.commit(msg.order_id);
-        if let Some(ref screen_address) = self.screen_address {
-            screen_address.do_send(SendOrder {
-                send_new_orders: MULTIPLICATION_BATCH,
-            }); // Aquí asumimos que queremos enviar un pedido nuevo
+        self.settle(format!("{},{},", COMMIT, msg.order_id), ctx);
+        self.progress
+            .lock()
+            .unwrap()
+            .record_settled(msg.order_id, true);
+        self.notify_status(OrderStatus::Committed {
+            order_id: msg.order_id,
+        });
+        if self.authorized_orders.remove(&msg.order_id) {
+            self.release_order(true);
         }
     }
 }
@@ -396,13 +1010,19 @@ impl Handler<CommitOrder> for ProcessorActor {
 impl Handler<AbortOrder> for ProcessorActor {
     type Result = ();
 
-    fn handle(&mut self, msg: AbortOrder, _ctx: &mut Context<Self>) -> () {
+    fn handle(&mut self, msg: AbortOrder, ctx: &mut Context<Self>) -> () {
         //let screen_addr = msg.screen_addr;
-        let _ = self.abort(msg.order_id);
-        if let Some(ref screen_address) = self.screen_address {
-            screen_address.do_send(SendOrder {
-                send_new_orders: MULTIPLICATION_BATCH,
-            }); // Aquí asumimos que queremos enviar un pedido nuevo
+        self.settle(format!("{},{},{},", ABORT, msg.order_id, msg.reason), ctx);
+        self.progress
+            .lock()
+            .unwrap()
+            .record_settled(msg.order_id, false);
+        self.notify_status(OrderStatus::Aborted {
+            order_id: msg.order_id,
+            reason: msg.reason,
+        });
+        if self.authorized_orders.remove(&msg.order_id) {
+            self.release_order(true);
         }
     }
 }
@@ -415,3 +1035,82 @@ impl Handler<Address> for ProcessorActor {
         self.screen_address = Some(msg.screen_addr);
     }
 }
+
+impl Handler<SubscribeStatus> for ProcessorActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: SubscribeStatus, _ctx: &mut Context<Self>) {
+        self.status_listeners.push(msg.listener);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_for(customer: &str) -> Order {
+        Order::new(vec![("Mint".to_string(), 100)], customer.to_string())
+    }
+
+    #[test]
+    fn customer_reference_removes_protocol_separators() {
+        assert_eq!(
+            order_for(" ana, ruiz;3 ").customer_reference(),
+            "ana  ruiz 3"
+        );
+        assert_eq!(order_for(",;").customer_reference(), "");
+    }
+
+    #[test]
+    fn customer_reference_quotes_gateway_commands() {
+        for command in ["PREPARE", "COMMIT", "ABORT", "SCREEN", "KEY"] {
+            assert_eq!(
+                order_for(command).customer_reference(),
+                format!("\"{}\"", command)
+            );
+        }
+        assert_eq!(order_for("COMMIT,").customer_reference(), "\"COMMIT\"");
+        assert_eq!(order_for("commit").customer_reference(), "commit");
+    }
+
+    #[test]
+    fn the_default_window_grows_with_each_settled_order() {
+        let window = OrderWindow::default();
+        let mut limit = FIRST_BATCH;
+        assert_eq!(window.size(limit, 0, None), 1);
+        for expected in 2..=5 {
+            limit = window.grow(limit);
+            assert_eq!(window.size(limit, 0, None), expected);
+        }
+    }
+
+    #[test]
+    fn max_in_flight_caps_the_window() {
+        let window = OrderWindow {
+            max_in_flight: Some(3),
+            queue_target: None,
+        };
+        let mut limit = FIRST_BATCH;
+        for _ in 0..10 {
+            limit = window.grow(limit);
+        }
+        assert_eq!(limit, 3);
+        assert_eq!(window.size(limit, 3, None), 3);
+        assert_eq!(window.size(10, 0, None), 3);
+    }
+
+    #[test]
+    fn the_queue_target_uses_the_reported_queue_depth() {
+        let window = OrderWindow {
+            max_in_flight: Some(4),
+            queue_target: Some(6),
+        };
+        // Sin foto del robot líder, solo se aplica el límite
+        assert_eq!(window.size(4, 0, None), 4);
+        assert_eq!(window.size(4, 1, Some(3)), 4);
+        assert_eq!(window.size(4, 2, Some(5)), 3);
+        assert_eq!(window.size(4, 2, Some(6)), 2);
+        // Con la cola llena, la pantalla igual mantiene un pedido en curso
+        assert_eq!(window.size(4, 0, Some(9)), 1);
+    }
+}
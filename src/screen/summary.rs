@@ -0,0 +1,375 @@
+/// Este módulo implementa el resumen de lo procesado por una pantalla.
+///
+/// El SummaryActor se suscribe al estado de los pedidos del ProcessorActor y, cuando la pantalla
+/// termina de leer sus pedidos y ya no le queda ninguno en curso, escribe el resumen en JSON en
+/// `screen_<id>_summary.json`: cantidad de pedidos por resultado (incluidos los descartados sin
+/// cobrar por falta de stock), gramos entregados por sabor,
+/// demoras entre la autorización y la entrega, y los pedidos cancelados. También lleva las
+/// métricas de pedidos de la pantalla.
+use actix::{Actor, Context, Handler, Message};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::time::Instant;
+
+use super::screen_actors::OrderStatus;
+use crate::common::messages::AbortReason;
+use crate::common::metrics::{self, Metric, MetricKind};
+use crate::{log_error, log_info};
+
+/// Pedidos procesados por la pantalla, según la etiqueta `result`.
+pub const ORDERS: Metric = Metric {
+    name: "heladeria_screen_orders_total",
+    help: "Pedidos procesados por la pantalla, por resultado.",
+    kind: MetricKind::Counter,
+};
+
+/// Pedidos autorizados por la pantalla que todavía no se entregaron ni cancelaron.
+pub const ORDERS_IN_FLIGHT: Metric = Metric {
+    name: "heladeria_screen_orders_in_flight",
+    help: "Pedidos autorizados por la pantalla pendientes de entrega o cancelación.",
+    kind: MetricKind::Gauge,
+};
+
+/// Demoras entre la autorización y la entrega de los pedidos, en milisegundos.
+#[derive(Debug, Serialize)]
+pub struct LatencyStats {
+    pub count: usize,
+    pub min_ms: u128,
+    pub avg_ms: u128,
+    pub p50_ms: u128,
+    pub p95_ms: u128,
+    pub max_ms: u128,
+}
+
+impl LatencyStats {
+    /// Calcula las estadísticas de las demoras indicadas. Devuelve `None` si no hay ninguna.
+    fn from_latencies(latencies: &[u128]) -> Option<Self> {
+        if latencies.is_empty() {
+            return None;
+        }
+        let mut sorted = latencies.to_vec();
+        sorted.sort_unstable();
+        let count = sorted.len();
+        // Percentil por rango más cercano
+        let percentile = |p: usize| sorted[(count * p).div_ceil(100).max(1) - 1];
+        Some(LatencyStats {
+            count,
+            min_ms: sorted[0],
+            avg_ms: sorted.iter().sum::<u128>() / count as u128,
+            p50_ms: percentile(50),
+            p95_ms: percentile(95),
+            max_ms: sorted[count - 1],
+        })
+    }
+}
+
+/// Pedido cancelado por los robots.
+#[derive(Debug, Clone, Serialize)]
+pub struct AbortedOrder {
+    pub order_id: u32,
+    pub id: Option<String>,
+    pub details: String,
+    pub reason: AbortReason,
+}
+
+/// Resumen de los pedidos procesados por una pantalla.
+#[derive(Debug, Serialize)]
+pub struct RunSummary {
+    pub screen_id: usize,
+    pub authorized: usize,
+    pub rejected: usize,
+    pub discarded: usize,
+    pub out_of_stock: usize,
+    pub committed: usize,
+    pub aborted: usize,
+    pub grams_per_flavor: BTreeMap<String, i32>,
+    pub latency: Option<LatencyStats>,
+    pub aborted_orders: Vec<AbortedOrder>,
+}
+
+/// Pedido autorizado que todavía no se entregó ni canceló.
+struct AuthorizedOrder {
+    id: Option<String>,
+    details: String,
+    authorized_at: Instant,
+}
+
+/// Representa un actor SummaryActor que lleva la cuenta de los pedidos de una pantalla.
+pub struct SummaryActor {
+    screen_id: usize,
+    authorized: usize,
+    rejected: usize,
+    discarded: usize,
+    out_of_stock: usize,
+    committed: usize,
+    grams_per_flavor: BTreeMap<String, i32>,
+    latencies: Vec<u128>,
+    aborted_orders: Vec<AbortedOrder>,
+    open_orders: HashMap<u32, AuthorizedOrder>,
+}
+
+impl SummaryActor {
+    /// Devuelve la ruta del resumen de la pantalla `screen_id`.
+    pub fn path(screen_id: usize) -> String {
+        format!("screen_{}_summary.json", screen_id)
+    }
+
+    /// Crea una nueva instancia de SummaryActor para la pantalla `screen_id`.
+    pub fn new(screen_id: usize) -> Self {
+        SummaryActor {
+            screen_id,
+            authorized: 0,
+            rejected: 0,
+            discarded: 0,
+            out_of_stock: 0,
+            committed: 0,
+            grams_per_flavor: BTreeMap::new(),
+            latencies: Vec::new(),
+            aborted_orders: Vec::new(),
+            open_orders: HashMap::new(),
+        }
+    }
+
+    /// Arma el resumen con lo procesado hasta el momento.
+    pub fn summary(&self) -> RunSummary {
+        RunSummary {
+            screen_id: self.screen_id,
+            authorized: self.authorized,
+            rejected: self.rejected,
+            discarded: self.discarded,
+            out_of_stock: self.out_of_stock,
+            committed: self.committed,
+            aborted: self.aborted_orders.len(),
+            grams_per_flavor: self.grams_per_flavor.clone(),
+            latency: LatencyStats::from_latencies(&self.latencies),
+            aborted_orders: self.aborted_orders.clone(),
+        }
+    }
+
+    /// Suma los gramos de cada sabor de un pedido entregado (`Mint,100g;Vanilla,50g;`).
+    fn add_served(&mut self, details: &str) {
+        for (flavor, grams) in details
+            .split(';')
+            .filter_map(|flavor| flavor.split_once(','))
+            .filter(|(flavor, _)| !flavor.is_empty())
+        {
+            if let Ok(grams) = grams.trim_end_matches('g').parse::<i32>() {
+                let served = self.grams_per_flavor.entry(flavor.to_string()).or_insert(0);
+                *served = served.saturating_add(grams);
+            }
+        }
+    }
+
+    /// Cuenta el nuevo estado de un pedido.
+    fn record_status(&mut self, status: OrderStatus) {
+        let result = match status {
+            OrderStatus::Authorized { .. } => "authorized",
+            OrderStatus::Rejected { .. } => "rejected",
+            OrderStatus::OutOfStock { .. } => "out_of_stock",
+            OrderStatus::Committed { .. } => "committed",
+            OrderStatus::Aborted { .. } => "aborted",
+        };
+        metrics::inc(&ORDERS, &[("result", result)]);
+        match status {
+            OrderStatus::Authorized {
+                id,
+                order_id,
+                details,
+                ..
+            } => {
+                self.authorized += 1;
+                self.open_orders.insert(
+                    order_id,
+                    AuthorizedOrder {
+                        id,
+                        details,
+                        authorized_at: Instant::now(),
+                    },
+                );
+            }
+            OrderStatus::Rejected { .. } => self.rejected += 1,
+            // El ScreenActor informa los pedidos sin stock con DiscardedOrder, para que lleguen
+            // antes de que pida el resumen
+            OrderStatus::OutOfStock { .. } => {}
+            // Los pedidos que no autorizó esta pantalla (por ejemplo, los recibidos de una
+            // pantalla caída) no forman parte del resumen
+            OrderStatus::Committed { order_id } => {
+                if let Some(order) = self.open_orders.remove(&order_id) {
+                    self.committed += 1;
+                    self.latencies
+                        .push(order.authorized_at.elapsed().as_millis());
+                    self.add_served(&order.details);
+                }
+            }
+            OrderStatus::Aborted { order_id, reason } => {
+                if let Some(order) = self.open_orders.remove(&order_id) {
+                    self.aborted_orders.push(AbortedOrder {
+                        order_id,
+                        id: order.id,
+                        details: order.details,
+                        reason,
+                    });
+                }
+            }
+        }
+        metrics::set(&ORDERS_IN_FLIGHT, &[], self.open_orders.len() as f64);
+    }
+
+    /// Cuenta un pedido descartado sin cobrar.
+    fn record_discarded(&mut self, out_of_stock: bool) {
+        // Los pedidos sin stock se cuentan con su estado OutOfStock
+        if !out_of_stock {
+            metrics::inc(&ORDERS, &[("result", "discarded")]);
+        }
+        if out_of_stock {
+            self.out_of_stock += 1;
+        } else {
+            self.discarded += 1;
+        }
+    }
+}
+
+impl Actor for SummaryActor {
+    type Context = Context<Self>;
+}
+
+impl Handler<OrderStatus> for SummaryActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: OrderStatus, _ctx: &mut Context<Self>) {
+        self.record_status(msg);
+    }
+}
+
+/// Mensaje que informa al SummaryActor que la pantalla descartó un pedido sin cobrarlo, por
+/// tener sabores inválidos o, si `out_of_stock`, por falta de stock.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct DiscardedOrder {
+    pub out_of_stock: bool,
+}
+
+impl Handler<DiscardedOrder> for SummaryActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: DiscardedOrder, _ctx: &mut Context<Self>) {
+        self.record_discarded(msg.out_of_stock);
+    }
+}
+
+/// Mensaje para escribir el resumen de la pantalla, enviado cuando termina sus pedidos.
+#[derive(Message)]
+#[rtype(result = "()")]
+pub struct WriteSummary;
+
+impl Handler<WriteSummary> for SummaryActor {
+    type Result = ();
+
+    fn handle(&mut self, _msg: WriteSummary, _ctx: &mut Context<Self>) {
+        let summary = match serde_json::to_string_pretty(&self.summary()) {
+            Ok(summary) => summary,
+            Err(e) => {
+                log_error!("No se pudo armar el resumen de la pantalla: {}", e);
+                return;
+            }
+        };
+        log_info!("Resumen de la pantalla:\n{}", summary);
+        let path = Self::path(self.screen_id);
+        if let Err(e) = fs::write(&path, summary) {
+            log_error!("No se pudo escribir el resumen en {}: {}", path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorized(order_id: u32, details: &str) -> OrderStatus {
+        OrderStatus::Authorized {
+            id: Some(format!("orders.json#{}", order_id)),
+            order_id,
+            customer: String::new(),
+            details: details.to_string(),
+        }
+    }
+
+    #[test]
+    fn latency_percentiles_use_the_nearest_rank() {
+        assert!(LatencyStats::from_latencies(&[]).is_none());
+
+        let single = LatencyStats::from_latencies(&[7]).unwrap();
+        assert_eq!(
+            (single.min_ms, single.p50_ms, single.p95_ms, single.max_ms),
+            (7, 7, 7, 7)
+        );
+
+        let latencies: Vec<u128> = (1..=20).rev().collect();
+        let stats = LatencyStats::from_latencies(&latencies).unwrap();
+        assert_eq!(stats.count, 20);
+        assert_eq!(stats.min_ms, 1);
+        assert_eq!(stats.avg_ms, 10);
+        assert_eq!(stats.p50_ms, 10);
+        assert_eq!(stats.p95_ms, 19);
+        assert_eq!(stats.max_ms, 20);
+    }
+
+    #[test]
+    fn orders_are_counted_by_result() {
+        let mut summary = SummaryActor::new(1);
+        summary.record_status(authorized(1, "Mint,100g;Vanilla,50g;"));
+        summary.record_status(authorized(2, "Mint,30g;"));
+        summary.record_status(authorized(3, "Chocolate,10g;"));
+        summary.record_status(OrderStatus::Rejected {
+            id: None,
+            customer: String::new(),
+            details: String::new(),
+        });
+        summary.record_discarded(false);
+        summary.record_discarded(true);
+        summary.record_status(OrderStatus::Committed { order_id: 1 });
+        summary.record_status(OrderStatus::Committed { order_id: 2 });
+        summary.record_status(OrderStatus::Aborted {
+            order_id: 3,
+            reason: AbortReason::RobotLost,
+        });
+        // Pedidos de otra pantalla
+        summary.record_status(OrderStatus::Committed { order_id: 40 });
+        summary.record_status(OrderStatus::Aborted {
+            order_id: 41,
+            reason: AbortReason::OutOfStock,
+        });
+
+        let run = summary.summary();
+        assert_eq!(run.screen_id, 1);
+        assert_eq!(
+            (
+                run.authorized,
+                run.rejected,
+                run.discarded,
+                run.out_of_stock
+            ),
+            (3, 1, 1, 1)
+        );
+        assert_eq!((run.committed, run.aborted), (2, 1));
+        assert_eq!(run.grams_per_flavor.get("Mint"), Some(&130));
+        assert_eq!(run.grams_per_flavor.get("Vanilla"), Some(&50));
+        assert_eq!(run.grams_per_flavor.get("Chocolate"), None);
+        assert_eq!(run.latency.map(|latency| latency.count), Some(2));
+        assert_eq!(run.aborted_orders[0].order_id, 3);
+        assert_eq!(run.aborted_orders[0].id.as_deref(), Some("orders.json#3"));
+    }
+
+    #[test]
+    fn malformed_details_are_not_served() {
+        let mut summary = SummaryActor::new(0);
+        summary.add_served("Mint,abc;Vanilla;,10g;Chocolate,20;");
+        summary.add_served(&format!("Mint,{}g;Mint,1g;", i32::MAX));
+
+        assert_eq!(summary.grams_per_flavor.get("Mint"), Some(&i32::MAX));
+        assert_eq!(summary.grams_per_flavor.get(""), None);
+        assert_eq!(summary.grams_per_flavor.get("Chocolate"), Some(&20));
+        assert_eq!(summary.grams_per_flavor.get("Vanilla"), None);
+    }
+}
@@ -0,0 +1,266 @@
+/// Este módulo implementa el modo interactivo de la pantalla.
+///
+/// Un cliente arma su pedido desde la terminal eligiendo el tamaño del pote y los sabores del
+/// catálogo, y el pedido sigue el mismo camino que los leídos de archivo
+/// (ScreenActor -> ProcessorActor). El TerminalActor recibe del ProcessorActor los cambios de
+/// estado de cada pedido y se los muestra al cliente.
+use actix::{Actor, Context, Handler, Recipient};
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::thread;
+
+use super::screen_actors::{InputFinished, Order, OrderStatus, Orders};
+use crate::common::flavors::IceCreamFlavor;
+
+/// Tamaños de pote disponibles: nombre, gramos y cantidad máxima de sabores.
+const CUP_SIZES: [(&str, i32, usize); 3] =
+    [("1/4 kg", 250, 2), ("1/2 kg", 500, 3), ("1 kg", 1000, 4)];
+
+/// Representa un actor TerminalActor que toma pedidos de la terminal y muestra su estado.
+pub struct TerminalActor {
+    screen: Recipient<Orders>,
+    input_finished: Recipient<InputFinished>,
+    customers: HashMap<u32, String>,
+}
+
+impl TerminalActor {
+    /// Crea una nueva instancia de TerminalActor que envía los pedidos al ScreenActor y le
+    /// avisa a `input_finished` cuando se cierra la entrada.
+    pub fn new(screen: Recipient<Orders>, input_finished: Recipient<InputFinished>) -> Self {
+        TerminalActor {
+            screen,
+            input_finished,
+            customers: HashMap::new(),
+        }
+    }
+}
+
+impl Actor for TerminalActor {
+    type Context = Context<Self>;
+
+    fn started(&mut self, _ctx: &mut Context<Self>) {
+        let screen = self.screen.clone();
+        let input_finished = self.input_finished.clone();
+        thread::spawn(move || {
+            take_orders(screen);
+            let _ = input_finished.do_send(InputFinished);
+        });
+    }
+}
+
+/// Lee pedidos de la entrada estándar hasta que se cierra, enviándolos al ScreenActor.
+fn take_orders(screen: Recipient<Orders>) {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut taken = 0;
+    loop {
+        println!("=== Nuevo pedido ===");
+        let mut order = match read_order(&mut lines) {
+            Some(order) => order,
+            None => {
+                println!("Fin de la entrada. No se toman más pedidos");
+                return;
+            }
+        };
+        taken += 1;
+        order.set_id(format!("term-{}", taken));
+        if screen.do_send(Orders::new(vec![order])).is_err() {
+            eprintln!("No se pudo enviar el pedido a la pantalla");
+            return;
+        }
+        println!("Pedido enviado. Esperando la autorización del pago...");
+    }
+}
+
+/// Arma un pedido preguntando tamaño, sabores y cliente. Devuelve `None` si se cierra la entrada.
+fn read_order<B: BufRead>(lines: &mut io::Lines<B>) -> Option<Order> {
+    let (size_name, grams, max_flavors) = loop {
+        println!("Elegí el tamaño:");
+        for (index, (name, _, max_flavors)) in CUP_SIZES.iter().enumerate() {
+            println!("  {}) {} (hasta {} sabores)", index + 1, name, max_flavors);
+        }
+        match parse_choice(&prompt(lines)?, CUP_SIZES.len()) {
+            Some(index) => break CUP_SIZES[index],
+            None => println!("Opción inválida"),
+        }
+    };
+
+    let catalog: Vec<&IceCreamFlavor> = IceCreamFlavor::iter().collect();
+    let flavors = loop {
+        println!(
+            "Elegí hasta {} sabores separados por coma para tu pote de {}:",
+            max_flavors, size_name
+        );
+        for (index, flavor) in catalog.iter().enumerate() {
+            println!("  {}) {}", index + 1, flavor);
+        }
+        let input = prompt(lines)?;
+        let choices: Option<Vec<usize>> = input
+            .split(',')
+            .map(|choice| parse_choice(choice, catalog.len()))
+            .collect();
+        match choices {
+            Some(choices) if !choices.is_empty() && choices.len() <= max_flavors => {
+                break choices;
+            }
+            _ => println!("Selección inválida"),
+        }
+    };
+
+    println!("Nombre del cliente (opcional):");
+    let customer = prompt(lines)?;
+
+    // Se reparten los gramos del pote en partes iguales entre los sabores elegidos
+    let grams_per_flavor = grams / flavors.len() as i32;
+    let remainder = grams % flavors.len() as i32;
+    let order_flavors = flavors
+        .iter()
+        .enumerate()
+        .map(|(position, index)| {
+            let extra = if position == 0 { remainder } else { 0 };
+            (catalog[*index].to_string(), grams_per_flavor + extra)
+        })
+        .collect();
+    Some(Order::new(order_flavors, customer))
+}
+
+/// Muestra el prompt y devuelve la siguiente línea ingresada, o `None` si se cierra la entrada.
+fn prompt<B: BufRead>(lines: &mut io::Lines<B>) -> Option<String> {
+    print!("> ");
+    let _ = io::stdout().flush();
+    match lines.next() {
+        Some(Ok(line)) => Some(line.trim().to_string()),
+        _ => None,
+    }
+}
+
+/// Convierte una opción numerada (desde 1) en un índice válido para `options` elementos.
+fn parse_choice(input: &str, options: usize) -> Option<usize> {
+    match input.trim().parse::<usize>() {
+        Ok(choice) if choice >= 1 && choice <= options => Some(choice - 1),
+        _ => None,
+    }
+}
+
+/// Muestra al cliente cada cambio de estado de sus pedidos.
+impl Handler<OrderStatus> for TerminalActor {
+    type Result = ();
+
+    fn handle(&mut self, msg: OrderStatus, _ctx: &mut Context<Self>) {
+        match msg {
+            OrderStatus::Authorized {
+                order_id,
+                customer,
+                details,
+                ..
+            } => {
+                println!(
+                    ">> Pago APROBADO para {}: pedido {} ({}). Preparando...",
+                    display_customer(&customer),
+                    order_id,
+                    details
+                );
+                self.customers.insert(order_id, customer);
+            }
+            OrderStatus::Rejected {
+                customer, details, ..
+            } => {
+                println!(
+                    ">> Pago RECHAZADO para {} ({})",
+                    display_customer(&customer),
+                    details
+                );
+            }
+            OrderStatus::OutOfStock {
+                customer, details, ..
+            } => {
+                println!(
+                    ">> No hay stock para el pedido de {} ({}). No se realizó el cobro",
+                    display_customer(&customer),
+                    details
+                );
+            }
+            OrderStatus::Committed { order_id } => {
+                let customer = self.customers.remove(&order_id).unwrap_or_default();
+                println!(
+                    ">> Pedido {} de {} ENTREGADO y cobrado",
+                    order_id,
+                    display_customer(&customer)
+                );
+            }
+            OrderStatus::Aborted { order_id, reason } => {
+                let customer = self.customers.remove(&order_id).unwrap_or_default();
+                println!(
+                    ">> Pedido {} de {} CANCELADO ({}). No se realizó el cobro",
+                    order_id,
+                    display_customer(&customer),
+                    reason.description()
+                );
+            }
+        }
+    }
+}
+
+/// Nombre a mostrar para un cliente, que puede no haberse identificado.
+fn display_customer(customer: &str) -> &str {
+    if customer.is_empty() {
+        "cliente anónimo"
+    } else {
+        customer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &str) -> Option<Order> {
+        read_order(&mut input.as_bytes().lines())
+    }
+
+    #[test]
+    fn choices_are_numbered_from_one() {
+        assert_eq!(parse_choice("1", 3), Some(0));
+        assert_eq!(parse_choice(" 3 ", 3), Some(2));
+        assert_eq!(parse_choice("0", 3), None);
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("-1", 3), None);
+        assert_eq!(parse_choice("dos", 3), None);
+        assert_eq!(parse_choice("", 3), None);
+    }
+
+    #[test]
+    fn cup_grams_are_split_between_the_chosen_flavors() {
+        let order = read("1\n1,4\nana\n").unwrap();
+        assert_eq!(order.describe(), "Vanilla 125g, Mint 125g");
+        assert_eq!(order.customer_reference(), "ana");
+
+        // El resto de la división queda en el primer sabor
+        let order = read("2\n2,3,4\n\n").unwrap();
+        assert_eq!(
+            order.describe(),
+            "Chocolate 168g, Strawberry 166g, Mint 166g"
+        );
+        assert_eq!(order.customer_reference(), "");
+    }
+
+    #[test]
+    fn invalid_answers_are_asked_again() {
+        // Tamaño inexistente, sabor inexistente, selección vacía y más sabores que los del pote
+        let order = read("9\n1\n1,7\n\n1,2,3\n3\nana\n").unwrap();
+        assert_eq!(order.describe(), "Strawberry 250g");
+    }
+
+    #[test]
+    fn closing_the_input_cancels_the_order() {
+        assert!(read("").is_none());
+        assert!(read("1\n").is_none());
+        assert!(read("1\n1\n").is_none());
+    }
+
+    #[test]
+    fn anonymous_customers_are_named_for_display() {
+        assert_eq!(display_customer(""), "cliente anónimo");
+        assert_eq!(display_customer("ana"), "ana");
+    }
+}
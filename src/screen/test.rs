@@ -1,4 +1,7 @@
 use crate::fs::remove_file;
+use heladeria::common::{logger, recorder};
+use heladeria::screen::progress::ProgressJournal;
+use heladeria::screen::summary::SummaryActor;
 use serial_test::serial;
 use std::fs::File;
 use std::io::BufRead;
@@ -8,13 +11,28 @@ use std::io::Write;
 
 const LOG_FILE_PATH: &str = "transactions.log";
 const ORDERS_TEST: &str = "orders_test.json";
+const RECONCILE_LOG_TEST: &str = "reconcile_test.log";
+const STANDBY_LOG_FILE_PATH: &str = "transactions_standby.log";
+const HTTP_TEST_PORT: u16 = 8081;
+const SPOOL_DIR_TEST: &str = "orders_spool_test";
+const MAX_TEST_SCREENS: usize = 4;
+const WINDOW_ORDERS_TEST: &str = "orders_window_test.ndjson";
+const SUMMARY_ORDERS_TEST: &str = "orders_summary_test.ndjson";
+const DEADLINE_ORDERS_TEST: &str = "orders_deadline_test.ndjson";
+const PRECHECK_ORDERS_TEST: &str = "orders_precheck_test.ndjson";
+const LAUNCHER_ORDERS_TEST: &str = "orders_launcher_test.ndjson";
+const LAUNCHER_TOPOLOGY_TEST: &str = "topology_test.json";
+const SCENARIO_TEST: &str = "scenarios/dead_robot.json";
+const LOADGEN_PROFILE_TEST: &str = "loadgen_profile_test.json";
+const LOADGEN_ORDERS_TEST: &str = "orders_loadgen_test.ndjson";
+const RECORD_DIR_TEST: &str = "recording_test";
 
 #[cfg(test)]
 mod test {
 
     use super::*;
 
-    use std::process::{Child, Command};
+    use std::process::{Child, Command, Stdio};
 
     fn start_gateway(reject_percentage: Option<u8>) -> Child {
         let mut cmd = Command::new("cargo");
@@ -25,6 +43,13 @@ mod test {
         cmd.spawn().expect("Failed to start gateway")
     }
 
+    fn start_standby_gateway() -> Child {
+        Command::new("cargo")
+            .args(&["run", "--bin", "gateway", "0", "--standby"])
+            .spawn()
+            .expect("Failed to start standby gateway")
+    }
+
     fn start_robot(robot_id: u8, total_robots: u8) -> Child {
         Command::new("cargo")
             .args(&[
@@ -52,6 +77,73 @@ mod test {
             .expect("Failed to start screen")
     }
 
+    fn start_windowed_screen(
+        screen_id: u8,
+        total_screens: u8,
+        file_name: &str,
+        window: usize,
+    ) -> Child {
+        Command::new("cargo")
+            .args(&[
+                "run",
+                "--bin",
+                "screen",
+                &screen_id.to_string(),
+                &total_screens.to_string(),
+                file_name,
+                "--window",
+                &window.to_string(),
+            ])
+            .spawn()
+            .expect("Failed to start screen")
+    }
+
+    fn start_precheck_screen(screen_id: u8, total_screens: u8, file_name: &str) -> Child {
+        Command::new("cargo")
+            .args(&[
+                "run",
+                "--bin",
+                "screen",
+                &screen_id.to_string(),
+                &total_screens.to_string(),
+                file_name,
+                "--precheck-stock",
+            ])
+            .spawn()
+            .expect("Failed to start screen")
+    }
+
+    fn start_interactive_screen(screen_id: u8, total_screens: u8) -> Child {
+        Command::new("cargo")
+            .args(&[
+                "run",
+                "--bin",
+                "screen",
+                &screen_id.to_string(),
+                &total_screens.to_string(),
+                "--interactive",
+            ])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("Failed to start interactive screen")
+    }
+
+    fn start_http_screen(screen_id: u8, total_screens: u8, file_name: &str, port: u16) -> Child {
+        Command::new("cargo")
+            .args(&[
+                "run",
+                "--bin",
+                "screen",
+                &screen_id.to_string(),
+                &total_screens.to_string(),
+                file_name,
+                "--http",
+                &port.to_string(),
+            ])
+            .spawn()
+            .expect("Failed to start screen")
+    }
+
     fn create_rejected_flavour_test_orders() {
         let orders = r#"
         {
@@ -210,6 +302,14 @@ mod test {
         if let Err(e) = remove_file(ORDERS_TEST) {
             eprintln!("Failed to delete file {}: {}", ORDERS_TEST, e);
         }
+        delete_progress_journals();
+    }
+
+    fn delete_progress_journals() {
+        for screen_id in 0..MAX_TEST_SCREENS {
+            let _ = remove_file(ProgressJournal::path(screen_id));
+            let _ = remove_file(SummaryActor::path(screen_id));
+        }
     }
 
     #[test]
@@ -275,9 +375,71 @@ mod test {
         let _ = robot1.kill();
         let _ = screen0.kill();
         let _ = screen1.kill();
-        delete_orders_test_file();
         // Verificar el log para comprobar el estado ABORT
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert!(
+            !log.contains("COMMIT"),
+            "An order without stock was committed"
+        );
         verify_aborted_transaction(LOG_FILE_PATH);
+        delete_orders_test_file();
+    }
+
+    #[test]
+    #[serial]
+    fn test_out_of_stock_order_is_skipped_before_charging() {
+        // El primer pedido se prepara, y al asignarlo el robot líder publica su stock; el segundo
+        // sale cuando termina el primero, así que la pantalla ya sabe que no alcanza
+        let orders = "{\"flavors\": [{\"name\": \"Mint\", \"grams\": 100}], \"customer\": \"c1\"}\n\
+                      {\"flavors\": [{\"name\": \"Mint\", \"grams\": 10001}], \"customer\": \"c2\"}\n";
+        std::fs::write(PRECHECK_ORDERS_TEST, orders).expect("Failed to create orders file");
+        delete_progress_journals();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens checking the published stock before charging
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_precheck_screen(0, 2, PRECHECK_ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_precheck_screen(1, 2, PRECHECK_ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        let _ = remove_file(PRECHECK_ORDERS_TEST);
+
+        let summaries: Vec<String> = (0..2)
+            .map(|screen_id| std::fs::read_to_string(SummaryActor::path(screen_id)))
+            .collect::<Result<_, _>>()
+            .expect("Summary was not written");
+        delete_progress_journals();
+        for summary in summaries {
+            let summary: serde_json::Value =
+                serde_json::from_str(&summary).expect("Summary is not valid JSON");
+            assert_eq!(summary["out_of_stock"], 1);
+            assert_eq!(summary["authorized"], 1);
+        }
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert_eq!(log.matches("PREPARE").count(), 2);
+        assert!(
+            !log.contains("10001g"),
+            "An order without stock reached the gateway"
+        );
+        assert!(!log.contains("ABORT"), "An order was charged and aborted");
+        verify_transactions(LOG_FILE_PATH);
     }
 
     #[test]
@@ -707,6 +869,1352 @@ mod test {
         // Check the outcomes here if necessary
     }
 
+    #[test]
+    #[serial]
+    fn test_partial_capture_and_refund() {
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let mut stream =
+            std::net::TcpStream::connect("127.0.0.1:6000").expect("Failed to connect to gateway");
+
+        let order_id =
+            send_gateway_command(&mut stream, "PREPARE,customer-1,Mint,100g;Vanilla,150g;")
+                .trim_start_matches("COMMIT,")
+                .to_string();
+
+        let captured = send_gateway_command(&mut stream, &format!("CAPTURE,{},100", order_id));
+        let over_refund = send_gateway_command(&mut stream, &format!("REFUND,{},101", order_id));
+        let refunded = send_gateway_command(&mut stream, &format!("REFUND,{},40", order_id));
+        let over_capture = send_gateway_command(&mut stream, &format!("CAPTURE,{},50", order_id));
+        let overflow = send_gateway_command(
+            &mut stream,
+            "PREPARE,customer-2,Mint,4294967295g;Vanilla,1g;",
+        );
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+
+        assert_eq!(captured, format!("CAPTURED,{},100", order_id));
+        assert_eq!(over_refund, "Amount exceeds captured amount");
+        assert_eq!(refunded, format!("REFUNDED,{},40", order_id));
+        assert_eq!(over_capture, "Order ID not found");
+        assert_eq!(overflow, "ABORT,AMOUNT_OVERFLOW");
+        verify_partial_capture_and_refund(LOG_FILE_PATH, &order_id);
+    }
+
+    #[test]
+    #[serial]
+    fn test_interactive_screen_order() {
+        // Start gateway
+        create_test_orders();
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_interactive_screen(1, 2);
+
+        // Order a 1/2 kg cup of Vanilla and Mint for customer Ana
+        screen1
+            .stdin
+            .take()
+            .expect("Failed to open screen stdin")
+            .write_all(b"2\n1,4\nAna\n")
+            .expect("Failed to write to screen stdin");
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(12));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert!(
+            log.contains(",1,Ana,Vanilla,250g;Mint,250g;"),
+            "Interactive order was not authorized"
+        );
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_http_order_api() {
+        // Start gateway
+        create_test_orders();
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_http_screen(1, 2, ORDERS_TEST, HTTP_TEST_PORT);
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let (invalid_status, _) = http_request(
+            "POST",
+            "/orders",
+            r#"{"flavors": [{"name": "Banana Split", "grams": 100}]}"#,
+        );
+        let (created_status, created) = http_request(
+            "POST",
+            "/orders",
+            r#"{"flavors": [{"name": "Mint", "grams": 100}], "customer": "Ana"}"#,
+        );
+        let (pending_status, pending) = http_request("GET", "/orders", "");
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(10));
+        let (status, order) = http_request("GET", "/orders/web-1", "");
+        let (missing_status, _) = http_request("GET", "/orders/web-2", "");
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        assert_eq!(invalid_status, 400);
+        assert_eq!(created_status, 201);
+        assert!(
+            created.contains(r#""id":"web-1""#),
+            "Unexpected body {}",
+            created
+        );
+        assert_eq!(pending_status, 200);
+        assert!(
+            pending.contains("web-1"),
+            "Order is not pending: {}",
+            pending
+        );
+        assert_eq!(status, 200);
+        assert!(
+            order.contains(r#""status":"committed""#),
+            "Order was not committed: {}",
+            order
+        );
+        assert_eq!(missing_status, 404);
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    fn http_request(method: &str, path: &str, body: &str) -> (u16, String) {
+        http_request_to(HTTP_TEST_PORT, method, path, body)
+    }
+
+    fn http_request_to(port: u16, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = std::net::TcpStream::connect(("127.0.0.1", port))
+            .expect("Failed to connect to HTTP endpoint");
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        );
+        stream
+            .write_all(request.as_bytes())
+            .expect("Failed to write HTTP request");
+        let mut response = String::new();
+        std::io::Read::read_to_string(&mut stream, &mut response)
+            .expect("Failed to read HTTP response");
+        let status = response
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .expect("Invalid HTTP response");
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, body)| body.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    #[test]
+    #[serial]
+    fn test_watched_directory_orders() {
+        // Start gateway
+        create_test_orders();
+        let _ = std::fs::remove_dir_all(SPOOL_DIR_TEST);
+        std::fs::create_dir(SPOOL_DIR_TEST).expect("Failed to create spool directory");
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, SPOOL_DIR_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        // Drop order files into the watched directory
+        spool_orders_file(
+            "a.ndjson",
+            "{\"flavors\": [{\"name\": \"Mint\", \"grams\": 100}], \"customer\": \"Ana\"}\n",
+        );
+        spool_orders_file(
+            "b.csv",
+            "order,customer,flavor,grams\n7,Beto,Vanilla,100\n7,Beto,Chocolate,50\n",
+        );
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(20));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        let processed = std::path::Path::new(SPOOL_DIR_TEST).join("processed");
+        let processed_files = std::fs::read_dir(&processed)
+            .expect("Failed to read processed directory")
+            .count();
+        let _ = std::fs::remove_dir_all(SPOOL_DIR_TEST);
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert_eq!(processed_files, 2, "Order files were not moved");
+        assert!(log.contains(",1,Ana,Mint,100g;"), "NDJSON order missing");
+        assert!(
+            log.contains(",1,Beto,Vanilla,100g;Chocolate,50g;"),
+            "CSV order missing"
+        );
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    fn spool_orders_file(name: &str, contents: &str) {
+        // Se escribe con otra extensión y se renombra para que no se lea a medio escribir
+        let path = std::path::Path::new(SPOOL_DIR_TEST).join(name);
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, contents).expect("Failed to write orders file");
+        std::fs::rename(&tmp_path, &path).expect("Failed to move orders file");
+    }
+
+    #[test]
+    #[serial]
+    fn test_transaction_screen_and_customer() {
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let mut stream =
+            std::net::TcpStream::connect("127.0.0.1:6000").expect("Failed to connect to gateway");
+        stream
+            .write_all(b"SCREEN,3,")
+            .expect("Failed to write to gateway");
+        let order_id = send_gateway_command(&mut stream, "PREPARE,customer-42,Mint,100g;")
+            .trim_start_matches("COMMIT,")
+            .to_string();
+        stream
+            .write_all(format!("COMMIT,{},", order_id).as_bytes())
+            .expect("Failed to write to gateway");
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert!(
+            log.contains(&format!("PREPARE,{},3,customer-42,Mint,100g;", order_id)),
+            "PREPARE does not record screen and customer"
+        );
+        assert!(
+            log.contains(&format!("COMMIT,{},3,customer-42,Mint,100g;", order_id)),
+            "COMMIT does not record screen and customer"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_standby_gateway_takeover() {
+        // Start gateways
+        let mut gateway = start_gateway(Some(0));
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let mut standby = start_standby_gateway();
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let mut stream =
+            std::net::TcpStream::connect("127.0.0.1:6000").expect("Failed to connect to gateway");
+        let order_id = send_gateway_command(&mut stream, "PREPARE,customer-1,Mint,100g;")
+            .trim_start_matches("COMMIT,")
+            .to_string();
+
+        // Kill the primary gateway and let the standby take over
+        let _ = gateway.kill();
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let mut stream = std::net::TcpStream::connect("127.0.0.1:6001")
+            .expect("Failed to connect to standby gateway");
+        stream
+            .write_all(format!("COMMIT,{},", order_id).as_bytes())
+            .expect("Failed to write to standby gateway");
+        std::thread::sleep(std::time::Duration::from_secs(1));
+
+        let _ = standby.kill();
+
+        verify_transactions(STANDBY_LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_standby_without_primary_does_not_take_over() {
+        let mut standby = start_standby_gateway();
+
+        // El respaldo reintenta conectarse al primario durante el período de gracia
+        std::thread::sleep(std::time::Duration::from_secs(3));
+        let listening = std::net::TcpStream::connect("127.0.0.1:6001").is_ok();
+        let status = standby.wait().expect("Failed to wait for standby gateway");
+
+        assert!(!listening, "Standby gateway took over without a primary");
+        assert!(
+            !status.success(),
+            "Standby gateway did not report the error"
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn test_repeated_prepare_key_is_not_held_twice() {
+        // Start gateways
+        let mut gateway = start_gateway(Some(0));
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let mut standby = start_standby_gateway();
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let prepare = "KEY,3-1-0,PREPARE,customer-1,Mint,100g;";
+        let mut stream =
+            std::net::TcpStream::connect("127.0.0.1:6000").expect("Failed to connect to gateway");
+        let first = send_gateway_command(&mut stream, prepare);
+        // La pantalla reenvía el PREPARE por otra conexión si no le llegó la respuesta
+        let mut stream =
+            std::net::TcpStream::connect("127.0.0.1:6000").expect("Failed to connect to gateway");
+        let repeated = send_gateway_command(&mut stream, prepare);
+
+        // Kill the primary gateway and let the standby take over
+        let _ = gateway.kill();
+        std::thread::sleep(std::time::Duration::from_secs(2));
+
+        let mut stream = std::net::TcpStream::connect("127.0.0.1:6001")
+            .expect("Failed to connect to standby gateway");
+        let failed_over = send_gateway_command(&mut stream, prepare);
+
+        let _ = standby.kill();
+
+        assert!(first.starts_with("COMMIT,"), "PREPARE was not authorized");
+        assert_eq!(repeated, first);
+        assert_eq!(failed_over, first);
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert_eq!(log.matches("PREPARE").count(), 1);
+        let standby_log =
+            std::fs::read_to_string(STANDBY_LOG_FILE_PATH).expect("Failed to read log file");
+        assert_eq!(standby_log.matches("PREPARE").count(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_screen_waits_for_gateway() {
+        create_test_orders();
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens before the gateway is available
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+
+        // Start gateway late, screens must reconnect and keep their orders
+        std::thread::sleep(std::time::Duration::from_secs(3));
+        let mut gateway = start_gateway(Some(0));
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert!(log.contains("COMMIT"), "No order was committed");
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_screen_resumes_orders_after_restart() {
+        create_test_orders();
+        delete_progress_journals();
+
+        // First run: screens process the orders in the file
+        let mut processes = start_two_screen_system();
+        std::thread::sleep(std::time::Duration::from_secs(12));
+        for process in processes.iter_mut() {
+            let _ = process.kill();
+        }
+        let first_log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        verify_transactions(LOG_FILE_PATH);
+
+        // Second run with the same file: screens only authorize the orders left unprocessed
+        let mut processes = start_two_screen_system();
+        std::thread::sleep(std::time::Duration::from_secs(12));
+        for process in processes.iter_mut() {
+            let _ = process.kill();
+        }
+        let second_log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        let journal = std::fs::read_to_string(ProgressJournal::path(0)).unwrap_or_default();
+        delete_orders_test_file();
+
+        assert_eq!(
+            first_log.matches("PREPARE").count() + second_log.matches("PREPARE").count(),
+            4,
+            "Each order must be authorized exactly once across restarts"
+        );
+        assert_eq!(journal.matches("AUTHORIZED").count(), 2);
+    }
+
+    fn start_two_screen_system() -> Vec<Child> {
+        let gateway = start_gateway(Some(0));
+        let robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let robot1 = start_robot(1, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let screen1 = start_screen(1, 2, ORDERS_TEST);
+        vec![gateway, robot0, robot1, screen0, screen1]
+    }
+
+    #[test]
+    #[serial]
+    fn test_order_window_limits_in_flight_orders() {
+        let orders: String = (1..=3)
+            .map(|n| {
+                format!(
+                    "{{\"flavors\": [{{\"name\": \"Mint\", \"grams\": 50}}], \"customer\": \"c{}\"}}\n",
+                    n
+                )
+            })
+            .collect();
+        std::fs::write(WINDOW_ORDERS_TEST, orders).expect("Failed to create orders file");
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens allowing a single order in flight
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_windowed_screen(0, 2, WINDOW_ORDERS_TEST, 1);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_windowed_screen(1, 2, WINDOW_ORDERS_TEST, 1);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(20));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        let _ = remove_file(WINDOW_ORDERS_TEST);
+        delete_progress_journals();
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert_eq!(
+            log.matches("PREPARE").count(),
+            6,
+            "Not every order was processed"
+        );
+        assert_eq!(max_in_flight_per_screen(&log), 1);
+        for screen in ["0", "1"] {
+            let customers: Vec<&str> = log
+                .lines()
+                .map(|line| line.split(',').collect::<Vec<&str>>())
+                .filter(|parts| parts[0] == "PREPARE" && parts.get(2) == Some(&screen))
+                .map(|parts| parts[3])
+                .collect();
+            assert_eq!(
+                customers,
+                ["c1", "c2", "c3"],
+                "Orders not processed in file order"
+            );
+        }
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_screen_writes_summary() {
+        let orders = "{\"flavors\": [{\"name\": \"Mint\", \"grams\": 50}]}\n\
+                      {\"flavors\": [{\"name\": \"Pistachio\", \"grams\": 50}]}\n\
+                      {\"flavors\": [{\"name\": \"Vanilla\", \"grams\": 50}]}\n";
+        std::fs::write(SUMMARY_ORDERS_TEST, orders).expect("Failed to create orders file");
+        delete_progress_journals();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, SUMMARY_ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, SUMMARY_ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        let _ = remove_file(SUMMARY_ORDERS_TEST);
+
+        let summary = std::fs::read_to_string(SummaryActor::path(0));
+        delete_progress_journals();
+        let summary: serde_json::Value =
+            serde_json::from_str(&summary.expect("Summary was not written"))
+                .expect("Summary is not valid JSON");
+        assert_eq!(summary["authorized"], 2);
+        assert_eq!(summary["discarded"], 1);
+        assert_eq!(summary["rejected"], 0);
+        let committed = summary["committed"].as_u64().unwrap();
+        let aborted = summary["aborted_orders"].as_array().unwrap().len() as u64;
+        assert_eq!(committed + aborted, 2);
+        let served: i64 = summary["grams_per_flavor"]
+            .as_object()
+            .unwrap()
+            .values()
+            .map(|grams| grams.as_i64().unwrap())
+            .sum();
+        assert_eq!(served, 50 * committed as i64);
+        if committed > 0 {
+            assert_eq!(summary["latency"]["count"], committed);
+        }
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_expired_order_is_aborted() {
+        // El pedido de Mint tarda 10 segundos en prepararse, pero tiene 2 de plazo
+        let orders =
+            "{\"flavors\": [{\"name\": \"Mint\", \"grams\": 1000}], \"deadline_secs\": 2}\n\
+                      {\"flavors\": [{\"name\": \"Vanilla\", \"grams\": 50}]}\n";
+        std::fs::write(DEADLINE_ORDERS_TEST, orders).expect("Failed to create orders file");
+        delete_progress_journals();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, DEADLINE_ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, DEADLINE_ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(40));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        let _ = remove_file(DEADLINE_ORDERS_TEST);
+        delete_progress_journals();
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        let expired: Vec<&str> = log
+            .lines()
+            .filter(|line| line.starts_with("ABORT") && line.contains(",DEADLINE_EXPIRED,"))
+            .filter_map(|line| line.split(',').nth(1))
+            .collect();
+        assert_eq!(expired.len(), 2, "Expired orders were not aborted");
+        for order_id in expired {
+            assert!(
+                !log.contains(&format!("COMMIT,{},", order_id)),
+                "Expired order {} was committed",
+                order_id
+            );
+        }
+        // Cancelar los pedidos vencidos libera la ventana para los siguientes
+        assert_eq!(log.matches("COMMIT").count(), 2);
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_launcher_runs_topology() {
+        let orders = "{\"flavors\": [{\"name\": \"Mint\", \"grams\": 50}]}\n\
+                      {\"flavors\": [{\"name\": \"Vanilla\", \"grams\": 50}]}\n";
+        std::fs::write(LAUNCHER_ORDERS_TEST, orders).expect("Failed to create orders file");
+        let topology = format!(
+            r#"{{"gateway": {{"reject_percentage": 0}}, "robots": 2,
+                "screens": [{{"orders": "{0}"}}, {{"orders": "{0}"}}], "duration_secs": 15}}"#,
+            LAUNCHER_ORDERS_TEST
+        );
+        std::fs::write(LAUNCHER_TOPOLOGY_TEST, topology).expect("Failed to create topology");
+        delete_progress_journals();
+
+        // El launcher ejecuta los binarios ya compilados
+        let build = Command::new("cargo")
+            .args(&["build", "--bins"])
+            .status()
+            .expect("Failed to build binaries");
+        assert!(build.success());
+
+        let status = Command::new("cargo")
+            .args(&["run", "--bin", "launcher", LAUNCHER_TOPOLOGY_TEST])
+            .status()
+            .expect("Failed to run launcher");
+
+        let _ = remove_file(LAUNCHER_ORDERS_TEST);
+        let _ = remove_file(LAUNCHER_TOPOLOGY_TEST);
+        delete_progress_journals();
+        assert!(status.success());
+
+        // Al terminar, el launcher detuvo todos los procesos
+        assert!(std::net::TcpStream::connect("127.0.0.1:6000").is_err());
+        assert!(std::net::TcpStream::connect("127.0.0.1:10000").is_err());
+        assert!(std::net::TcpStream::connect("127.0.0.1:3000").is_err());
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert_eq!(log.matches("COMMIT").count(), 4);
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_scenario_dead_robot() {
+        delete_progress_journals();
+
+        // El runner de escenarios ejecuta los binarios ya compilados
+        let build = Command::new("cargo")
+            .args(&["build", "--bins"])
+            .status()
+            .expect("Failed to build binaries");
+        assert!(build.success());
+
+        let status = Command::new("cargo")
+            .args(&["run", "--bin", "scenario", SCENARIO_TEST])
+            .status()
+            .expect("Failed to run scenario");
+
+        delete_progress_journals();
+        assert!(status.success());
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_loadgen_orders_are_processed() {
+        let profile = r#"{"orders": 4, "cup_sizes": [100], "rate_per_sec": 2, "seed": 42}"#;
+        std::fs::write(LOADGEN_PROFILE_TEST, profile).expect("Failed to create profile");
+        delete_progress_journals();
+
+        // La pantalla 1 lee los pedidos generados en un archivo
+        let generated = Command::new("cargo")
+            .args(&[
+                "run",
+                "--bin",
+                "loadgen",
+                "--",
+                "--profile",
+                LOADGEN_PROFILE_TEST,
+                "--output",
+                LOADGEN_ORDERS_TEST,
+            ])
+            .status()
+            .expect("Failed to run loadgen");
+        assert!(generated.success());
+        let orders = std::fs::read_to_string(LOADGEN_ORDERS_TEST).expect("Orders were not written");
+        assert_eq!(orders.lines().count(), 4);
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // La pantalla 0 recibe los pedidos por la entrada estándar a medida que se generan
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut loadgen = Command::new("cargo")
+            .args(&[
+                "run",
+                "--bin",
+                "loadgen",
+                "--",
+                "--profile",
+                LOADGEN_PROFILE_TEST,
+            ])
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to start loadgen");
+        let mut screen0 = Command::new("cargo")
+            .args(&["run", "--bin", "screen", "0", "2", "-"])
+            .stdin(loadgen.stdout.take().expect("Failed to pipe loadgen"))
+            .spawn()
+            .expect("Failed to start screen");
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, LOADGEN_ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(30));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        let _ = loadgen.kill();
+        let _ = remove_file(LOADGEN_PROFILE_TEST);
+        let _ = remove_file(LOADGEN_ORDERS_TEST);
+        delete_progress_journals();
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        assert_eq!(log.matches("COMMIT").count(), 8);
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_robot_json_logs_carry_order_ids() {
+        create_test_orders();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // El robot líder registra sus eventos en JSON
+        let mut robot0 = Command::new("cargo")
+            .args(&["run", "--bin", "robot", "0", "2"])
+            .env(logger::LOG_FORMAT_ENV, "json")
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to start robot");
+        let robot0_output = robot0.stdout.take().expect("Failed to pipe robot");
+        let (tx_lines, rx_lines) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(robot0_output).lines().map_while(Result::ok) {
+                let _ = tx_lines.send(line);
+            }
+        });
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        let records: Vec<serde_json::Value> = rx_lines
+            .try_iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(&line).expect("Log line is not JSON"))
+            .collect();
+        assert!(!records.is_empty());
+        for record in &records {
+            assert_eq!(record["component"], "robot");
+            assert_eq!(record["node_id"], 0);
+            assert!(record["level"].is_string());
+            assert!(record["message"].is_string());
+        }
+
+        // Cada pedido del log del gateway aparece con su ID en los eventos del robot líder
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        for line in log.lines().filter(|line| line.starts_with("PREPARE")) {
+            let order_id: u64 = line.split(',').nth(1).unwrap().parse().unwrap();
+            assert!(records
+                .iter()
+                .any(|record| record["order_id"].as_u64() == Some(order_id)));
+        }
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    fn metric_value(metrics: &str, series: &str) -> Option<f64> {
+        metrics
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .find_map(|line| line.strip_prefix(series)?.strip_prefix(' '))
+            .and_then(|value| value.parse().ok())
+    }
+
+    #[test]
+    #[serial]
+    fn test_metrics_endpoints() {
+        create_test_orders();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        let (gateway_status, gateway_metrics) = http_request_to(9300, "GET", "/metrics", "");
+        let robot_metrics: Vec<String> = [9100, 9101]
+            .iter()
+            .map(|port| http_request_to(*port, "GET", "/metrics", "").1)
+            .collect();
+        let (_, screen_metrics) = http_request_to(9200, "GET", "/metrics", "");
+        let (missing_status, _) = http_request_to(9300, "GET", "/missing", "");
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        let log = std::fs::read_to_string(LOG_FILE_PATH).expect("Failed to read log file");
+        let count = |kind: &str| {
+            log.lines()
+                .filter(|line| line.starts_with(&format!("{},", kind)))
+                .count() as f64
+        };
+
+        assert_eq!(gateway_status, 200);
+        assert_eq!(missing_status, 404);
+        assert_eq!(
+            metric_value(
+                &gateway_metrics,
+                r#"heladeria_gateway_payments_total{result="approved"}"#
+            ),
+            Some(count("PREPARE"))
+        );
+        assert_eq!(
+            metric_value(&gateway_metrics, "heladeria_gateway_pending_holds"),
+            Some(0.0)
+        );
+
+        // Cada pedido cobrado lo preparó alguno de los robots
+        let prepared: f64 = robot_metrics
+            .iter()
+            .filter_map(|metrics| metric_value(metrics, "heladeria_robot_orders_prepared_total"))
+            .sum();
+        assert_eq!(prepared, count("COMMIT"));
+        for flavor in ["Chocolate", "Mint", "Strawberry", "Vanilla"] {
+            assert!(metric_value(
+                &robot_metrics[0],
+                &format!(r#"heladeria_robot_stock_grams{{flavor="{}"}}"#, flavor)
+            )
+            .is_some());
+        }
+        assert!(robot_metrics[0].contains("# TYPE heladeria_robot_tokens_passed_total counter"));
+
+        assert!(metric_value(
+            &screen_metrics,
+            r#"heladeria_screen_orders_total{result="committed"}"#
+        )
+        .is_some_and(|committed| committed > 0.0));
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_token_stats_reach_leader() {
+        create_test_orders();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // El robot líder registra sus eventos de depuración, incluidas las estadísticas recibidas
+        let mut robot0 = Command::new("cargo")
+            .args(&["run", "--bin", "robot", "0", "2"])
+            .env(logger::LOG_LEVEL_ENV, "debug")
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to start robot");
+        let robot0_output = robot0.stdout.take().expect("Failed to pipe robot");
+        let (tx_lines, rx_lines) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(robot0_output).lines().map_while(Result::ok) {
+                let _ = tx_lines.send(line);
+            }
+        });
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        let waited: f64 = [9100, 9101]
+            .iter()
+            .map(|port| http_request_to(*port, "GET", "/metrics", "").1)
+            .flat_map(|metrics| {
+                ["Chocolate", "Mint", "Strawberry", "Vanilla"].map(|flavor| {
+                    metric_value(
+                        &metrics,
+                        &format!(
+                            r#"heladeria_robot_token_wait_seconds_total{{flavor="{}"}}"#,
+                            flavor
+                        ),
+                    )
+                    .unwrap_or(0.0)
+                })
+            })
+            .sum();
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        let lines: Vec<String> = rx_lines.try_iter().collect();
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("Estadísticas de tokens del robot 1")));
+        assert!(lines
+            .iter()
+            .any(|line| line.contains("Estadísticas de tokens del robot 0")));
+        assert!(waited > 0.0);
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_admin_ctl() {
+        create_test_orders();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        // Los pedidos de prueba no usan Strawberry, así que su stock solo cambia al reponerlo
+        let strawberry = |output: &str| -> Option<i32> {
+            output
+                .lines()
+                .find_map(|line| line.strip_prefix("Strawberry "))
+                .and_then(|grams| grams.trim_end_matches(" g").parse().ok())
+        };
+        let (stock_ok, stock_before) = run_ctl(&["--robot", "0", "stock"]);
+        let (restock_ok, _) = run_ctl(&["--robot", "1", "restock", "Strawberry", "500"]);
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let (_, stock_after) = run_ctl(&["--robot", "0", "stock"]);
+        let (invalid_ok, _) = run_ctl(&["--robot", "0", "restock", "Strawberry", "-5"]);
+
+        let (drain_ok, _) = run_ctl(&["--robot", "0", "drain", "1"]);
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        let statuses: Vec<String> = ["0", "1"]
+            .iter()
+            .map(|id| run_ctl(&["--robot", id, "status"]).1)
+            .collect();
+        let (_, screen_leaders) = run_ctl(&["--screen", "0", "leader"]);
+        let (screen_drain_ok, _) = run_ctl(&["--screen", "0", "drain", "1"]);
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        assert!(stock_ok && restock_ok && drain_ok);
+        assert!(!invalid_ok && !screen_drain_ok);
+        assert_eq!(
+            strawberry(&stock_after),
+            strawberry(&stock_before).map(|grams| grams + 500)
+        );
+        for status in &statuses {
+            assert!(status.contains("Robots retirados: 1"), "{}", status);
+        }
+        assert!(screen_leaders.contains("Pantalla líder: 0"));
+        assert!(screen_leaders.contains("Robot líder: 0"));
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_topology_snapshot() {
+        create_test_orders();
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        // Se parte de una pantalla para que la consola descubra los robots por el líder
+        let (json_ok, json) = run_ctl(&["--screen", "1", "topology"]);
+        let (dot_ok, dot) = run_ctl(&["topology", "dot"]);
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        assert!(json_ok && dot_ok);
+        let topology: serde_json::Value =
+            serde_json::from_str(&json).expect("Invalid topology JSON");
+        for ring in ["robots", "screens"] {
+            assert_eq!(topology[ring]["leader_id"], 0);
+            assert_eq!(topology[ring]["nodes"].as_array().map(Vec::len), Some(2));
+            assert_eq!(topology[ring]["links"].as_array().map(Vec::len), Some(2));
+        }
+        assert_eq!(topology["screen_robot_link"]["screen_id"], 0);
+        assert_eq!(topology["screen_robot_link"]["robot_id"], 0);
+
+        assert!(dot.starts_with("digraph heladeria {"));
+        assert!(dot.contains("\"robot 0\" -> \"robot 1\";"));
+        assert!(dot.contains("\"screen 0\" -> \"robot 0\" [style=dashed"));
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_recorded_traffic_decodes() {
+        create_test_orders();
+        let _ = std::fs::remove_dir_all(RECORD_DIR_TEST);
+
+        // Los procesos heredan la variable de entorno que activa la grabación
+        std::env::set_var(recorder::RECORD_DIR_ENV, RECORD_DIR_TEST);
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot0 = start_robot(0, 2);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 2);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+        std::env::remove_var(recorder::RECORD_DIR_ENV);
+
+        // Simulate a waiting period to allow processes to run
+        std::thread::sleep(std::time::Duration::from_secs(15));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        let output = Command::new("cargo")
+            .args(&[
+                "run",
+                "--bin",
+                "decode",
+                "--",
+                "--skip",
+                "Token",
+                RECORD_DIR_TEST,
+            ])
+            .output()
+            .expect("Failed to run decode");
+        let timeline = String::from_utf8_lossy(&output.stdout).to_string();
+        let recordings: Vec<String> = std::fs::read_dir(RECORD_DIR_TEST)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default();
+        let _ = std::fs::remove_dir_all(RECORD_DIR_TEST);
+
+        assert!(output.status.success());
+        for node in ["robot_0", "robot_1", "screen_0", "screen_1", "gateway"] {
+            assert!(
+                recordings.contains(&format!("{}.rec", node)),
+                "{:?}",
+                recordings
+            );
+        }
+        let received = |node: &str, peer: &str, kind: &str| {
+            timeline
+                .lines()
+                .any(|line| line.contains(&format!("{} <- {}", node, peer)) && line.contains(kind))
+        };
+        assert!(received("robot 0  ", "robot ", "AllConnected("));
+        assert!(received("robot 0  ", "screen", "Order("));
+        assert!(received("screen 0 ", "robot ", "Commit("));
+        assert!(received("gateway  ", "screen", "\"KEY,"));
+        assert!(received("screen 0 ", "gateway", "\"COMMIT,"));
+        assert!(!timeline.contains("Token(Token"));
+        assert!(!timeline.contains("mensaje inválido"));
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    #[test]
+    #[serial]
+    fn test_replay_reproduces_dead_robot() {
+        create_test_orders();
+        let _ = std::fs::remove_dir_all(RECORD_DIR_TEST);
+        std::env::set_var(recorder::RECORD_DIR_ENV, RECORD_DIR_TEST);
+
+        // Start gateway
+        let mut gateway = start_gateway(Some(0));
+
+        // Start robots
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot0 = start_robot(0, 3);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot1 = start_robot(1, 3);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut robot2 = start_robot(2, 3);
+
+        // Start screens
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen0 = start_screen(0, 2, ORDERS_TEST);
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        let mut screen1 = start_screen(1, 2, ORDERS_TEST);
+        std::env::remove_var(recorder::RECORD_DIR_ENV);
+
+        // El robot 1 lee los mensajes del robot 2: es el que detecta su caída
+        std::thread::sleep(std::time::Duration::from_secs(4));
+        let _ = robot2.kill();
+        std::thread::sleep(std::time::Duration::from_secs(8));
+
+        // Kill the processes after the test
+        let _ = gateway.kill();
+        let _ = robot0.kill();
+        let _ = robot1.kill();
+        let _ = screen0.kill();
+        let _ = screen1.kill();
+        delete_orders_test_file();
+
+        let recording = format!("{}/robot_1.rec", RECORD_DIR_TEST);
+        let output = Command::new("cargo")
+            .args(&["run", "--bin", "replay", "--", &recording, "3"])
+            .output()
+            .expect("Failed to run replay");
+        let replay = String::from_utf8_lossy(&output.stdout).to_string();
+        let _ = std::fs::remove_dir_all(RECORD_DIR_TEST);
+
+        assert!(output.status.success());
+        let lines: Vec<&str> = replay
+            .lines()
+            .filter(|line| !line.starts_with("INFO") && !line.starts_with("DEBUG"))
+            .collect();
+        let closed = lines
+            .iter()
+            .position(|line| line.contains("-x robot    conexión cerrada"))
+            .expect("La reproducción no muestra la caída del robot 2");
+        assert!(lines[closed + 1]
+            .contains("-> robot    DeadRobot(DeadRobot { sender_id: 1, dead_robot_id: 2 })"));
+        assert!(replay.contains("Anillo: 0 -> 1, 1 -> 0"), "{}", replay);
+        verify_transactions(LOG_FILE_PATH);
+    }
+
+    fn run_ctl(args: &[&str]) -> (bool, String) {
+        let output = Command::new("cargo")
+            .args(&["run", "--bin", "heladeria-ctl", "--"])
+            .args(args)
+            .output()
+            .expect("Failed to run heladeria-ctl");
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stdout).to_string(),
+        )
+    }
+
+    fn max_in_flight_per_screen(log: &str) -> usize {
+        let mut screen_by_order = std::collections::HashMap::new();
+        let mut open_by_screen = std::collections::HashMap::new();
+        let mut max_in_flight = 0;
+        for line in log.lines() {
+            let parts: Vec<&str> = line.split(',').collect();
+            if parts.len() < 3 {
+                continue;
+            }
+            if parts[0] == "PREPARE" {
+                screen_by_order.insert(parts[1].to_string(), parts[2].to_string());
+                let open = open_by_screen.entry(parts[2].to_string()).or_insert(0);
+                *open += 1;
+                max_in_flight = max_in_flight.max(*open);
+            } else if parts[0] == "COMMIT" || parts[0] == "ABORT" {
+                if let Some(screen) = screen_by_order.get(parts[1]) {
+                    *open_by_screen.get_mut(screen).unwrap() -= 1;
+                }
+            }
+        }
+        max_in_flight
+    }
+
+    #[test]
+    #[serial]
+    fn test_reconcile_transactions_log() {
+        let consistent_log = "PREPARE,1,0,customer-1,Mint,100g;\nPREPARE,2,1,,Vanilla,150g;\n\
+                              COMMIT,1,Mint,100g;\nCAPTURE,2,100,Vanilla,150g;\n\
+                              REFUND,2,50,Vanilla,150g;\n";
+        let inconsistent_log = "PREPARE,1,0,customer-1,Mint,100g;\nPREPARE,2,1,,Vanilla,150g;\n\
+                                COMMIT,1,Mint,100g;\nCOMMIT,1,Mint,100g;\n";
+
+        assert!(run_reconcile(consistent_log), "Consistent log was rejected");
+        assert!(
+            !run_reconcile(inconsistent_log),
+            "Inconsistent log was accepted"
+        );
+    }
+
+    fn run_reconcile(log: &str) -> bool {
+        let mut file = File::create(RECONCILE_LOG_TEST).expect("Failed to create test log file");
+        file.write_all(log.as_bytes())
+            .expect("Failed to write to test log file");
+
+        let status = Command::new("cargo")
+            .args(&["run", "--bin", "reconcile", RECONCILE_LOG_TEST])
+            .status()
+            .expect("Failed to run reconcile");
+
+        let _ = remove_file(RECONCILE_LOG_TEST);
+        status.success()
+    }
+
+    fn send_gateway_command(stream: &mut std::net::TcpStream, command: &str) -> String {
+        use std::io::Read;
+
+        stream
+            .write_all(command.as_bytes())
+            .expect("Failed to write to gateway");
+        let mut buffer = [0; 1024];
+        let bytes_read = stream
+            .read(&mut buffer)
+            .expect("Failed to read from gateway");
+        String::from_utf8_lossy(&buffer[..bytes_read])
+            .trim()
+            .to_string()
+    }
+
+    fn verify_partial_capture_and_refund(log_file: &str, order_id: &str) {
+        let file = File::open(log_file).expect("Failed to open log file");
+        let reader = BufReader::new(file);
+
+        let mut found_capture = false;
+        let mut found_refund = false;
+
+        for line in reader.lines() {
+            let line = line.expect("Failed to read line");
+            if line.starts_with(&format!("CAPTURE,{},100,,customer-1,", order_id)) {
+                found_capture = true;
+            } else if line.starts_with(&format!("REFUND,{},40,,customer-1,", order_id)) {
+                found_refund = true;
+            }
+        }
+
+        assert!(found_capture, "No CAPTURE found");
+        assert!(found_refund, "No REFUND found");
+    }
+
     fn verify_aborted_transaction(log_file: &str) {
         let file = File::open(log_file).expect("Failed to open log file");
         let reader = BufReader::new(file);
@@ -716,6 +2224,7 @@ mod test {
         for line in reader.lines() {
             let line = line.expect("Failed to read line");
             if line.starts_with("ABORT") {
+                assert_eq!(line.split(',').nth(2), Some("OUT_OF_STOCK"));
                 found_abort = true;
                 break;
             }
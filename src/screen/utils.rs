@@ -3,7 +3,9 @@
 // use heladeria::common::messages::*;
 use std::{io::Read, net::TcpStream, sync::Arc, thread};
 
+use crate::common::recorder::{self, Peer};
 use crate::common::{constants::BUFFER_SIZE, messages::*};
+use crate::{log_error, log_warn};
 
 use super::screen::Screen;
 
@@ -23,56 +25,38 @@ pub fn handle_incoming_connection(screen: Arc<Screen>, mut socket: TcpStream, my
         }
         Ok(n) => {
             read_buffer.extend_from_slice(&buffer[..n]);
-            match deserialize_message(&buffer) {
-                Some(message) => {
-                    match message {
-                        MessageType::ScreenIntroduction(_) => {
-                            handle_screen_connection(
-                                screen_ref_c,
-                                my_id,
-                                socket.try_clone().unwrap(),
-                            );
-                        }
-                        MessageType::RobotIntroduction(_) => {
-                            handle_robot_connection(
-                                screen_ref_c,
-                                my_id,
-                                socket.try_clone().unwrap(),
-                            );
-                        }
-                        MessageType::NewLeaderIntroduction(robot_id) => {
-                            // set new robot leader id y mandar a todos para que sepan
-                            screen_ref_c.set_new_robot_leader(robot_id);
-                            let new_robot_leader_msg =
-                                MessageType::UpdateRobotLeader(robot_id.clone());
-                            let _ = screen_ref_c.tx_sender_channel.send(new_robot_leader_msg);
-
-                            // new sender thread con el nuevo robot lider
-                            let screen_arc = screen_ref_c.clone();
-                            screen_arc.connect_robot(true);
-
-                            handle_robot_connection(
-                                screen_ref_c,
-                                my_id,
-                                socket.try_clone().unwrap(),
-                            );
-                        }
-                        _ => {
-                            println!("Mensaje no manejado: {:?}", message);
-                        }
+            if let Some(message) = take_message(&mut read_buffer) {
+                match message {
+                    MessageType::ScreenIntroduction(_) => {
+                        handle_screen_connection(
+                            screen_ref_c,
+                            my_id,
+                            resume_reading(read_buffer, socket),
+                        );
+                    }
+                    MessageType::RobotIntroduction(_) => {
+                        handle_robot_connection(
+                            screen_ref_c,
+                            my_id,
+                            resume_reading(read_buffer, socket),
+                        );
+                    }
+                    MessageType::NewLeaderIntroduction(robot_id) => {
+                        accept_robot_leader(&screen_ref_c, robot_id);
+                        handle_robot_connection(
+                            screen_ref_c,
+                            my_id,
+                            resume_reading(read_buffer, socket),
+                        );
+                    }
+                    _ => {
+                        log_warn!("Mensaje no manejado: {:?}", message);
                     }
-                    read_buffer.clear(); // Clear the buffer
-                }
-                None => {
-                    //println!("No value present");
                 }
             }
         }
         Err(e) => {
-            eprintln!(
-                "screen {}: Failed to read from socket; err = {:?}",
-                my_id, e
-            );
+            log_error!("Failed to read from socket; err = {:?}", e);
         }
     }
 }
@@ -81,105 +65,83 @@ pub fn handle_incoming_connection(screen: Arc<Screen>, mut socket: TcpStream, my
 ///
 /// Esta función lee mensajes desde el socket y los procesa según su tipo.
 ///
-pub fn handle_screen_connection(screen: Arc<Screen>, my_id: usize, mut socket: TcpStream) {
+pub fn handle_screen_connection(screen: Arc<Screen>, my_id: usize, mut socket: impl Read) {
+    recorder::set_peer(Peer::Screen);
     let screen_ref = screen.clone();
-    let tx_sender = screen.tx_sender_channel.clone();
     let mut buffer = vec![0; BUFFER_SIZE];
     let mut read_buffer = Vec::new();
 
     loop {
-        let screen_ref_c = screen_ref.clone();
         match socket.read(&mut buffer) {
             Ok(0) => {
-                let prev_screen_id = screen_ref_c.find_prev_screen(my_id);
-                // prev screen id es el que murio. Tengo que notificar su baja.
-                if let Some(dead_id) = prev_screen_id {
-                    println!("ID screen Muerto: {}", dead_id);
-                    screen.transfer_orders(dead_id, my_id);
-                    let deadscreen_msg = MessageType::DeadScreen(DeadScreen {
-                        sender_id: my_id,
-                        dead_screen_id: dead_id,
-                    });
-
-                    let _ = tx_sender.send(deadscreen_msg);
-
-                    // update network
-                    Screen::update_network(screen.clone(), dead_id);
-
-                    // Si el id muerto es el lider, hay que elegir nuevo lider
-                    if screen_ref.is_leader(dead_id) {
-                        screen_ref.send_leader_election_message(my_id, dead_id);
-                    }
-
-                    break;
-                }
+                recorder::record_closed();
+                handle_prev_screen_death(&screen, my_id);
+                break;
             }
             Ok(n) => {
                 read_buffer.extend_from_slice(&buffer[..n]);
-                match deserialize_message(&buffer) {
-                    Some(message) => {
-                        match message {
-                            MessageType::OrderScreen(ref order) => {
-                                if order.sender_id != my_id {
-                                    //println!("Recibido Order: {:?}", order);
-                                    Screen::apply_order(screen.clone(), order);
-                                    tx_sender.send(message).unwrap();
-                                } else {
-                                    Screen::apply_order(screen.clone(), order);
-                                }
-                            }
+                for message in take_messages(&mut read_buffer) {
+                    let screen_ref_c = screen_ref.clone();
+                    match message {
+                        MessageType::OrderScreen(_) => handle_order_screen(&screen, my_id, message),
 
-                            // Other type of message
-                            _ => {
-                                thread::spawn(move || {
-                                    handle_other_messages(
-                                        screen_ref_c.clone(),
-                                        my_id.clone(),
-                                        message,
-                                    );
-                                });
-                            }
+                        // Other type of message
+                        _ => {
+                            thread::spawn(move || {
+                                handle_other_messages(screen_ref_c.clone(), my_id.clone(), message);
+                            });
                         }
-                        read_buffer.clear(); // Clear the buffer
-                    }
-                    None => {
-                        //
                     }
                 }
             }
 
             Err(e) => {
-                let prev_screen_id = screen_ref_c.find_prev_screen(my_id);
-                // prev screen id es el que murio. Tengo que notificar su baja.
-                if let Some(dead_id) = prev_screen_id {
-                    println!("ID screen Muerto: {}", dead_id);
-                    screen.transfer_orders(dead_id, my_id);
-                    let deadscreen_msg = MessageType::DeadScreen(DeadScreen {
-                        sender_id: my_id,
-                        dead_screen_id: dead_id,
-                    });
+                recorder::record_closed();
+                handle_prev_screen_death(&screen, my_id);
+                log_error!("Failed to read from socket; err = {:?}", e);
+                break;
+            }
+        }
+    }
+}
+
+/// Aplica un pedido que circula por el anillo de pantallas y lo reenvía, salvo que sea de esta
+/// pantalla y ya haya dado la vuelta.
+pub fn handle_order_screen(screen: &Arc<Screen>, my_id: usize, message: MessageType) {
+    if let MessageType::OrderScreen(ref order) = message {
+        Screen::apply_order(screen.clone(), order);
+        if order.sender_id != my_id {
+            //println!("Recibido Order: {:?}", order);
+            screen.tx_sender_channel.send(message).unwrap();
+        }
+    }
+}
 
-                    let _ = tx_sender.send(deadscreen_msg);
+/// Maneja la caída de la pantalla anterior del anillo, de la que esta pantalla leía mensajes:
+/// toma sus pedidos, avisa la caída al resto del anillo y, si la caída era la líder, inicia la
+/// elección.
+pub fn handle_prev_screen_death(screen: &Arc<Screen>, my_id: usize) {
+    let prev_screen_id = screen.find_prev_screen(my_id);
+    // prev screen id es el que murio. Tengo que notificar su baja.
+    let Some(dead_id) = prev_screen_id else {
+        log_error!("No Value Found");
+        return;
+    };
+    log_warn!("ID screen Muerto: {}", dead_id);
+    screen.transfer_orders(dead_id, my_id);
+    let deadscreen_msg = MessageType::DeadScreen(DeadScreen {
+        sender_id: my_id,
+        dead_screen_id: dead_id,
+    });
 
-                    // update network
-                    Screen::update_network(screen.clone(), dead_id);
+    let _ = screen.tx_sender_channel.send(deadscreen_msg);
 
-                    // Si el id muerto es el lider, hay que elegir nuevo lider
-                    if screen_ref.is_leader(dead_id) {
-                        screen_ref.send_leader_election_message(my_id, dead_id);
-                    }
+    // update network
+    Screen::update_network(screen.clone(), dead_id);
 
-                    break;
-                } else {
-                    eprintln!("No Value Found");
-                }
-                eprintln!(
-                    "screen {}: Failed to read from socket; err = {:?}",
-                    my_id, e
-                );
-                break;
-            }
-        }
+    // Si el id muerto es el lider, hay que elegir nuevo lider
+    if screen.is_leader(dead_id) {
+        screen.send_leader_election_message(my_id, Some(dead_id));
     }
 }
 
@@ -187,47 +149,30 @@ pub fn handle_screen_connection(screen: Arc<Screen>, my_id: usize, mut socket: T
 ///
 /// Esta función lee mensajes desde el socket y los procesa según su tipo.
 ///
-pub fn handle_robot_connection(screen: Arc<Screen>, _my_id: usize, mut socket: TcpStream) {
-    let screen_ref = screen.clone();
+pub fn handle_robot_connection(screen: Arc<Screen>, _my_id: usize, mut socket: impl Read) {
+    recorder::set_peer(Peer::Robot);
     //let tx_sender = screen.tx_robot_sender_channel.clone();
     let tx_prepare = screen.tx_prepare_channel.clone();
-    let tx_ring_sender = screen.tx_sender_channel.clone();
     let mut buffer = vec![0; BUFFER_SIZE];
     let mut read_buffer = Vec::new();
     loop {
-        let _screen_ref_c = screen_ref.clone();
         match socket.read(&mut buffer) {
             Ok(0) => {
+                recorder::record_closed();
                 let _ = tx_prepare.send(MessageType::Kill());
                 break;
             }
             Ok(n) => {
                 read_buffer.extend_from_slice(&buffer[..n]);
-                match deserialize_message(&buffer) {
-                    Some(message) => {
-                        match message {
-                            MessageType::Commit(ref commit) => {
-                                Screen::commit_order(screen.clone(), commit);
-                                tx_ring_sender.send(message).unwrap();
-                            }
-                            MessageType::Abort(ref abort) => {
-                                Screen::abort_order(screen.clone(), abort);
-                                tx_ring_sender.send(message).unwrap();
-                            }
-                            // Other type of message
-                            _ => {}
-                        }
-                        read_buffer.clear(); // Clear the buffer
-                    }
-                    None => {
-                        //
-                    }
+                for message in take_messages(&mut read_buffer) {
+                    handle_robot_message(&screen, message);
                 }
             }
 
             Err(_e) => {
                 // Mato la conexion y mando mensaje para que se de cuenta
                 // de que se desconectó el socket
+                recorder::record_closed();
                 let _ = tx_prepare.send(MessageType::Kill());
                 break;
             }
@@ -235,6 +180,40 @@ pub fn handle_robot_connection(screen: Arc<Screen>, _my_id: usize, mut socket: T
     }
 }
 
+/// Procesa un mensaje recibido del robot líder: confirma o rechaza un pedido, o guarda la foto
+/// del stock, y lo reenvía al resto del anillo de pantallas.
+pub fn handle_robot_message(screen: &Arc<Screen>, message: MessageType) {
+    let tx_ring_sender = screen.tx_sender_channel.clone();
+    match message {
+        MessageType::Commit(ref commit) => {
+            Screen::commit_order(screen.clone(), commit);
+            tx_ring_sender.send(message).unwrap();
+        }
+        MessageType::Abort(ref abort) => {
+            Screen::abort_order(screen.clone(), abort);
+            tx_ring_sender.send(message).unwrap();
+        }
+        MessageType::StockSnapshot(ref snapshot) => {
+            screen.apply_stock_snapshot(snapshot);
+            tx_ring_sender.send(message).unwrap();
+        }
+        // Other type of message
+        _ => {}
+    }
+}
+
+/// Toma a `robot_id` como robot líder, lo avisa al resto del anillo e inicia el hilo que le
+/// envía los pedidos.
+pub fn accept_robot_leader(screen: &Arc<Screen>, robot_id: usize) {
+    // set new robot leader id y mandar a todos para que sepan
+    screen.set_new_robot_leader(robot_id);
+    let new_robot_leader_msg = MessageType::UpdateRobotLeader(robot_id.clone());
+    let _ = screen.tx_sender_channel.send(new_robot_leader_msg);
+
+    // new sender thread con el nuevo robot lider
+    screen.clone().connect_robot(true);
+}
+
 /// Función para procesar mensajes que no son de tipo "Order" provenientes de nodos screen.
 ///
 /// Esta función maneja varios tipos de mensajes recibidos de la red.
@@ -345,8 +324,20 @@ pub fn handle_other_messages(screen: Arc<Screen>, my_id: usize, message: Message
                 tx_sender.send(message).unwrap();
             }
         }
+        MessageType::StockSnapshot(ref snapshot) => {
+            if !screen.i_am_leader() {
+                screen.apply_stock_snapshot(snapshot);
+                tx_sender.send(message).unwrap();
+            }
+        }
+        MessageType::CancelOrder(ref cancel) => {
+            if cancel.sender_id != my_id {
+                screen.cancel_order(cancel);
+                tx_sender.send(message).unwrap();
+            }
+        }
         _ => {
-            println!("Recibi otra cosa... {:?}", message);
+            log_warn!("Recibi otra cosa... {:?}", message);
         }
     }
 }